@@ -0,0 +1,153 @@
+package main
+
+import (
+	"encoding/json"
+	"fmt"
+	"os"
+	"slices"
+	"sort"
+)
+
+// backupVersion is bumped whenever the backup format changes incompatibly.
+const backupVersion = 1
+
+// backupArchive is the single file written by `backup`: every profile,
+// decrypted, along with state.json.
+type backupArchive struct {
+	Version   int                 `json:"version"`
+	CreatedAt uint64              `json:"created_at"`
+	Profiles  map[string]*Profile `json:"profiles"`
+	State     State               `json:"state"`
+}
+
+func cmdBackup(args []string) error {
+	parsed, err := parseArgs(args)
+	if err != nil {
+		return err
+	}
+	if len(parsed.pos) != 1 {
+		return fmt.Errorf("backup requires an output file")
+	}
+	out := parsed.pos[0]
+
+	names, err := listProfiles()
+	if err != nil {
+		return err
+	}
+	archive := backupArchive{
+		Version:   backupVersion,
+		CreatedAt: nowMs(),
+		Profiles:  make(map[string]*Profile, len(names)),
+		State:     loadState(),
+	}
+	for _, name := range names {
+		profile, err := loadProfile(name)
+		if err != nil {
+			return err
+		}
+		archive.Profiles[name] = profile
+	}
+
+	data, err := json.MarshalIndent(archive, "", "  ")
+	if err != nil {
+		return err
+	}
+	if err := writeSecure(out, data); err != nil {
+		return err
+	}
+	logf("Backed up %d profile(s) to %s\n", len(names), out)
+	if archive.State.Locked {
+		fmt.Fprintln(os.Stderr, "Warning: the backup holds the profiles decrypted; keep it somewhere safe.")
+	}
+	return nil
+}
+
+// cmdRestore writes back the profiles of a backup, then their nicknames,
+// disabled flags and last-used times. Profiles that already exist are kept
+// unless --overwrite is given.
+func cmdRestore(args []string) error {
+	parsed, err := parseArgs(args, "--overwrite")
+	if err != nil {
+		return err
+	}
+	if len(parsed.pos) != 1 {
+		return fmt.Errorf("restore requires a backup file")
+	}
+	path := parsed.pos[0]
+
+	data, err := os.ReadFile(path)
+	if err != nil {
+		return err
+	}
+	var archive backupArchive
+	if err := json.Unmarshal(data, &archive); err != nil {
+		return fmt.Errorf("%s isn't a claude-switch backup: %w", path, err)
+	}
+	if archive.Version != backupVersion {
+		return fmt.Errorf("%s has backup version %d; this claude-switch reads version %d", path, archive.Version, backupVersion)
+	}
+
+	// Check everything before writing anything, so a bad entry can't leave
+	// a half-restored backup
+	names := make([]string, 0, len(archive.Profiles))
+	for name, profile := range archive.Profiles {
+		if err := validateProfileName(name); err != nil {
+			return fmt.Errorf("backup: %w", err)
+		}
+		if profile == nil {
+			return fmt.Errorf("backup: profile '%s' is empty", name)
+		}
+		if err := checkProfile(profile); err != nil {
+			return fmt.Errorf("backup: profile '%s' is unusable: %w", name, err)
+		}
+		names = append(names, name)
+	}
+	sort.Strings(names)
+
+	var restored []string
+	for _, name := range names {
+		if profileStored(name) && !parsed.has("--overwrite") {
+			logf("Skipped '%s': it already exists (use --overwrite to replace it)\n", name)
+			continue
+		}
+		if err := saveProfile(name, archive.Profiles[name]); err != nil {
+			return err
+		}
+		restored = append(restored, name)
+		printProfileSaved("restored", name, archive.Profiles[name])
+	}
+
+	state := loadState()
+	saved := archive.State
+	for _, name := range restored {
+		if nick, ok := saved.Nicknames[name]; ok {
+			if state.Nicknames == nil {
+				state.Nicknames = make(map[string]string)
+			}
+			state.Nicknames[name] = nick
+		}
+		if saved.Disabled[name] {
+			if state.Disabled == nil {
+				state.Disabled = make(map[string]bool)
+			}
+			state.Disabled[name] = true
+		}
+		if ts, ok := saved.LastUsed[name]; ok {
+			if state.LastUsed == nil {
+				state.LastUsed = make(map[string]uint64)
+			}
+			state.LastUsed[name] = ts
+		}
+	}
+	if err := saveState(&state); err != nil {
+		return err
+	}
+
+	logf("Restored %d of %d profile(s) from %s\n", len(restored), len(names), path)
+	// Claude's own credential files aren't part of the backup, so the
+	// active profile is left for the user to switch to
+	if active := saved.ActiveProfile; active != nil && slices.Contains(restored, *active) && !state.isActive(*active) {
+		logf("'%s' was active when the backup was made; run 'claude-switch use %s' to switch to it\n", *active, *active)
+	}
+	return nil
+}
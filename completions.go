@@ -0,0 +1,76 @@
+package main
+
+import (
+	"fmt"
+	"strings"
+)
+
+// completionCommands are the commands offered as the first word.
+var completionCommands = []string{
+	"add", "import", "import-dir", "import-url", "use", "back", "revert",
+	"list", "info", "show", "summary", "current", "whoami", "nick", "label", "rename",
+	"set-default", "auto", "disable", "enable", "remove", "prune", "trash", "refresh",
+	"rotate", "exec", "token", "env", "activate", "export", "prompt", "lock", "unlock",
+	"storage", "backup", "restore", "purge", "validate", "doctor", "completions",
+}
+
+// profileCommands take a profile name as their first argument, which the
+// scripts complete by calling `claude-switch __complete <prefix>`.
+var profileCommands = []string{
+	"use", "info", "show", "nick", "label", "set-default", "rename", "disable",
+	"enable", "remove", "refresh", "rotate", "exec", "token", "env", "activate",
+	"export",
+}
+
+const bashCompletion = `_claude_switch() {
+    local cur=${COMP_WORDS[COMP_CWORD]}
+    if [[ $COMP_CWORD -eq 1 ]]; then
+        COMPREPLY=($(compgen -W "%s" -- "$cur"))
+        return
+    fi
+    case ${COMP_WORDS[1]} in
+        %s)
+            if [[ $COMP_CWORD -eq 2 ]]; then
+                COMPREPLY=($(claude-switch __complete "$cur"))
+            fi
+            ;;
+    esac
+}
+complete -F _claude_switch claude-switch
+`
+
+const zshCompletion = `#compdef claude-switch
+_claude_switch() {
+    if (( CURRENT == 2 )); then
+        compadd -- %s
+    elif (( CURRENT == 3 )); then
+        case ${words[2]} in
+            %s) compadd -- ${(f)"$(claude-switch __complete)"} ;;
+        esac
+    fi
+}
+compdef _claude_switch claude-switch
+`
+
+const fishCompletion = `complete -c claude-switch -f
+complete -c claude-switch -n __fish_use_subcommand -a "%s"
+complete -c claude-switch -n "__fish_seen_subcommand_from %s" -a "(claude-switch __complete)"
+`
+
+func cmdCompletions(args []string) error {
+	if len(args) != 1 {
+		return fmt.Errorf("completions requires a shell: bash, zsh or fish")
+	}
+	commands := strings.Join(completionCommands, " ")
+	switch args[0] {
+	case "bash":
+		fmt.Printf(bashCompletion, commands, strings.Join(profileCommands, "|"))
+	case "zsh":
+		fmt.Printf(zshCompletion, commands, strings.Join(profileCommands, "|"))
+	case "fish":
+		fmt.Printf(fishCompletion, commands, strings.Join(profileCommands, " "))
+	default:
+		return fmt.Errorf("unsupported shell '%s': expected bash, zsh or fish", args[0])
+	}
+	return nil
+}
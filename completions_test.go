@@ -0,0 +1,49 @@
+package main
+
+import (
+	"slices"
+	"strings"
+	"testing"
+)
+
+// Every command in the usage text should complete, so a new command can't
+// be added without its completion.
+func TestCompletionsCoverUsage(t *testing.T) {
+	for _, line := range strings.Split(usage, "\n") {
+		rest, ok := strings.CutPrefix(line, "  ")
+		if !ok || rest == "" || rest[0] < 'a' || rest[0] > 'z' {
+			continue
+		}
+		for _, cmd := range strings.Split(strings.Fields(rest)[0], "|") {
+			if !slices.Contains(completionCommands, cmd) {
+				t.Errorf("%q is in the usage text but not in completionCommands", cmd)
+			}
+		}
+	}
+}
+
+func TestCompleteProfileNames(t *testing.T) {
+	e := newTestEnv(t)
+	if r := e.mustRun("__complete", "wo"); r.stdout != "" {
+		t.Errorf("__complete with no profiles printed %q, want nothing", r.stdout)
+	}
+
+	for _, name := range []string{"work", "work-2", "personal"} {
+		e.writeProfile(name, &Profile{Type: "oauth", Credentials: testCreds(name+"-access", name+"-refresh")})
+	}
+	tests := []struct {
+		prefix string
+		want   []string
+	}{
+		{"wo", []string{"work", "work-2"}},
+		{"work-", []string{"work-2"}},
+		{"", []string{"personal", "work", "work-2"}},
+		{"x", nil},
+	}
+	for _, tt := range tests {
+		got := strings.Fields(e.mustRun("__complete", tt.prefix).stdout)
+		if !slices.Equal(got, tt.want) {
+			t.Errorf("__complete %q = %q, want %q", tt.prefix, got, tt.want)
+		}
+	}
+}
@@ -0,0 +1,294 @@
+package main
+
+import (
+	"bufio"
+	"bytes"
+	"crypto/aes"
+	"crypto/cipher"
+	"crypto/pbkdf2"
+	"crypto/rand"
+	"crypto/sha256"
+	"encoding/base64"
+	"errors"
+	"fmt"
+	"os"
+	"os/exec"
+	"path/filepath"
+	"strings"
+	"sync"
+)
+
+// Locked profiles are stored as encryptedMagic followed by the base64 of
+// salt, nonce and AES-256-GCM ciphertext. The key comes from the passphrase
+// through PBKDF2-SHA256.
+const (
+	encryptedMagic = "claude-switch-encrypted-v1\n"
+	kdfIterations  = 600000
+	saltSize       = 16
+)
+
+var errWrongPassphrase = errors.New("wrong passphrase")
+
+// passphrase and derivedKeys cache what the user typed and what it derived,
+// so a command touching many profiles asks and derives only once per salt.
+// cryptMu guards them, as batch commands load profiles in parallel.
+var (
+	cryptMu     sync.Mutex
+	passphrase  *string
+	derivedKeys = make(map[string][]byte)
+	lockSalt    []byte
+)
+
+func isEncrypted(data []byte) bool {
+	return bytes.HasPrefix(data, []byte(encryptedMagic))
+}
+
+// readPassphrase returns the passphrase from CLAUDE_SWITCH_PASSPHRASE, or
+// asks for it on the terminal without echoing it.
+func readPassphrase(prompt string) (string, error) {
+	if p := os.Getenv("CLAUDE_SWITCH_PASSPHRASE"); p != "" {
+		return p, nil
+	}
+	if info, err := os.Stdin.Stat(); err != nil || info.Mode()&os.ModeCharDevice == 0 {
+		return "", fmt.Errorf("profiles are locked; set CLAUDE_SWITCH_PASSPHRASE to unlock them without a terminal")
+	}
+
+	fmt.Fprint(os.Stderr, prompt)
+	if setEcho(false) == nil {
+		defer func() {
+			setEcho(true)
+			fmt.Fprintln(os.Stderr)
+		}()
+	}
+	line, err := bufio.NewReader(os.Stdin).ReadString('\n')
+	if err != nil && line == "" {
+		return "", fmt.Errorf("reading passphrase: %w", err)
+	}
+	return strings.TrimRight(line, "\r\n"), nil
+}
+
+func setEcho(on bool) error {
+	arg := "-echo"
+	if on {
+		arg = "echo"
+	}
+	cmd := exec.Command("stty", arg)
+	cmd.Stdin = os.Stdin
+	return cmd.Run()
+}
+
+func sessionPassphrase() (string, error) {
+	if passphrase == nil {
+		p, err := readPassphrase("Passphrase for claude-switch profiles: ")
+		if err != nil {
+			return "", err
+		}
+		passphrase = &p
+	}
+	return *passphrase, nil
+}
+
+// keyFor returns the key for salt, asking for the passphrase if needed. A nil
+// salt picks this run's salt for new encryptions, returned alongside.
+func keyFor(salt []byte) ([]byte, []byte, error) {
+	cryptMu.Lock()
+	defer cryptMu.Unlock()
+
+	pass, err := sessionPassphrase()
+	if err != nil {
+		return nil, nil, err
+	}
+	// One salt per run keeps locking many profiles to a single derivation
+	if salt == nil {
+		if lockSalt == nil {
+			lockSalt = make([]byte, saltSize)
+			if _, err := rand.Read(lockSalt); err != nil {
+				return nil, nil, err
+			}
+		}
+		salt = lockSalt
+	}
+	if key, ok := derivedKeys[string(salt)]; ok {
+		return key, salt, nil
+	}
+	key, err := pbkdf2.Key(sha256.New, pass, salt, kdfIterations, 32)
+	if err != nil {
+		return nil, nil, err
+	}
+	derivedKeys[string(salt)] = key
+	return key, salt, nil
+}
+
+func encryptProfile(plain []byte) ([]byte, error) {
+	key, salt, err := keyFor(nil)
+	if err != nil {
+		return nil, err
+	}
+	gcm, err := newGCM(key)
+	if err != nil {
+		return nil, err
+	}
+	nonce := make([]byte, gcm.NonceSize())
+	if _, err := rand.Read(nonce); err != nil {
+		return nil, err
+	}
+
+	blob := append(append(append([]byte{}, salt...), nonce...), gcm.Seal(nil, nonce, plain, nil)...)
+	return []byte(encryptedMagic + base64.StdEncoding.EncodeToString(blob) + "\n"), nil
+}
+
+func decryptProfile(data []byte) ([]byte, error) {
+	blob, err := base64.StdEncoding.DecodeString(strings.TrimSpace(string(data[len(encryptedMagic):])))
+	if err != nil {
+		return nil, fmt.Errorf("corrupt encrypted profile: %w", err)
+	}
+	if len(blob) < saltSize {
+		return nil, fmt.Errorf("corrupt encrypted profile: too short")
+	}
+	key, _, err := keyFor(blob[:saltSize])
+	if err != nil {
+		return nil, err
+	}
+	gcm, err := newGCM(key)
+	if err != nil {
+		return nil, err
+	}
+	rest := blob[saltSize:]
+	if len(rest) < gcm.NonceSize() {
+		return nil, fmt.Errorf("corrupt encrypted profile: too short")
+	}
+	plain, err := gcm.Open(nil, rest[:gcm.NonceSize()], rest[gcm.NonceSize():], nil)
+	if err != nil {
+		return nil, errWrongPassphrase
+	}
+	return plain, nil
+}
+
+func newGCM(key []byte) (cipher.AEAD, error) {
+	block, err := aes.NewCipher(key)
+	if err != nil {
+		return nil, err
+	}
+	return cipher.NewGCM(block)
+}
+
+// rewriteProfiles passes every stored profile through fn, in whichever
+// backend holds it, along with the copies in the trash. fn returns nil for
+// data it leaves alone; rewriteProfiles returns how many it changed.
+func rewriteProfiles(fn func(data []byte) ([]byte, error)) (int, error) {
+	names, err := listProfiles()
+	if err != nil {
+		return 0, err
+	}
+	changed := 0
+	for _, name := range names {
+		data, err := readProfileData(name)
+		if err != nil {
+			return changed, fmt.Errorf("profile '%s': %w", name, err)
+		}
+		out, err := fn(data)
+		if err != nil {
+			return changed, fmt.Errorf("profile '%s': %w", name, err)
+		}
+		if out == nil {
+			continue
+		}
+		if err := storeProfileData(name, out, inKeyring(name)); err != nil {
+			return changed, err
+		}
+		changed++
+	}
+
+	trashed, err := listTrash()
+	if err != nil {
+		return changed, err
+	}
+	for _, t := range trashed {
+		path := filepath.Join(trashDir(), t.File)
+		data, err := os.ReadFile(path)
+		if err != nil {
+			return changed, err
+		}
+		out, err := fn(data)
+		if err != nil {
+			return changed, fmt.Errorf("%s: %w", path, err)
+		}
+		if out == nil {
+			continue
+		}
+		if err := writeSecure(path, out); err != nil {
+			return changed, err
+		}
+		changed++
+	}
+	return changed, nil
+}
+
+func cmdLock() error {
+	if err := requireWritable("lock"); err != nil {
+		return err
+	}
+	state := loadState()
+	if state.Locked {
+		return fmt.Errorf("profiles are already locked")
+	}
+
+	if os.Getenv("CLAUDE_SWITCH_PASSPHRASE") == "" {
+		first, err := readPassphrase("New passphrase: ")
+		if err != nil {
+			return err
+		}
+		if first == "" {
+			return fmt.Errorf("the passphrase can't be empty")
+		}
+		again, err := readPassphrase("Repeat passphrase: ")
+		if err != nil {
+			return err
+		}
+		if first != again {
+			return fmt.Errorf("passphrases don't match")
+		}
+		passphrase = &first
+	}
+
+	locked, err := rewriteProfiles(func(data []byte) ([]byte, error) {
+		if isEncrypted(data) {
+			return nil, nil
+		}
+		return encryptProfile(data)
+	})
+	if err != nil {
+		return err
+	}
+
+	state = loadState()
+	state.Locked = true
+	if err := saveState(&state); err != nil {
+		return err
+	}
+	logf("Encrypted %d profile(s); new profiles will be encrypted too\n", locked)
+	return nil
+}
+
+func cmdUnlock() error {
+	if err := requireWritable("unlock"); err != nil {
+		return err
+	}
+	unlocked, err := rewriteProfiles(func(data []byte) ([]byte, error) {
+		if !isEncrypted(data) {
+			return nil, nil
+		}
+		return decryptProfile(data)
+	})
+	if err != nil {
+		return err
+	}
+
+	state := loadState()
+	state.Locked = false
+	if err := saveState(&state); err != nil {
+		return err
+	}
+	logf("Decrypted %d profile(s)\n", unlocked)
+	return nil
+}
@@ -0,0 +1,103 @@
+package main
+
+import (
+	"bytes"
+	"errors"
+	"os"
+	"strings"
+	"testing"
+)
+
+// usePassphrase starts a fresh crypt session with pass, as a new run that
+// was given pass would.
+func usePassphrase(t *testing.T, pass string) {
+	t.Helper()
+	passphrase = &pass
+	derivedKeys = make(map[string][]byte)
+	lockSalt = nil
+	t.Cleanup(func() {
+		passphrase = nil
+		derivedKeys = make(map[string][]byte)
+		lockSalt = nil
+	})
+}
+
+func TestEncryptRoundTrip(t *testing.T) {
+	usePassphrase(t, "correct horse")
+	plain := mustJSON(&Profile{Type: "oauth", Credentials: testCreds("secret-access", "secret-refresh")})
+
+	sealed, err := encryptProfile(plain)
+	if err != nil {
+		t.Fatal(err)
+	}
+	if !isEncrypted(sealed) {
+		t.Fatalf("encryptProfile output doesn't start with the magic header: %q", sealed)
+	}
+	if bytes.Contains(sealed, []byte("secret-refresh")) {
+		t.Fatal("the sealed profile contains the refresh token in the clear")
+	}
+
+	// A later run with the same passphrase derives the key afresh
+	usePassphrase(t, "correct horse")
+	opened, err := decryptProfile(sealed)
+	if err != nil {
+		t.Fatal(err)
+	}
+	if !bytes.Equal(opened, plain) {
+		t.Errorf("decryptProfile = %s, want %s", opened, plain)
+	}
+}
+
+func TestDecryptWrongPassphrase(t *testing.T) {
+	usePassphrase(t, "correct horse")
+	sealed, err := encryptProfile([]byte(`{"type":"oauth"}`))
+	if err != nil {
+		t.Fatal(err)
+	}
+
+	usePassphrase(t, "battery staple")
+	if _, err := decryptProfile(sealed); !errors.Is(err, errWrongPassphrase) {
+		t.Errorf("decryptProfile with the wrong passphrase = %v, want errWrongPassphrase", err)
+	}
+}
+
+func TestDecryptCorrupt(t *testing.T) {
+	usePassphrase(t, "correct horse")
+	for _, data := range []string{
+		encryptedMagic + "not base64!\n",
+		encryptedMagic + "c2hvcnQ=\n", // "short", less than a salt
+	} {
+		if _, err := decryptProfile([]byte(data)); err == nil || !strings.Contains(err.Error(), "corrupt") {
+			t.Errorf("decryptProfile(%q) = %v, want a corrupt profile error", data, err)
+		}
+	}
+}
+
+func TestLockUnlock(t *testing.T) {
+	e := newTestEnv(t)
+	e.writeProfile("work", &Profile{Type: "oauth", Credentials: testCreds("work-access", "work-refresh")})
+	t.Setenv("CLAUDE_SWITCH_PASSPHRASE", "correct horse")
+
+	e.mustRun("lock")
+	data, err := os.ReadFile(e.profilePath("work"))
+	if err != nil {
+		t.Fatal(err)
+	}
+	if !isEncrypted(data) {
+		t.Fatalf("lock left the profile unencrypted:\n%s", data)
+	}
+	if out := e.mustRun("token", "work").stdout; strings.TrimSpace(out) != "work-access" {
+		t.Errorf("token on a locked profile printed %q, want work-access", out)
+	}
+
+	t.Setenv("CLAUDE_SWITCH_PASSPHRASE", "battery staple")
+	if r := e.run("", "token", "work"); r.code == 0 || !strings.Contains(r.stderr, "wrong passphrase") {
+		t.Errorf("token with the wrong passphrase exited with %d, stderr %q; want a wrong passphrase error", r.code, r.stderr)
+	}
+
+	t.Setenv("CLAUDE_SWITCH_PASSPHRASE", "correct horse")
+	e.mustRun("unlock")
+	if got := e.readProfile("work").Credentials.AccessToken; got != "work-access" {
+		t.Errorf("after unlock the profile holds access token %q, want work-access", got)
+	}
+}
@@ -0,0 +1,501 @@
+package main
+
+import (
+	"encoding/json"
+	"errors"
+	"fmt"
+	"io/fs"
+	"os"
+	"os/exec"
+	"path/filepath"
+	"runtime"
+	"strconv"
+	"strings"
+	"text/tabwriter"
+)
+
+type checkStatus int
+
+const (
+	checkOK checkStatus = iota
+	checkWarn
+	checkFail
+)
+
+func (s checkStatus) String() string {
+	switch s {
+	case checkOK:
+		return ansiGreen + "OK  " + ansiReset
+	case checkWarn:
+		return ansiYellow + "WARN" + ansiReset
+	}
+	return ansiRed + "FAIL" + ansiReset
+}
+
+// checkResult is one finding of `doctor`. fix is set for problems doctor
+// knows how to correct; it returns a description of what it changed.
+type checkResult struct {
+	status checkStatus
+	msg    string
+	fix    func() (string, error)
+}
+
+func passed(format string, a ...any) checkResult {
+	return checkResult{status: checkOK, msg: fmt.Sprintf(format, a...)}
+}
+
+// doctorChecks lists every check in the order doctor reports them.
+var doctorChecks = []func() []checkResult{
+	checkClaudeBinary,
+	checkClaudeVersion,
+	checkClaudePaths,
+	checkConfigDir,
+	checkStateFile,
+	checkProfilesLoad,
+	checkActiveProfile,
+	checkActiveToken,
+	checkOAuthConfig,
+	checkPermissions,
+	checkTempFiles,
+	checkExpiryUnits,
+	checkImplausibleExpiry,
+	checkTokenClaims,
+}
+
+func cmdDoctor(args []string) error {
+	parsed, err := parseArgs(args, "--fix")
+	if err != nil {
+		return err
+	}
+	if len(parsed.pos) > 0 {
+		return fmt.Errorf("unexpected argument: %s", parsed.pos[0])
+	}
+	fix := parsed.has("--fix")
+	if fix {
+		if err := requireWritable("doctor --fix"); err != nil {
+			return err
+		}
+	}
+
+	out := colourWriter(os.Stdout, stdoutColour())
+	problems := 0
+	for _, check := range doctorChecks {
+		for _, r := range check() {
+			fmt.Fprintf(out, "%s  %s\n", r.status, r.msg)
+			if r.status == checkOK {
+				continue
+			}
+			if !fix || r.fix == nil {
+				if r.status == checkFail {
+					problems++
+				}
+				continue
+			}
+			var done string
+			err := withLock(func() (err error) {
+				done, err = r.fix()
+				return err
+			})
+			if err != nil {
+				fmt.Printf("      fix failed: %v\n", err)
+				problems++
+				continue
+			}
+			fmt.Printf("      fixed: %s\n", done)
+		}
+	}
+
+	if problems > 0 {
+		return fmt.Errorf("doctor found %d problem(s) it could not fix", problems)
+	}
+	return nil
+}
+
+func checkClaudeBinary() []checkResult {
+	path, err := exec.LookPath(claudeBinary())
+	if err != nil {
+		return []checkResult{{
+			status: checkFail,
+			msg:    fmt.Sprintf("claude binary '%s' not found in PATH", claudeBinary()),
+		}}
+	}
+	return []checkResult{passed("claude binary: %s", path)}
+}
+
+func checkClaudeVersion() []checkResult {
+	v, err := claudeVersion()
+	if err != nil {
+		return []checkResult{{status: checkWarn, msg: fmt.Sprintf("couldn't detect the Claude Code version: %v", err)}}
+	}
+	if warning := claudeVersionWarning(); warning != "" {
+		return []checkResult{{status: checkWarn, msg: warning}}
+	}
+	return []checkResult{passed("Claude Code %s", v)}
+}
+
+// checkClaudePaths reports where claude-switch looks for Claude's own files,
+// which is the usual culprit when CLAUDE_CONFIG_DIR points somewhere else.
+func checkClaudePaths() []checkResult {
+	var results []checkResult
+	for _, path := range []string{claudeConfigDir(), credentialsPath(), claudeJSONPath()} {
+		info, err := os.Stat(path)
+		switch {
+		case errors.Is(err, fs.ErrNotExist):
+			results = append(results, checkResult{status: checkWarn, msg: fmt.Sprintf("%s doesn't exist (has Claude Code been logged in here?)", path)})
+		case err != nil:
+			results = append(results, checkResult{status: checkFail, msg: fmt.Sprintf("%s: %v", path, err)})
+		case info.IsDir():
+			if _, err := os.ReadDir(path); err != nil {
+				results = append(results, checkResult{status: checkFail, msg: fmt.Sprintf("%s isn't readable: %v", path, err)})
+			} else {
+				results = append(results, passed("%s", path))
+			}
+		default:
+			if f, err := os.Open(path); err != nil {
+				results = append(results, checkResult{status: checkFail, msg: fmt.Sprintf("%s isn't readable: %v", path, err)})
+			} else {
+				f.Close()
+				results = append(results, passed("%s", path))
+			}
+		}
+	}
+	return results
+}
+
+func checkConfigDir() []checkResult {
+	dir := configDir()
+	info, err := os.Stat(dir)
+	if errors.Is(err, fs.ErrNotExist) {
+		return []checkResult{passed("config directory %s (not created yet)", dir)}
+	}
+	if err != nil {
+		return []checkResult{{status: checkFail, msg: fmt.Sprintf("%s: %v", dir, err)}}
+	}
+	if !info.IsDir() {
+		return []checkResult{{status: checkFail, msg: notDirError(dir).Error()}}
+	}
+	return []checkResult{passed("config directory %s", dir)}
+}
+
+func checkStateFile() []checkResult {
+	path := statePath()
+	data, err := os.ReadFile(path)
+	if err != nil {
+		return []checkResult{passed("no state file yet")}
+	}
+	var state State
+	if err := json.Unmarshal(data, &state); err != nil {
+		return []checkResult{{
+			status: checkFail,
+			msg:    fmt.Sprintf("%s is corrupt: %v", path, err),
+			fix: func() (string, error) {
+				aside := path + ".corrupt-" + strconv.FormatUint(nowMs(), 10)
+				if err := os.Rename(path, aside); err != nil {
+					return "", explainWriteError(path, err)
+				}
+				return "moved it aside to " + aside, nil
+			},
+		}}
+	}
+	return []checkResult{passed("state file is valid")}
+}
+
+func checkActiveProfile() []checkResult {
+	state := loadState()
+	if state.ActiveProfile == nil {
+		return []checkResult{passed("no active profile")}
+	}
+	name := *state.ActiveProfile
+	if profileExists(name) {
+		return []checkResult{passed("active profile '%s' exists", name)}
+	}
+	return []checkResult{{
+		status: checkWarn,
+		msg:    fmt.Sprintf("active profile '%s' no longer exists", name),
+		fix: func() (string, error) {
+			state := loadState()
+			state.ActiveProfile = nil
+			if err := saveState(&state); err != nil {
+				return "", err
+			}
+			return "cleared the active profile", nil
+		},
+	}}
+}
+
+// checkProfilesLoad finds stored profiles that fail to load, which list only
+// shows as error rows.
+func checkProfilesLoad() []checkResult {
+	names, err := listProfiles()
+	if err != nil {
+		return []checkResult{{status: checkFail, msg: fmt.Sprintf("can't list profiles: %v", err)}}
+	}
+	var results []checkResult
+	for _, name := range names {
+		if _, err := loadProfile(name); err != nil {
+			results = append(results, checkResult{status: checkFail, msg: err.Error()})
+		}
+	}
+	if len(results) == 0 {
+		results = append(results, passed("%d profile(s) load cleanly", len(names)))
+	}
+	return results
+}
+
+// checkActiveToken warns when the active profile's token has expired. That
+// is harmless, as the next use refreshes it, but explains failing requests
+// from a Claude session that was left running.
+func checkActiveToken() []checkResult {
+	state := loadState()
+	if state.ActiveProfile == nil {
+		return nil
+	}
+	profile, err := loadProfile(*state.ActiveProfile)
+	if err != nil || profile.Credentials == nil {
+		return nil
+	}
+	if isExpired(profile.Credentials, expiryBuffer()) {
+		return []checkResult{{
+			status: checkWarn,
+			msg:    fmt.Sprintf("the active profile's token has expired; run 'claude-switch refresh %s'", *state.ActiveProfile),
+		}}
+	}
+	return []checkResult{passed("the active profile's token expires %s", formatTimestamp(profile.Credentials.ExpiresAt))}
+}
+
+// checkOAuthConfig reports overrides of the OAuth client settings, which
+// are easy to forget once Anthropic's defaults work again.
+func checkOAuthConfig() []checkResult {
+	cfg, err := oauthConfig()
+	if err != nil {
+		return []checkResult{{status: checkFail, msg: err.Error()}}
+	}
+	if cfg == (oauthSettings{defaultClientID, defaultTokenURL, defaultScopes}) {
+		return []checkResult{passed("OAuth client settings are the defaults")}
+	}
+	return []checkResult{{
+		status: checkWarn,
+		msg:    fmt.Sprintf("OAuth client settings are overridden: client %s, token URL %s, scopes %q", cfg.clientID, cfg.tokenURL, cfg.scopes),
+	}}
+}
+
+// checkPermissions looks for files holding tokens that other users can read.
+func checkPermissions() []checkResult {
+	if runtime.GOOS == "windows" {
+		return nil
+	}
+	paths := []string{statePath(), credentialsPath()}
+	names, _ := listProfiles()
+	for _, name := range names {
+		paths = append(paths, profilePath(name))
+	}
+
+	var results []checkResult
+	loose := 0
+	for _, path := range paths {
+		info, err := os.Stat(path)
+		if err != nil || info.Mode().Perm()&0o077 == 0 {
+			continue
+		}
+		loose++
+		results = append(results, checkResult{
+			status: checkWarn,
+			msg:    fmt.Sprintf("%s has mode %04o; other users can read it", path, info.Mode().Perm()),
+			fix: func() (string, error) {
+				if err := os.Chmod(path, 0o600); err != nil {
+					return "", explainWriteError(path, err)
+				}
+				return "set mode 0600 on " + filepath.Base(path), nil
+			},
+		})
+	}
+	if loose == 0 {
+		results = append(results, passed("credential files are private"))
+	}
+	return results
+}
+
+// checkTempFiles looks for temp files from writes that never finished. They
+// can hold a copy of a token, so fixing removes them rather than leaving
+// them around.
+func checkTempFiles() []checkResult {
+	orphans := orphanedTempFiles()
+	if len(orphans) == 0 {
+		return []checkResult{passed("no leftover temp files")}
+	}
+	var results []checkResult
+	for _, path := range orphans {
+		results = append(results, checkResult{
+			status: checkWarn,
+			msg:    fmt.Sprintf("%s is left over from an interrupted write", path),
+			fix: func() (string, error) {
+				if err := os.Remove(path); err != nil && !errors.Is(err, fs.ErrNotExist) {
+					return "", explainWriteError(path, err)
+				}
+				return "removed " + filepath.Base(path), nil
+			},
+		})
+	}
+	return results
+}
+
+// checkExpiryUnits finds profiles whose expiresAt is in seconds, which makes
+// the token look long expired and forces a refresh on every use.
+func checkExpiryUnits() []checkResult {
+	names, _ := listProfiles()
+	var results []checkResult
+	for _, name := range names {
+		profile, err := loadProfile(name)
+		if err != nil || profile.Credentials == nil {
+			continue
+		}
+		exp := profile.Credentials.ExpiresAt
+		if exp == 0 || normalizeMillis(exp) == exp {
+			continue
+		}
+		results = append(results, checkResult{
+			status: checkWarn,
+			msg:    fmt.Sprintf("profile '%s' stores expiresAt in seconds", name),
+			fix: func() (string, error) {
+				profile.Credentials.ExpiresAt = normalizeMillis(exp)
+				if err := saveProfile(name, profile); err != nil {
+					return "", err
+				}
+				return fmt.Sprintf("converted '%s' expiresAt to milliseconds", name), nil
+			},
+		})
+	}
+	if len(results) == 0 {
+		results = append(results, passed("token expiry timestamps are in milliseconds"))
+	}
+	return results
+}
+
+// checkImplausibleExpiry finds profiles claiming to expire further out than
+// any real token does. isExpired would treat such a token as fresh forever,
+// so it is never refreshed and Claude fails once the server expires it.
+func checkImplausibleExpiry() []checkResult {
+	names, _ := listProfiles()
+	var results []checkResult
+	for _, name := range names {
+		profile, err := loadProfile(name)
+		if err != nil || profile.Credentials == nil || !implausibleExpiry(profile.Credentials) {
+			continue
+		}
+		results = append(results, checkResult{
+			status: checkWarn,
+			msg:    fmt.Sprintf("profile '%s' claims to expire %s, which is implausibly far away; re-import it", name, formatTimestamp(normalizeMillis(profile.Credentials.ExpiresAt))),
+		})
+	}
+	if len(results) == 0 {
+		results = append(results, passed("no token claims an implausibly distant expiry"))
+	}
+	return results
+}
+
+// checkTokenClaims compares the stored expiry with the one a JWT access token
+// carries itself. Opaque tokens have nothing to compare and are skipped.
+func checkTokenClaims() []checkResult {
+	names, _ := listProfiles()
+	var results []checkResult
+	for _, name := range names {
+		profile, err := loadProfile(name)
+		if err != nil || profile.Credentials == nil {
+			continue
+		}
+		claimed, mismatch := claimedExpiry(profile.Credentials)
+		if !mismatch {
+			continue
+		}
+		results = append(results, checkResult{
+			status: checkWarn,
+			msg:    fmt.Sprintf("profile '%s' stores an expiry that its access token disagrees with (token says %s)", name, formatTimestamp(claimed)),
+			fix: func() (string, error) {
+				profile.Credentials.ExpiresAt = claimed
+				if err := saveProfile(name, profile); err != nil {
+					return "", err
+				}
+				return fmt.Sprintf("set '%s' expiresAt from its token", name), nil
+			},
+		})
+	}
+	if len(results) == 0 {
+		results = append(results, passed("stored expiries agree with the tokens"))
+	}
+	return results
+}
+
+// profileProblems lists everything wrong with a stored profile that can be
+// seen without the network.
+func profileProblems(name string) []string {
+	profile, err := loadProfile(name)
+	if err != nil {
+		return []string{err.Error()}
+	}
+	var problems []string
+	if err := checkProfile(profile); err != nil {
+		problems = append(problems, err.Error())
+	}
+
+	c := profile.Credentials
+	if profile.Type != "oauth" || c == nil {
+		return problems
+	}
+	switch {
+	case c.ExpiresAt == 0:
+		// Unknown rather than wrong: settleUnknownExpiry fills it in on use
+	case normalizeMillis(c.ExpiresAt) != c.ExpiresAt:
+		problems = append(problems, "expiresAt is in seconds (doctor --fix converts it)")
+	case implausibleExpiry(c):
+		problems = append(problems, "expiresAt is implausibly far in the future")
+	}
+	if c.LacksInference() {
+		problems = append(problems, "scopes lack user:inference")
+	}
+	for _, key := range []string{"accountUuid", "organizationUuid"} {
+		if v := accountField(profile.Account, key); v != "" && !isUUID(v) {
+			problems = append(problems, fmt.Sprintf("%s '%s' isn't a UUID", key, escapeControl(v)))
+		}
+	}
+	return problems
+}
+
+// cmdValidate checks every profile offline and fails if any is invalid, as a
+// pre-flight before a backup or migration.
+func cmdValidate(args []string) error {
+	parsed, err := parseArgs(args)
+	if err != nil {
+		return err
+	}
+	if len(parsed.pos) > 0 {
+		return fmt.Errorf("unexpected argument: %s", parsed.pos[0])
+	}
+	names, err := listProfiles()
+	if err != nil {
+		return err
+	}
+	if len(names) == 0 {
+		logf("No profiles to validate.\n")
+		return nil
+	}
+
+	tw := tabwriter.NewWriter(os.Stdout, 0, 0, 2, ' ', 0)
+	w := colourWriter(tw, stdoutColour())
+	fmt.Fprintf(w, "%s\t%s\t%s\n", ansiBold+"NAME"+ansiReset, ansiBold+"RESULT"+ansiReset, ansiBold+"PROBLEMS"+ansiReset)
+	invalid := 0
+	for _, name := range names {
+		problems := profileProblems(name)
+		result := ansiGreen + "pass" + ansiReset
+		if len(problems) > 0 {
+			result = ansiRed + "fail" + ansiReset
+			invalid++
+		}
+		fmt.Fprintf(w, "%s\t%s\t%s\n", escapeControl(name), result, strings.Join(problems, "; "))
+	}
+	tw.Flush()
+
+	if invalid > 0 {
+		return fmt.Errorf("%d of %d profile(s) are invalid", invalid, len(names))
+	}
+	return nil
+}
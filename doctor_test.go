@@ -0,0 +1,84 @@
+package main
+
+import (
+	"os"
+	"path/filepath"
+	"slices"
+	"strings"
+	"testing"
+)
+
+func TestProfileProblemsScopes(t *testing.T) {
+	e := newTestEnv(t)
+	noScopes := testCreds("access", "refresh")
+	noScopes.Scopes = nil
+	e.writeProfile("unknown", &Profile{Type: "oauth", Credentials: noScopes})
+	noInference := testCreds("access", "refresh")
+	noInference.Scopes = []string{"user:profile"}
+	e.writeProfile("lacking", &Profile{Type: "oauth", Credentials: noInference})
+
+	if problems := profileProblems("unknown"); len(problems) != 0 {
+		t.Errorf("a profile with no scopes recorded has problems %q; its scopes are unknown, not lacking", problems)
+	}
+	if problems := profileProblems("lacking"); !slices.Contains(problems, "scopes lack user:inference") {
+		t.Errorf("profileProblems = %q, want the missing user:inference scope reported", problems)
+	}
+}
+
+func TestDoctorWarnsAboutImplausibleExpiry(t *testing.T) {
+	e := newTestEnv(t)
+	e.writeProfile("work", &Profile{Type: "oauth", Credentials: testCreds("work-access", "work-refresh")})
+	distant := testCreds("far-access", "far-refresh")
+	distant.ExpiresAt = nowMs() + 365*24*60*60*1000
+	e.writeProfile("far", &Profile{Type: "oauth", Credentials: distant})
+
+	results := checkImplausibleExpiry()
+	if len(results) != 1 || results[0].status != checkWarn || !strings.Contains(results[0].msg, "'far'") {
+		t.Fatalf("checkImplausibleExpiry = %+v, want one warning about 'far'", results)
+	}
+	r := e.run("", "doctor")
+	if !strings.Contains(r.stdout, "profile 'far' claims to expire") {
+		t.Errorf("doctor didn't report the implausible expiry:\n%s", r.stdout)
+	}
+}
+
+func TestDoctorFix(t *testing.T) {
+	e := newTestEnv(t)
+	statePath := filepath.Join(e.dataDir, "state.json")
+	e.writeFile(statePath, []byte("{corrupt"))
+	if r := e.run("", "doctor"); r.code == 0 || !strings.Contains(r.stdout, "FAIL  "+statePath+" is corrupt") {
+		t.Errorf("doctor with a corrupt state file exited with %d:\n%s", r.code, r.stdout)
+	}
+	e.mustRun("doctor", "--fix")
+	if _, err := os.Stat(statePath); err == nil {
+		t.Error("doctor --fix left the corrupt state file in place")
+	}
+
+	secs := testCreds("work-access", "work-refresh")
+	secs.ExpiresAt /= 1000
+	e.writeProfile("work", &Profile{Type: "oauth", Credentials: secs})
+	if err := os.Chmod(e.profilePath("work"), 0o644); err != nil {
+		t.Fatal(err)
+	}
+	gone := "gone"
+	e.writeState(State{ActiveProfile: &gone})
+
+	r := e.mustRun("doctor", "--fix")
+	for _, want := range []string{"fixed: cleared the active profile", "fixed: set mode 0600 on work.json", "fixed: converted 'work' expiresAt to milliseconds"} {
+		if !strings.Contains(r.stdout, want) {
+			t.Errorf("doctor --fix output is missing %q:\n%s", want, r.stdout)
+		}
+	}
+	if state := e.readState(); state.ActiveProfile != nil {
+		t.Errorf("the missing active profile %q is still set", *state.ActiveProfile)
+	}
+	if info, err := os.Stat(e.profilePath("work")); err != nil || info.Mode().Perm() != 0o600 {
+		t.Errorf("after doctor --fix, work.json has mode %v (%v), want 0600", info, err)
+	}
+	if got := e.readProfile("work").Credentials.ExpiresAt; got != secs.ExpiresAt*1000 {
+		t.Errorf("after doctor --fix, expiresAt = %d, want %d", got, secs.ExpiresAt*1000)
+	}
+	if r := e.mustRun("doctor"); strings.Contains(r.stdout, "FAIL") {
+		t.Errorf("doctor still fails after fixing:\n%s", r.stdout)
+	}
+}
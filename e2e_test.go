@@ -0,0 +1,1058 @@
+package main
+
+import (
+	"encoding/json"
+	"fmt"
+	"io/fs"
+	"maps"
+	"net/http"
+	"os"
+	"path/filepath"
+	"slices"
+	"strings"
+	"sync"
+	"testing"
+	"time"
+)
+
+func TestImportUseListRemove(t *testing.T) {
+	e := newTestEnv(t)
+
+	work := testCreds("work-access", "work-refresh")
+	e.login(work, testAccount("me@acme.com", "11111111-1111-1111-1111-111111111111"))
+	e.mustRun("import", "work")
+
+	personal := testCreds("personal-access", "personal-refresh")
+	e.login(personal, testAccount("me@example.com", "22222222-2222-2222-2222-222222222222"))
+	e.mustRun("import", "personal")
+
+	if got := e.readProfile("work").DisplayEmail(); got != "me@acme.com" {
+		t.Errorf("work profile email = %q, want me@acme.com", got)
+	}
+
+	e.mustRun("use", "work")
+	if got := e.liveCredentials().AccessToken; got != "work-access" {
+		t.Errorf("after use work, Claude's access token = %q, want work-access", got)
+	}
+
+	out := e.mustRun("list").stdout
+	for _, want := range []string{"work", "personal", "me@acme.com", "me@example.com"} {
+		if !strings.Contains(out, want) {
+			t.Errorf("list output is missing %q:\n%s", want, out)
+		}
+	}
+
+	e.mustRun("remove", "personal")
+	if e.hasProfile("personal") {
+		t.Error("personal is still stored after remove")
+	}
+	if out := e.mustRun("list").stdout; strings.Contains(out, "personal") {
+		t.Errorf("list still shows the removed profile:\n%s", out)
+	}
+}
+
+func TestImportRejectsPartialCredentials(t *testing.T) {
+	e := newTestEnv(t)
+	partial := testCreds("work-access", "")
+	e.login(partial, testAccount("me@acme.com", "11111111-1111-1111-1111-111111111111"))
+
+	r := e.run("", "import", "work")
+	if r.code == 0 || !strings.Contains(r.stderr, "OAuth credentials are missing refreshToken") {
+		t.Errorf("import of credentials without a refresh token exited with %d (stderr %q), want them refused", r.code, r.stderr)
+	}
+	if e.hasProfile("work") {
+		t.Error("import saved a partially written login")
+	}
+
+	e.login(testCreds("work-access", "work-refresh"), testAccount("me@acme.com", "11111111-1111-1111-1111-111111111111"))
+	e.mustRun("import", "work")
+}
+
+func TestAddRunsClaudeLogin(t *testing.T) {
+	e := newTestEnv(t)
+	e.login(testCreds("old-access", "old-refresh"), testAccount("old@example.com", "33333333-3333-3333-3333-333333333333"))
+	e.mustRun("import", "old")
+
+	e.stubLogin(testCreds("new-access", "new-refresh"), testAccount("new@example.com", "44444444-4444-4444-4444-444444444444"))
+	e.mustRun("add", "spare", "--no-activate")
+
+	if got := e.readProfile("spare").Credentials.AccessToken; got != "new-access" {
+		t.Errorf("added profile's access token = %q, want new-access", got)
+	}
+	if got := e.liveCredentials().AccessToken; got != "old-access" {
+		t.Errorf("with --no-activate, Claude's access token = %q, want old-access back", got)
+	}
+}
+
+func TestAddExpectEmail(t *testing.T) {
+	e := newTestEnv(t)
+	e.stubLogin(testCreds("new-access", "new-refresh"), testAccount("new@example.com", "44444444-4444-4444-4444-444444444444"))
+
+	r := e.run("", "add", "spare", "--expect-email", "other@example.com")
+	if r.code == 0 || !strings.Contains(r.stderr, "logged in as new@example.com, expected other@example.com") {
+		t.Errorf("add with the wrong --expect-email exited with %d (stderr %q), want a failure naming both emails", r.code, r.stderr)
+	}
+	if e.hasProfile("spare") {
+		t.Error("add saved a login for the wrong account")
+	}
+
+	// The comparison ignores case, as email addresses do
+	e.mustRun("add", "spare", "--expect-email", "NEW@example.com")
+	if !e.hasProfile("spare") {
+		t.Error("add didn't save a login matching --expect-email")
+	}
+}
+
+func TestImportDir(t *testing.T) {
+	e := newTestEnv(t)
+	dir := filepath.Join(e.root, "configs")
+	for _, name := range []string{"work", "taken"} {
+		credentials, claudeJSON := claudeFiles(testCreds(name+"-access", name+"-refresh"), testAccount(name+"@example.com", "11111111-1111-1111-1111-111111111111"))
+		e.writeFile(filepath.Join(dir, name, ".credentials.json"), credentials)
+		e.writeFile(filepath.Join(dir, name, ".claude.json"), claudeJSON)
+	}
+	if err := os.MkdirAll(filepath.Join(dir, "empty"), 0o700); err != nil {
+		t.Fatal(err)
+	}
+	e.writeFile(filepath.Join(dir, "notes.txt"), []byte("not a config"))
+	e.writeProfile("old-taken", &Profile{Type: "oauth", Credentials: testCreds("kept-access", "kept-refresh")})
+
+	r := e.mustRun("import-dir", dir, "--prefix", "old-")
+	if !strings.Contains(r.stderr, "Imported 1 profile(s)") {
+		t.Errorf("import-dir reported:\n%s\nwant 1 profile imported", r.stderr)
+	}
+	if p := e.readProfile("old-work"); p.Credentials.AccessToken != "work-access" || p.DisplayEmail() != "work@example.com" {
+		t.Errorf("old-work holds %s for %s, want work-access for work@example.com", p.Credentials.AccessToken, p.DisplayEmail())
+	}
+	if got := e.readProfile("old-taken").Credentials.AccessToken; got != "kept-access" {
+		t.Errorf("import-dir overwrote an existing profile with %s", got)
+	}
+	for _, name := range []string{"old-empty", "old-notes.txt"} {
+		if e.hasProfile(name) {
+			t.Errorf("import-dir made a profile %s from something that isn't a Claude config", name)
+		}
+	}
+}
+
+func TestNoActivate(t *testing.T) {
+	e := newTestEnv(t)
+	e.login(testCreds("work-access", "work-refresh"), testAccount("work@example.com", "11111111-1111-1111-1111-111111111111"))
+	e.mustRun("import", "work")
+	if state := e.readState(); state.ActiveProfile == nil || *state.ActiveProfile != "work" {
+		t.Fatalf("import didn't make work active: %+v", state)
+	}
+
+	e.login(testCreds("personal-access", "personal-refresh"), testAccount("personal@example.com", "22222222-2222-2222-2222-222222222222"))
+	e.mustRun("import", "personal", "--no-activate")
+	if state := e.readState(); *state.ActiveProfile != "work" {
+		t.Errorf("after import --no-activate, the active profile is %s, want work", *state.ActiveProfile)
+	}
+
+	// With nothing active before, there is no login to put back
+	e2 := newTestEnv(t)
+	e2.stubLogin(testCreds("new-access", "new-refresh"), testAccount("new@example.com", "33333333-3333-3333-3333-333333333333"))
+	r := e2.mustRun("add", "spare", "--no-activate")
+	if !strings.Contains(r.stderr, "No profile was active before") {
+		t.Errorf("add --no-activate with nothing active reported:\n%s", r.stderr)
+	}
+	if got := e2.liveCredentials().AccessToken; got != "new-access" {
+		t.Errorf("Claude's access token = %q, want the new login kept", got)
+	}
+}
+
+func TestAddCaptureOnly(t *testing.T) {
+	e := newTestEnv(t)
+	e.login(testCreds("old-access", "old-refresh"), testAccount("old@example.com", "11111111-1111-1111-1111-111111111111"))
+	checkOldLogin := func(when string) {
+		t.Helper()
+		if got := e.liveCredentials(); got == nil || got.AccessToken != "old-access" {
+			t.Errorf("%s, Claude's credentials are %+v, want old-access back", when, got)
+		}
+		var doc struct {
+			OAuthAccount json.RawMessage `json:"oauthAccount"`
+		}
+		data, _ := os.ReadFile(filepath.Join(e.home, ".claude.json"))
+		json.Unmarshal(data, &doc)
+		if got := accountField(doc.OAuthAccount, "emailAddress"); got != "old@example.com" {
+			t.Errorf("%s, Claude's account is %q, want old@example.com back", when, got)
+		}
+	}
+
+	// A failed login puts the previous one back too
+	if r := e.run("", "add", "spare", "--capture-only"); r.code == 0 {
+		t.Fatal("add succeeded without a login")
+	}
+	checkOldLogin("after a failed add --capture-only")
+
+	e.stubLogin(testCreds("new-access", "new-refresh"), testAccount("new@example.com", "22222222-2222-2222-2222-222222222222"))
+	e.mustRun("add", "spare", "--capture-only")
+	if got := e.readProfile("spare").Credentials.AccessToken; got != "new-access" {
+		t.Errorf("captured profile's access token = %q, want new-access", got)
+	}
+	checkOldLogin("after add --capture-only")
+	if _, err := os.Stat(filepath.Join(e.dataDir, "state.json")); err == nil {
+		if active := e.readState().ActiveProfile; active != nil {
+			t.Errorf("add --capture-only made %s active", *active)
+		}
+	}
+}
+
+func TestAddWithAPIKeyLogin(t *testing.T) {
+	e := newTestEnv(t)
+	e.stubAPIKeyLogin("sk-ant-api03-test")
+
+	e.mustRun("add", "ci")
+
+	p := e.readProfile("ci")
+	if p.Type != "api_key" || p.ApiKey != "sk-ant-api03-test" {
+		t.Errorf("add saved a %s profile with key %q, want an api_key profile with sk-ant-api03-test", p.Type, p.ApiKey)
+	}
+	if p.Credentials != nil {
+		t.Errorf("the API key profile also holds OAuth credentials: %+v", p.Credentials)
+	}
+}
+
+func TestMissingProfilesDirectoryIsEmpty(t *testing.T) {
+	e := newTestEnv(t)
+	if r := e.mustRun("list"); !strings.Contains(r.stderr, "No profiles") {
+		t.Errorf("list on a fresh install reported:\n%s", r.stderr)
+	}
+	if r := e.mustRun("list", "--json"); strings.TrimSpace(r.stdout) != "[]" {
+		t.Errorf("list --json on a fresh install printed %q, want []", r.stdout)
+	}
+	for _, args := range [][]string{{"use", "work"}, {"remove", "work"}, {"info", "work"}} {
+		if r := e.run("", args...); r.code != exitNotFound {
+			t.Errorf("%v on a fresh install exited with %d, want %d\nstderr: %s", args, r.code, exitNotFound, r.stderr)
+		}
+	}
+}
+
+func TestProfilesDirectoryThatIsAFile(t *testing.T) {
+	e := newTestEnv(t)
+	profiles := filepath.Join(e.dataDir, "profiles")
+	e.writeFile(profiles, []byte("not a directory"))
+	e.login(testCreds("work-access", "work-refresh"), testAccount("me@acme.com", "11111111-1111-1111-1111-111111111111"))
+
+	want := profiles + " should be a directory but is a file"
+	for _, args := range [][]string{{"list"}, {"import", "work"}} {
+		if r := e.run("", args...); r.code == 0 || !strings.Contains(r.stderr, want) {
+			t.Errorf("%v with a file in place of the profiles directory exited with %d (stderr %q), want %q", args, r.code, r.stderr, want)
+		}
+	}
+}
+
+func TestInfo(t *testing.T) {
+	e := newTestEnv(t)
+	e.writeProfile("work", &Profile{
+		Type:           "oauth",
+		Credentials:    testCreds("work-access-token", "work-refresh-token"),
+		Account:        testAccount("me@acme.com", "11111111-1111-1111-1111-111111111111"),
+		DefaultCommand: []string{"claude", "--resume"},
+	})
+	e.mustRun("use", "work")
+
+	out := e.mustRun("info", "work").stdout
+	for _, want := range []string{"Name", "work", "Active", "true", "me@acme.com", "claude --resume", "work-acc…"} {
+		if !strings.Contains(out, want) {
+			t.Errorf("info output is missing %q:\n%s", want, out)
+		}
+	}
+	if strings.Contains(out, "work-access-token") || strings.Contains(out, "work-refresh-token") {
+		t.Errorf("info printed a token unmasked:\n%s", out)
+	}
+
+	var info profileInfo
+	if err := json.Unmarshal([]byte(e.mustRun("info", "work", "--json", "--show-secrets").stdout), &info); err != nil {
+		t.Fatal(err)
+	}
+	if !info.Active || info.AccessToken != "work-access-token" || info.RefreshToken != "work-refresh-token" || info.LastUsed == nil {
+		t.Errorf("info --json --show-secrets = %+v, want the active profile with its last use and full tokens", info)
+	}
+}
+
+func TestSummary(t *testing.T) {
+	e := newTestEnv(t)
+	var empty profileSummary
+	if err := json.Unmarshal([]byte(e.mustRun("summary", "--json").stdout), &empty); err != nil {
+		t.Fatal(err)
+	}
+	if empty.Active != nil || empty.Profiles != 0 || empty.NeedsReauth == nil {
+		t.Errorf("summary --json with no profiles = %+v, want no active profile and an empty needs_reauth list", empty)
+	}
+
+	e.writeProfile("work", &Profile{Type: "oauth", Credentials: testCreds("work-access", "work-refresh")})
+	expired := testCreds("old-access", "old-refresh")
+	expired.ExpiresAt = 1
+	e.writeProfile("stale", &Profile{Type: "oauth", Credentials: expired})
+	noInference := testCreds("weak-access", "weak-refresh")
+	noInference.Scopes = []string{"user:profile"}
+	e.writeProfile("weak", &Profile{Type: "oauth", Credentials: noInference})
+	e.writeProfile("ci", &Profile{Type: "api_key", ApiKey: "sk-ant-api03-test"})
+	e.writeFile(e.profilePath("broken"), []byte("{not a profile"))
+	e.mustRun("use", "work")
+
+	r := e.mustRun("summary", "--json")
+	if lines := strings.Count(strings.TrimSpace(r.stdout), "\n"); lines != 0 {
+		t.Errorf("summary --json printed %d lines, want a single object:\n%s", lines+1, r.stdout)
+	}
+	var got profileSummary
+	if err := json.Unmarshal([]byte(r.stdout), &got); err != nil {
+		t.Fatal(err)
+	}
+	want := profileSummary{Profiles: 5, OAuth: 3, ApiKey: 1, Expired: 1, Invalid: 1, NeedsReauth: []string{"weak"}}
+	if got.Active == nil || *got.Active != "work" {
+		t.Errorf("summary reports active %v, want work", got.Active)
+	}
+	got.Active = nil
+	if fmt.Sprint(got) != fmt.Sprint(want) {
+		t.Errorf("summary --json = %+v, want %+v", got, want)
+	}
+	if out := e.mustRun("summary").stdout; !strings.Contains(out, "Profiles: 5 (3 OAuth, 1 API key, 1 invalid)") {
+		t.Errorf("summary printed:\n%s", out)
+	}
+}
+
+func TestPurgeKeepsUnknownFiles(t *testing.T) {
+	e := newTestEnv(t)
+	e.login(testCreds("access", "refresh"), testAccount("me@example.com", "55555555-5555-5555-5555-555555555555"))
+	e.mustRun("import", "work")
+	unrelated := filepath.Join(e.dataDir, "notes.txt")
+	e.writeFile(unrelated, []byte("not claude-switch's"))
+
+	e.mustRun("purge", "--yes")
+
+	if e.hasProfile("work") {
+		t.Error("purge left the profile behind")
+	}
+	if _, err := os.Stat(filepath.Join(e.dataDir, "state.json")); !os.IsNotExist(err) {
+		t.Errorf("purge left state.json behind (stat: %v)", err)
+	}
+	if _, err := os.Stat(unrelated); err != nil {
+		t.Errorf("purge removed a file it doesn't own: %v", err)
+	}
+}
+
+func TestRefreshTakesTokensSavedByAnotherRun(t *testing.T) {
+	e := newTestEnv(t)
+	e.tokenServer(func(w http.ResponseWriter, r *http.Request) {
+		t.Error("refreshed a token another run had already refreshed")
+		http.Error(w, `{"error":"invalid_grant"}`, http.StatusBadRequest)
+	})
+	stale := testCreds("old-access", "old-refresh")
+	stale.ExpiresAt = 1
+	e.writeProfile("work", &Profile{Type: "oauth", Credentials: stale})
+
+	// Hold the lock as another run would, then hand the profile over with
+	// the tokens that run got
+	lock, err := os.OpenFile(filepath.Join(e.dataDir, "lock"), os.O_RDWR|os.O_CREATE, 0o600)
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer lock.Close()
+	if ok, err := tryLock(lock); !ok || err != nil {
+		t.Fatalf("taking the lock: %v", err)
+	}
+	done := make(chan result)
+	go func() { done <- e.run("", "token", "work") }()
+	time.Sleep(500 * time.Millisecond)
+	e.writeProfile("work", &Profile{Type: "oauth", Credentials: testCreds("new-access", "new-refresh")})
+	lock.Close()
+
+	r := <-done
+	if r.code != 0 || strings.TrimSpace(r.stdout) != "new-access" {
+		t.Errorf("token printed %q (exit %d, stderr %q), want the other run's new-access", r.stdout, r.code, r.stderr)
+	}
+}
+
+func TestDurationsTooLargeForATimerAreRejected(t *testing.T) {
+	e := newTestEnv(t)
+	e.writeProfile("work", &Profile{Type: "oauth", Credentials: testCreds("work-access", "work-refresh")})
+	for _, args := range [][]string{
+		{"list", "--watch", "--interval", "9223372036854776s"},
+		{"list", "--watch", "--refresh", "--refresh-every", "9223372036854776s"},
+		{"exec", "--timeout", "9223372036854776s", "work", "--", "true"},
+		{"use", "work", "--for", "9223372036854776s"},
+	} {
+		r := e.run("", args...)
+		if r.code != exitError || !strings.Contains(r.stderr, "too large") {
+			t.Errorf("%v exited with %d (stderr %q), want %d and 'too large'", args, r.code, r.stderr, exitError)
+		}
+	}
+}
+
+func TestWatchRejectsZeroIntervals(t *testing.T) {
+	e := newTestEnv(t)
+	for _, args := range [][]string{
+		{"list", "--watch", "--interval", "0"},
+		{"list", "--watch", "--refresh", "--refresh-every", "0"},
+		{"list", "--watch", "--refresh", "--refresh-every", "30s"},
+	} {
+		if r := e.run("", args...); r.code != exitError {
+			t.Errorf("%v exited with %d, want %d for an interval below the minimum", args, r.code, exitError)
+		}
+	}
+}
+
+func TestStrictJSONRejectsUnknownFields(t *testing.T) {
+	e := newTestEnv(t)
+	var doc map[string]any
+	json.Unmarshal(mustJSON(&Profile{Type: "oauth", Credentials: testCreds("work-access", "work-refresh")}), &doc)
+	doc["lable"] = "typo for label"
+	e.writeFile(e.profilePath("work"), mustJSON(doc))
+
+	if r := e.mustRun("token", "work"); strings.TrimSpace(r.stdout) != "work-access" {
+		t.Errorf("token printed %q, want work-access with the unknown field ignored", r.stdout)
+	}
+	r := e.run("", "--strict-json", "token", "work")
+	if r.code == 0 || !strings.Contains(r.stderr, "strict parsing failed") || !strings.Contains(r.stderr, "lable") {
+		t.Errorf("--strict-json token exited with %d (stderr %q), want a failure naming the unknown field", r.code, r.stderr)
+	}
+}
+
+func TestRefreshWarnsWhenTheSubjectChanges(t *testing.T) {
+	for _, tt := range []struct {
+		newSub string
+		warn   bool
+	}{
+		{"user-a", false},
+		{"user-b", true},
+	} {
+		e := newTestEnv(t)
+		stale := testCreds(testJWT(map[string]any{"sub": "user-a", "exp": 1}), "work-refresh")
+		stale.ExpiresAt = 1
+		e.writeProfile("work", &Profile{Type: "oauth", Credentials: stale})
+		e.tokenServer(grantTokens(testJWT(map[string]any{"sub": tt.newSub}), "new-refresh"))
+
+		r := e.mustRun("refresh", "work")
+		warned := strings.Contains(r.stderr, "has subject user-b, not user-a")
+		if warned != tt.warn {
+			t.Errorf("refreshing into a token for %s warned %v, want %v\nstderr: %s", tt.newSub, warned, tt.warn, r.stderr)
+		}
+	}
+}
+
+func TestOAuthClientOverrides(t *testing.T) {
+	e := newTestEnv(t)
+	t.Setenv("CLAUDE_SWITCH_CLIENT_ID", "test-client")
+	t.Setenv("CLAUDE_SWITCH_SCOPES", "  user:profile   user:inference ")
+	var mu sync.Mutex
+	var sent map[string]string
+	grant := grantTokens("new-access", "new-refresh")
+	e.tokenServer(func(w http.ResponseWriter, r *http.Request) {
+		mu.Lock()
+		json.NewDecoder(r.Body).Decode(&sent)
+		mu.Unlock()
+		grant(w, r)
+	})
+	expired := testCreds("work-access", "work-refresh")
+	expired.ExpiresAt = 1
+	e.writeProfile("work", &Profile{Type: "oauth", Credentials: expired})
+
+	e.mustRun("refresh", "work")
+	mu.Lock()
+	if sent["client_id"] != "test-client" || sent["scope"] != "user:profile user:inference" {
+		t.Errorf("refresh sent client_id %q and scope %q, want test-client and the normalised scopes", sent["client_id"], sent["scope"])
+	}
+	mu.Unlock()
+	if r := e.run("", "doctor"); !strings.Contains(r.stdout, "OAuth client settings are overridden: client test-client") {
+		t.Errorf("doctor didn't warn about the overrides:\n%s", r.stdout)
+	}
+
+	// Refresh tokens must not go to a remote host in the clear
+	t.Setenv("CLAUDE_SWITCH_TOKEN_URL", "http://example.com/token")
+	e.writeProfile("work", &Profile{Type: "oauth", Credentials: expired})
+	if r := e.run("", "refresh", "work"); r.code == 0 || !strings.Contains(r.stderr, "must use https") {
+		t.Errorf("refresh through a plain http token URL exited with %d (stderr %q), want a failure", r.code, r.stderr)
+	}
+	if r := e.run("", "doctor"); r.code == 0 || !strings.Contains(r.stdout, "FAIL  CLAUDE_SWITCH_TOKEN_URL must use https") {
+		t.Errorf("doctor with a plain http token URL exited with %d:\n%s", r.code, r.stdout)
+	}
+}
+
+func TestRefreshTakesPlanFromResponse(t *testing.T) {
+	e := newTestEnv(t)
+	for _, name := range []string{"work", "home"} {
+		pro, tier := "pro", "default_claude_ai"
+		stale := testCreds(name+"-access", name+"-refresh")
+		stale.ExpiresAt = 1
+		stale.SubscriptionType, stale.RateLimitTier = &pro, &tier
+		e.writeProfile(name, &Profile{Type: "oauth", Credentials: stale})
+	}
+	e.tokenServer(func(w http.ResponseWriter, r *http.Request) {
+		var req map[string]string
+		json.NewDecoder(r.Body).Decode(&req)
+		resp := map[string]any{"access_token": "new-access", "expires_in": 3600}
+		// Only work's plan changed; home's response leaves the fields out
+		if req["refresh_token"] == "work-refresh" {
+			resp["subscription_type"] = "max"
+			resp["rate_limit_tier"] = "default_claude_max_20x"
+			resp["scope"] = "user:profile user:inference user:sessions:claude_code"
+		}
+		w.Header().Set("Content-Type", "application/json")
+		json.NewEncoder(w).Encode(resp)
+	})
+
+	e.mustRun("refresh", "work", "home")
+	for _, tt := range []struct {
+		name, plan, tier string
+		scopes           []string
+	}{
+		{"work", "max", "default_claude_max_20x", []string{"user:profile", "user:inference", "user:sessions:claude_code"}},
+		{"home", "pro", "default_claude_ai", []string{"user:profile", "user:inference"}},
+	} {
+		p := e.readProfile(tt.name)
+		tier := "-"
+		if p.Credentials.RateLimitTier != nil {
+			tier = *p.Credentials.RateLimitTier
+		}
+		if p.DisplaySub() != tt.plan || tier != tt.tier || !slices.Equal(p.Credentials.Scopes, tt.scopes) {
+			t.Errorf("after refresh, %s has plan %s, tier %s and scopes %v, want %s, %s and %v", tt.name, p.DisplaySub(), tier, p.Credentials.Scopes, tt.plan, tt.tier, tt.scopes)
+		}
+	}
+}
+
+func TestRotate(t *testing.T) {
+	e := newTestEnv(t)
+	e.writeProfile("work", &Profile{Type: "oauth", Credentials: testCreds("work-access", "work-refresh")})
+	e.writeProfile("ci", &Profile{Type: "api_key", ApiKey: "sk-ant-api03-test"})
+	e.tokenServer(grantTokens("new-access", "new-refresh"))
+
+	// The token is still valid, but rotate replaces it anyway
+	r := e.mustRun("rotate", "work")
+	if !strings.Contains(r.stderr, "Rotated refresh token for 'work'") {
+		t.Errorf("rotate reported:\n%s", r.stderr)
+	}
+	p := e.readProfile("work")
+	if p.Credentials.RefreshToken != "new-refresh" || p.PrevRefreshToken != "work-refresh" {
+		t.Errorf("after rotate, work holds refresh token %q and previous %q, want new-refresh and work-refresh", p.Credentials.RefreshToken, p.PrevRefreshToken)
+	}
+
+	if r := e.run("", "rotate", "ci"); r.code == 0 || !strings.Contains(r.stderr, "no refresh token to rotate") {
+		t.Errorf("rotate of an API key profile exited with %d (stderr %q), want a failure", r.code, r.stderr)
+	}
+	// --all skips API key profiles instead of failing on them
+	if r := e.mustRun("rotate", "--all"); !strings.Contains(r.stderr, "kept the same refresh token") {
+		t.Errorf("rotate --all with a server that doesn't rotate reported:\n%s", r.stderr)
+	}
+}
+
+func TestBatchRefreshKeepsTokensAroundAFailure(t *testing.T) {
+	e := newTestEnv(t)
+	e.tokenServer(func(w http.ResponseWriter, r *http.Request) {
+		var req struct {
+			RefreshToken string `json:"refresh_token"`
+		}
+		json.NewDecoder(r.Body).Decode(&req)
+		name := strings.TrimSuffix(req.RefreshToken, "-refresh")
+		if name == "b" {
+			http.Error(w, `{"error":"invalid_grant"}`, http.StatusBadRequest)
+			return
+		}
+		grantTokens(name+"-new-access", name+"-new-refresh")(w, r)
+	})
+	for _, name := range []string{"a", "b", "c"} {
+		expired := testCreds(name+"-access", name+"-refresh")
+		expired.ExpiresAt = 1
+		e.writeProfile(name, &Profile{Type: "oauth", Credentials: expired})
+	}
+
+	for _, args := range [][]string{{"rotate", "--all", "-j", "1"}, {"list", "--refresh", "-j", "1"}} {
+		e.run("", args...)
+		for _, name := range []string{"a", "c"} {
+			if got := e.readProfile(name).Credentials.RefreshToken; got != name+"-new-refresh" {
+				t.Errorf("after %v, %s's refresh token = %q, want %s-new-refresh saved despite b failing", args, name, got, name)
+			}
+		}
+		if got := e.readProfile("b").Credentials.RefreshToken; got != "b-refresh" {
+			t.Errorf("after %v, b's refresh token = %q, want it unchanged", args, got)
+		}
+		// Start the next command from the same expired tokens
+		for _, name := range []string{"a", "c"} {
+			expired := testCreds(name+"-access", name+"-refresh")
+			expired.ExpiresAt = 1
+			e.writeProfile(name, &Profile{Type: "oauth", Credentials: expired})
+		}
+	}
+	if r := e.run("", "rotate", "--all"); r.code == 0 || !strings.Contains(r.stderr, "1 of 3 profiles failed to rotate") {
+		t.Errorf("rotate --all with one failure exited with %d (stderr %q), want it to fail counting b", r.code, r.stderr)
+	}
+}
+
+func TestNamesFromStdin(t *testing.T) {
+	e := newTestEnv(t)
+	e.tokenServer(grantTokens("new-access", "new-refresh"))
+	for _, name := range []string{"work", "personal", "spare"} {
+		e.writeProfile(name, &Profile{Type: "oauth", Credentials: testCreds(name+"-access", name+"-refresh")})
+	}
+
+	if r := e.run("work\npersonal\n", "refresh", "-"); r.code != 0 {
+		t.Fatalf("refresh - exited with %d: %s", r.code, r.stderr)
+	}
+	for _, name := range []string{"work", "personal"} {
+		if got := e.readProfile(name).Credentials.AccessToken; got != "new-access" {
+			t.Errorf("%s's access token after refresh - = %q, want new-access", name, got)
+		}
+	}
+	if got := e.readProfile("spare").Credentials.AccessToken; got != "spare-access" {
+		t.Errorf("refresh - refreshed spare, which wasn't piped in")
+	}
+
+	r := e.run("work\nnope\n", "refresh", "-")
+	if r.code != exitError || !strings.Contains(r.stderr, "Failed to refresh 'nope'") {
+		t.Errorf("refresh - with an unknown name exited with %d, stderr %q; want a failure for 'nope'", r.code, r.stderr)
+	}
+
+	if r := e.run("work\n\npersonal\n", "remove", "-"); r.code != 0 {
+		t.Fatalf("remove - exited with %d: %s", r.code, r.stderr)
+	}
+	if e.hasProfile("work") || e.hasProfile("personal") {
+		t.Error("remove - left a piped profile behind")
+	}
+	if !e.hasProfile("spare") {
+		t.Error("remove - removed spare, which wasn't piped in")
+	}
+}
+
+func TestBackSwitchesToThePreviousProfile(t *testing.T) {
+	e := newTestEnv(t)
+	for _, name := range []string{"work", "personal"} {
+		e.writeProfile(name, &Profile{Type: "oauth", Credentials: testCreds(name+"-access", name+"-refresh")})
+	}
+	if r := e.run("", "back"); r.code == 0 || !strings.Contains(r.stderr, "no previous profile") {
+		t.Errorf("back with no previous profile exited with %d (stderr %q), want a failure", r.code, r.stderr)
+	}
+
+	e.mustRun("use", "work")
+	e.mustRun("use", "personal")
+	// back toggles between the last two profiles
+	for _, want := range []string{"work", "personal", "work"} {
+		e.mustRun("back")
+		if got := e.liveCredentials().AccessToken; got != want+"-access" {
+			t.Errorf("after back, Claude's access token = %q, want %s-access", got, want)
+		}
+	}
+	e.mustRun("use", "--restore-previous")
+	if got := e.liveCredentials().AccessToken; got != "personal-access" {
+		t.Errorf("after use --restore-previous, Claude's access token = %q, want personal-access", got)
+	}
+}
+
+func TestUseAPIKeyProfile(t *testing.T) {
+	e := newTestEnv(t)
+	const key = "sk-ant-REDACTED"
+	e.writeProfile("ci", &Profile{Type: "api_key", ApiKey: key})
+	e.login(testCreds("work-access", "work-refresh"), testAccount("me@acme.com", "11111111-1111-1111-1111-111111111111"))
+	claudeJSON := filepath.Join(e.home, ".claude.json")
+
+	r := e.mustRun("use", "ci")
+	if strings.Contains(r.stderr, key) || !strings.Contains(r.stderr, "export ANTHROPIC_API_KEY=sk-ant-a…") {
+		t.Errorf("use printed the key unmasked or not at all:\n%s", r.stderr)
+	}
+	if data, _ := os.ReadFile(claudeJSON); strings.Contains(string(data), key) {
+		t.Error("use wrote the API key to Claude's config without --write-key")
+	}
+	if r := e.mustRun("use", "ci", "--show-key"); !strings.Contains(r.stderr, "export ANTHROPIC_API_KEY="+key) {
+		t.Errorf("use --show-key didn't print the full key:\n%s", r.stderr)
+	}
+	if r := e.mustRun("use", "ci", "--no-hint"); strings.Contains(r.stderr, "ANTHROPIC_API_KEY") {
+		t.Errorf("use --no-hint still printed the guidance:\n%s", r.stderr)
+	}
+
+	e.mustRun("use", "ci", "--write-key")
+	var doc map[string]any
+	data, _ := os.ReadFile(claudeJSON)
+	if err := json.Unmarshal(data, &doc); err != nil {
+		t.Fatal(err)
+	}
+	if doc["primaryApiKey"] != key || doc["oauthAccount"] != nil {
+		t.Errorf("after use --write-key, Claude's config holds %v, want the key and no OAuth account", doc)
+	}
+	if e.liveCredentials() != nil {
+		t.Error("use --write-key left the OAuth login in place next to the key")
+	}
+}
+
+func TestPrompt(t *testing.T) {
+	e := newTestEnv(t)
+	if r := e.mustRun("prompt"); r.stdout != "" {
+		t.Errorf("prompt with no active profile printed %q, want nothing", r.stdout)
+	}
+	if r := e.run("", "prompt", "--exit-code"); r.code != promptNoActive {
+		t.Errorf("prompt --exit-code with no active profile exited with %d, want %d", r.code, promptNoActive)
+	}
+
+	e.writeProfile("work", &Profile{Type: "oauth", Credentials: testCreds("work-access", "work-refresh")})
+	e.mustRun("use", "work")
+	if r := e.mustRun("prompt"); r.stdout != "work\n" {
+		t.Errorf("prompt printed %q, want work", r.stdout)
+	}
+	e.mustRun("prompt", "--exit-code")
+
+	expired := testCreds("work-access", "work-refresh")
+	expired.ExpiresAt = 1
+	e.writeProfile("work", &Profile{Type: "oauth", Credentials: expired})
+	before, _ := os.ReadFile(e.profilePath("work"))
+	if r := e.mustRun("prompt"); r.stdout != "work (expired)\n" {
+		t.Errorf("prompt printed %q, want work (expired)", r.stdout)
+	}
+	if r := e.run("", "prompt", "--exit-code"); r.code != promptExpired {
+		t.Errorf("prompt --exit-code with an expired token exited with %d, want %d", r.code, promptExpired)
+	}
+	// Nothing listens on the test token URL, but prompt mustn't even try
+	if after, _ := os.ReadFile(e.profilePath("work")); string(after) != string(before) {
+		t.Error("prompt changed the expired profile")
+	}
+}
+
+func TestUsePrefer(t *testing.T) {
+	e := newTestEnv(t)
+	const key = "sk-ant-api03-test"
+	e.writeProfile("both", &Profile{Type: "oauth", Credentials: testCreds("both-access", "both-refresh"), ApiKey: key})
+	e.writeProfile("work", &Profile{Type: "oauth", Credentials: testCreds("work-access", "work-refresh")})
+	e.login(testCreds("work-access", "work-refresh"), testAccount("me@acme.com", "11111111-1111-1111-1111-111111111111"))
+	apiKey := func() any {
+		var doc map[string]any
+		data, _ := os.ReadFile(filepath.Join(e.home, ".claude.json"))
+		json.Unmarshal(data, &doc)
+		return doc["primaryApiKey"]
+	}
+
+	e.mustRun("use", "both", "--prefer", "api_key")
+	if got := apiKey(); got != key {
+		t.Errorf("with --prefer api_key, Claude's API key = %v, want %s", got, key)
+	}
+	if e.liveCredentials() != nil {
+		t.Error("with --prefer api_key, the OAuth login is still in place")
+	}
+
+	e.mustRun("use", "both", "--prefer", "oauth")
+	if got := e.liveCredentials(); got == nil || got.AccessToken != "both-access" {
+		t.Errorf("with --prefer oauth, Claude's credentials = %+v, want both-access", got)
+	}
+	if got := apiKey(); got != nil {
+		t.Errorf("with --prefer oauth, Claude's config still holds the API key %v", got)
+	}
+
+	for _, args := range [][]string{{"use", "work", "--prefer", "api_key"}, {"use", "both", "--prefer", "password"}} {
+		if r := e.run("", args...); r.code == 0 {
+			t.Errorf("%v succeeded", args)
+		}
+	}
+}
+
+func TestElapsedTempSwitchRevertsOnNextCommand(t *testing.T) {
+	e := newTestEnv(t)
+	for _, name := range []string{"work", "personal"} {
+		e.writeProfile(name, &Profile{Type: "oauth", Credentials: testCreds(name+"-access", name+"-refresh")})
+	}
+	e.mustRun("use", "work")
+	e.mustRun("use", "personal", "--for", "1h")
+
+	// Let the deadline pass without anything running
+	state := e.readState()
+	if state.TempSwitch == nil {
+		t.Fatal("use --for recorded no temporary switch")
+	}
+	state.TempSwitch.RevertAt = 1
+	e.writeState(state)
+
+	r := e.mustRun("list")
+	if !strings.Contains(r.stderr, "switching back to 'work'") {
+		t.Errorf("the next command didn't say it switched back: %s", r.stderr)
+	}
+	if got := e.liveCredentials().AccessToken; got != "work-access" {
+		t.Errorf("after the deadline, Claude's access token = %q, want work-access", got)
+	}
+	state = e.readState()
+	if state.ActiveProfile == nil || *state.ActiveProfile != "work" || state.TempSwitch != nil {
+		t.Errorf("after the deadline the state is active %v with temporary switch %+v, want work and none", state.ActiveProfile, state.TempSwitch)
+	}
+}
+
+func TestStackedTempSwitchRevertsToFirstProfile(t *testing.T) {
+	e := newTestEnv(t)
+	for _, name := range []string{"work", "personal", "spare"} {
+		e.writeProfile(name, &Profile{Type: "oauth", Credentials: testCreds(name+"-access", name+"-refresh")})
+	}
+	e.login(testCreds("work-access", "work-refresh"), testAccount("me@acme.com", "77777777-7777-7777-7777-777777777777"))
+
+	e.mustRun("use", "work")
+	e.mustRun("use", "personal", "--for", "1h")
+	e.mustRun("use", "spare", "--for", "1h")
+	e.mustRun("revert")
+
+	if got := e.liveCredentials().AccessToken; got != "work-access" {
+		t.Errorf("after revert, Claude's access token = %q, want work-access from before both switches", got)
+	}
+}
+
+// snapshot records every file and directory under root with its mode and
+// contents, to tell whether a run changed anything.
+func snapshot(t *testing.T, root string) map[string]string {
+	t.Helper()
+	files := make(map[string]string)
+	err := filepath.WalkDir(root, func(path string, d fs.DirEntry, err error) error {
+		if err != nil {
+			return err
+		}
+		info, err := d.Info()
+		if err != nil {
+			return err
+		}
+		entry := info.Mode().String()
+		if d.Type().IsRegular() {
+			data, err := os.ReadFile(path)
+			if err != nil {
+				return err
+			}
+			entry += " " + string(data)
+		}
+		files[path] = entry
+		return nil
+	})
+	if err != nil {
+		t.Fatal(err)
+	}
+	return files
+}
+
+func TestNoStateWriteLeavesDiskAlone(t *testing.T) {
+	e := newTestEnv(t)
+	e.tokenServer(grantTokens("new-access", "new-refresh"))
+	e.login(testCreds("work-access", "work-refresh"), testAccount("me@acme.com", "88888888-8888-8888-8888-888888888888"))
+	e.mustRun("import", "work")
+	e.writeProfile("stale", &Profile{Type: "oauth", Credentials: testCreds("stale-access", "stale-refresh")})
+	e.mustRun("use", "stale", "--for", "1h")
+
+	// Tempt every run to write: the temporary switch is due to be reverted
+	// and stale's token needs refreshing
+	statePath := filepath.Join(e.dataDir, "state.json")
+	data, err := os.ReadFile(statePath)
+	if err != nil {
+		t.Fatal(err)
+	}
+	var state State
+	if err := json.Unmarshal(data, &state); err != nil {
+		t.Fatal(err)
+	}
+	state.TempSwitch.RevertAt = 1
+	e.writeFile(statePath, mustJSON(&state))
+	expired := testCreds("old-access", "old-refresh")
+	expired.ExpiresAt = 1
+	e.writeProfile("stale", &Profile{Type: "oauth", Credentials: expired})
+
+	before := snapshot(t, e.root)
+	for _, args := range [][]string{
+		{"list"},
+		{"list", "--refresh"},
+		{"info", "stale"},
+		{"current"},
+		{"doctor", "--fix"},
+		{"use", "work"},
+		{"refresh", "stale"},
+		{"token", "stale"},
+		{"remove", "work"},
+		{"import", "other"},
+		{"trash", "empty"},
+	} {
+		e.run("", append([]string{"--no-state-write"}, args...)...)
+		if after := snapshot(t, e.root); !maps.Equal(before, after) {
+			t.Errorf("--no-state-write %v changed files on disk", args)
+			before = after
+		}
+	}
+
+	r := e.run("", "--no-state-write", "use", "work")
+	if r.code == 0 {
+		t.Error("use succeeded under --no-state-write")
+	}
+	if want := "use would modify files, but --no-state-write is set"; !strings.Contains(r.stderr, want) {
+		t.Errorf("use under --no-state-write said %q, want %q", r.stderr, want)
+	}
+}
+
+func TestNDJSONRefreshWithCorruptProfile(t *testing.T) {
+	e := newTestEnv(t)
+	e.tokenServer(grantTokens("new-access", "new-refresh"))
+	expired := testCreds("old-access", "old-refresh")
+	expired.ExpiresAt = 1
+	e.writeProfile("stale", &Profile{Type: "oauth", Credentials: expired})
+	e.writeFile(e.profilePath("broken"), []byte("{not json"))
+
+	r := e.run("", "list", "--ndjson", "--refresh")
+	if r.code != 0 {
+		t.Fatalf("list --ndjson --refresh exited with %d\nstderr: %s", r.code, r.stderr)
+	}
+	if lines := strings.Split(strings.TrimSpace(r.stdout), "\n"); len(lines) != 2 {
+		t.Errorf("list --ndjson printed %d records, want one per profile:\n%s", len(lines), r.stdout)
+	}
+	if got := e.readProfile("stale").Credentials.AccessToken; got != "new-access" {
+		t.Errorf("stale's access token = %q, want it refreshed to new-access", got)
+	}
+}
+
+// Claude keeps MCP server logins in .credentials.json too, so once an API
+// key switch has removed claudeAiOauth that may be all the file holds.
+func TestCredentialsWithOnlyMCPLogins(t *testing.T) {
+	e := newTestEnv(t)
+	e.writeProfile("work", &Profile{Type: "oauth", Credentials: testCreds("work-access", "work-refresh")})
+	credsPath := filepath.Join(e.claudeDir, ".credentials.json")
+	e.writeFile(credsPath, []byte(`{"mcpOAuth":{"server":{"accessToken":"mcp-token"}}}`))
+
+	e.mustRun("use", "work")
+	if got := e.liveCredentials().AccessToken; got != "work-access" {
+		t.Errorf("Claude's access token = %q, want work-access", got)
+	}
+	data, err := os.ReadFile(credsPath)
+	if err != nil {
+		t.Fatal(err)
+	}
+	if !strings.Contains(string(data), "mcp-token") {
+		t.Errorf("use dropped Claude's MCP logins:\n%s", data)
+	}
+}
+
+func TestReauthenticationKeepsProfileSettings(t *testing.T) {
+	e := newTestEnv(t)
+	e.tokenServer(func(w http.ResponseWriter, r *http.Request) {
+		http.Error(w, `{"error":"invalid_grant"}`, http.StatusBadRequest)
+	})
+	expired := testCreds("old-access", "old-refresh")
+	expired.ExpiresAt = 1
+	label := "day job"
+	e.writeProfile("work", &Profile{
+		Type:             "oauth",
+		Credentials:      expired,
+		Account:          testAccount("me@acme.com", "99999999-9999-9999-9999-999999999999"),
+		Label:            &label,
+		DefaultCommand:   []string{"claude", "--continue"},
+		UIPrefs:          map[string]json.RawMessage{"theme": json.RawMessage(`"dark"`)},
+		PrevRefreshToken: "older-refresh",
+	})
+	e.stubLogin(testCreds("new-access", "new-refresh"), testAccount("me@acme.com", "99999999-9999-9999-9999-999999999999"))
+
+	e.mustRun("use", "work")
+
+	p := e.readProfile("work")
+	if p.Credentials.AccessToken != "new-access" {
+		t.Fatalf("after re-authenticating, the access token is %q, want new-access", p.Credentials.AccessToken)
+	}
+	if p.Label == nil || *p.Label != label {
+		t.Errorf("re-authenticating lost the label: %v", p.Label)
+	}
+	if strings.Join(p.DefaultCommand, " ") != "claude --continue" {
+		t.Errorf("re-authenticating lost the default command: %q", p.DefaultCommand)
+	}
+	if string(p.UIPrefs["theme"]) != `"dark"` {
+		t.Errorf("re-authenticating lost the UI preferences: %v", p.UIPrefs)
+	}
+	if p.PrevRefreshToken != "older-refresh" {
+		t.Errorf("re-authenticating lost the previous refresh token: %q", p.PrevRefreshToken)
+	}
+}
+
+// token, env and activate print for a caller to capture, so a revoked
+// refresh token must fail them rather than start a login on stdout.
+func TestRevokedTokenNeverLogsInForOutput(t *testing.T) {
+	e := newTestEnv(t)
+	e.tokenServer(func(w http.ResponseWriter, r *http.Request) {
+		http.Error(w, `{"error":"invalid_grant"}`, http.StatusBadRequest)
+	})
+	expired := testCreds("old-access", "old-refresh")
+	expired.ExpiresAt = 1
+	e.writeProfile("work", &Profile{Type: "oauth", Credentials: expired})
+	e.stubLogin(testCreds("new-access", "new-refresh"), testAccount("me@acme.com", "12121212-1212-1212-1212-121212121212"))
+
+	for _, args := range [][]string{
+		{"token", "work"},
+		{"env", "work"},
+		{"activate", "work"},
+		{"exec", "work", "--", "true"},
+	} {
+		r := e.run("", args...)
+		if r.code != exitReauth {
+			t.Errorf("%v with a revoked refresh token exited with %d, want %d\nstderr: %s", args, r.code, exitReauth, r.stderr)
+		}
+		if r.stdout != "" {
+			t.Errorf("%v printed %q on stdout, want nothing", args, r.stdout)
+		}
+		if !strings.Contains(r.stderr, "re-authenticate with") {
+			t.Errorf("%v didn't say how to re-authenticate: %s", args, r.stderr)
+		}
+	}
+	if got := e.readProfile("work").Credentials.AccessToken; got != "old-access" {
+		t.Errorf("a login ran: the profile now holds %q", got)
+	}
+}
+
+func TestNoColourWhenPiped(t *testing.T) {
+	e := newTestEnv(t)
+	e.writeProfile("work", &Profile{Type: "oauth", Credentials: testCreds("work-access", "work-refresh")})
+	e.writeFile(e.profilePath("broken"), []byte("{not json"))
+	e.writeProfile("old", &Profile{Type: "oauth", Credentials: testCreds("old-access", "old-refresh")})
+	e.mustRun("remove", "old")
+
+	// Output to a pipe is never coloured, NO_COLOR or not
+	for _, noColour := range []string{"1", ""} {
+		t.Setenv("NO_COLOR", noColour)
+		for _, args := range [][]string{
+			{"list"},
+			{"list", "--errors-only"},
+			{"info", "work"},
+			{"validate"},
+			{"doctor"},
+			{"trash", "list"},
+		} {
+			if r := e.run("", args...); strings.Contains(r.stdout, "\x1b[") {
+				t.Errorf("%v with NO_COLOR=%q printed colour codes into a pipe:\n%q", args, noColour, r.stdout)
+			}
+		}
+	}
+
+	if r := e.run("", "list", "--errors-only", "--color=always"); !strings.Contains(r.stdout, "\x1b[") {
+		t.Errorf("list --errors-only --color=always printed no colour codes:\n%q", r.stdout)
+	}
+	if r := e.run("", "list", "--errors-only", "--color=never"); strings.Contains(r.stdout, "\x1b[") {
+		t.Errorf("list --errors-only --color=never printed colour codes:\n%q", r.stdout)
+	}
+}
+
+func TestRefreshParallelLimit(t *testing.T) {
+	for _, jobs := range []int{1, 2} {
+		t.Run(fmt.Sprintf("-j %d", jobs), func(t *testing.T) {
+			e := newTestEnv(t)
+			var mu sync.Mutex
+			inFlight, most := 0, 0
+			grant := grantTokens("new-access", "new-refresh")
+			e.tokenServer(func(w http.ResponseWriter, r *http.Request) {
+				mu.Lock()
+				inFlight++
+				most = max(most, inFlight)
+				mu.Unlock()
+				time.Sleep(300 * time.Millisecond)
+				mu.Lock()
+				inFlight--
+				mu.Unlock()
+				grant(w, r)
+			})
+			names := []string{"a", "b", "c", "d"}
+			for _, name := range names {
+				expired := testCreds(name+"-access", name+"-refresh")
+				expired.ExpiresAt = 1
+				e.writeProfile(name, &Profile{Type: "oauth", Credentials: expired})
+			}
+
+			e.mustRun(append([]string{"refresh", "-j", fmt.Sprint(jobs)}, names...)...)
+			mu.Lock()
+			defer mu.Unlock()
+			if most != jobs {
+				t.Errorf("refresh -j %d made up to %d token requests at once, want %d", jobs, most, jobs)
+			}
+			for _, name := range names {
+				if got := e.readProfile(name).Credentials.AccessToken; got != "new-access" {
+					t.Errorf("%s's access token = %q, want new-access", name, got)
+				}
+			}
+		})
+	}
+}
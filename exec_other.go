@@ -0,0 +1,29 @@
+//go:build !unix
+
+package main
+
+import (
+	"errors"
+	"fmt"
+	"os"
+	"os/exec"
+)
+
+// replaceProcess runs the command as a child, since there is no exec(2)
+// here, and passes its exit status on through main.
+func replaceProcess(binary string, args, env []string) error {
+	cmd := exec.Command(binary, args[1:]...)
+	cmd.Env = env
+	cmd.Stdin = os.Stdin
+	cmd.Stdout = os.Stdout
+	cmd.Stderr = os.Stderr
+	err := cmd.Run()
+	var exitErr *exec.ExitError
+	if errors.As(err, &exitErr) {
+		return &childExitError{code: exitErr.ExitCode()}
+	}
+	if err != nil {
+		return fmt.Errorf("exec failed: %w", err)
+	}
+	return nil
+}
@@ -0,0 +1,11 @@
+//go:build unix
+
+package main
+
+import "syscall"
+
+// replaceProcess execs the command in place of claude-switch, so it gets the
+// terminal and signals directly.
+func replaceProcess(binary string, args, env []string) error {
+	return syscall.Exec(binary, args, env)
+}
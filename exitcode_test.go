@@ -0,0 +1,55 @@
+package main
+
+import (
+	"encoding/json"
+	"net/http"
+	"testing"
+)
+
+func TestExitCodes(t *testing.T) {
+	e := newTestEnv(t)
+	e.writeProfile("work", &Profile{Type: "oauth", Credentials: testCreds("work-access", "work-refresh")})
+	expired := testCreds("old-access", "old-refresh")
+	expired.ExpiresAt = 1
+	e.writeProfile("stale", &Profile{Type: "oauth", Credentials: expired})
+
+	tests := []struct {
+		args []string
+		want int
+	}{
+		{[]string{"use", "nope"}, exitNotFound},
+		{[]string{"token", "nope"}, exitNotFound},
+		{[]string{"use", "../etc"}, exitError},
+		// Nothing listens on the default test token URL
+		{[]string{"refresh", "stale"}, exitNetwork},
+		{[]string{"exec", "--timeout", "1s", "work", "--", "sleep", "5"}, exitTimeout},
+		{[]string{"exec", "work", "--", "sh", "-c", "exit 7"}, 7},
+	}
+	for _, tt := range tests {
+		if r := e.run("", tt.args...); r.code != tt.want {
+			t.Errorf("claude-switch %v exited with %d, want %d\nstderr: %s", tt.args, r.code, tt.want, r.stderr)
+		}
+	}
+
+	e.tokenServer(func(w http.ResponseWriter, r *http.Request) {
+		http.Error(w, `{"error":"invalid_grant"}`, http.StatusBadRequest)
+	})
+	if r := e.run("", "refresh", "stale"); r.code != exitReauth {
+		t.Errorf("refresh with a revoked refresh token exited with %d, want %d\nstderr: %s", r.code, exitReauth, r.stderr)
+	}
+}
+
+func TestJSONErrors(t *testing.T) {
+	e := newTestEnv(t)
+	r := e.run("", "--json-errors", "use", "nope")
+	if r.code != exitNotFound {
+		t.Errorf("--json-errors use nope exited with %d, want %d", r.code, exitNotFound)
+	}
+	var got struct{ Error, Kind string }
+	if err := json.Unmarshal([]byte(r.stdout), &got); err != nil {
+		t.Fatalf("--json-errors printed %q, not JSON: %v", r.stdout, err)
+	}
+	if got.Kind != "profile_not_found" || got.Error == "" {
+		t.Errorf("--json-errors reported %+v, want kind profile_not_found with a message", got)
+	}
+}
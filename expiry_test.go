@@ -0,0 +1,103 @@
+package main
+
+import (
+	"path/filepath"
+	"testing"
+	"time"
+)
+
+func TestImportSettlesZeroExpiryFromJWT(t *testing.T) {
+	e := newTestEnv(t)
+	exp := time.Now().Add(2 * time.Hour).Unix()
+	creds := testCreds(testJWT(map[string]any{"exp": exp, "sub": "user-1"}), "refresh-1")
+	creds.ExpiresAt = 0
+	snippet := filepath.Join(e.root, "creds.json")
+	e.writeFile(snippet, mustJSON(map[string]any{"claudeAiOauth": creds}))
+
+	e.mustRun("import", "moved", "--from-file", snippet)
+	if got := e.readProfile("moved").Credentials.ExpiresAt; got != 0 {
+		t.Fatalf("imported expiresAt = %d, want it kept at 0 until first use", got)
+	}
+
+	e.mustRun("env", "moved")
+	if got, want := e.readProfile("moved").Credentials.ExpiresAt, uint64(exp)*1000; got != want {
+		t.Errorf("expiresAt after first use = %d, want %d from the token's exp claim", got, want)
+	}
+}
+
+func TestImportSettlesZeroExpiryByRefreshing(t *testing.T) {
+	e := newTestEnv(t)
+	e.tokenServer(grantTokens("fresh-access", "fresh-refresh"))
+	creds := testCreds("opaque-access", "refresh-1")
+	creds.ExpiresAt = 0
+	snippet := filepath.Join(e.root, "creds.json")
+	e.writeFile(snippet, mustJSON(map[string]any{"claudeAiOauth": creds}))
+
+	e.mustRun("import", "moved", "--from-file", snippet)
+	e.mustRun("env", "moved")
+
+	got := e.readProfile("moved").Credentials
+	if got.AccessToken != "fresh-access" || got.ExpiresAt == 0 {
+		t.Errorf("after first use the profile holds %q expiring at %d, want a refreshed token with a real expiry", got.AccessToken, got.ExpiresAt)
+	}
+}
+
+func TestIsExpiredWithBuffer(t *testing.T) {
+	now := nowMs()
+	const minute = 60 * 1000
+	tests := []struct {
+		expiresAt, buffer uint64
+		want              bool
+	}{
+		{now + 10*minute, 5 * minute, false},
+		{now + 4*minute, 5 * minute, true},
+		{now + 4*minute, 0, false},
+		{now + 10*minute, 15 * minute, true},
+		{now - minute, 0, true},
+		{0, 0, true},
+	}
+	for _, tt := range tests {
+		creds := &OAuthCredentials{ExpiresAt: tt.expiresAt}
+		if got := isExpired(creds, tt.buffer); got != tt.want {
+			t.Errorf("isExpired(expires in %dms, buffer %dms) = %v, want %v", int64(tt.expiresAt-now), tt.buffer, got, tt.want)
+		}
+	}
+}
+
+func TestEnvDurationMs(t *testing.T) {
+	tests := []struct {
+		value string
+		want  uint64
+	}{
+		{"", 7},
+		{"90", 90 * 1000},
+		{"2m", 2 * 60 * 1000},
+		{"soon", 7},
+		{"9223372037", 7}, // too many seconds for a time.Duration
+	}
+	for _, tt := range tests {
+		t.Setenv("CLAUDE_SWITCH_TEST_DURATION", tt.value)
+		if got := envDurationMs("CLAUDE_SWITCH_TEST_DURATION", 7); got != tt.want {
+			t.Errorf("envDurationMs with %q = %d, want %d", tt.value, got, tt.want)
+		}
+	}
+}
+
+func TestExpiryBufferFromEnvironment(t *testing.T) {
+	e := newTestEnv(t)
+	e.tokenServer(grantTokens("new-access", "new-refresh"))
+	e.writeProfile("work", &Profile{Type: "oauth", Credentials: testCreds("old-access", "old-refresh")})
+
+	// An hour left is plenty with the default 5 minute buffer...
+	e.mustRun("refresh", "work", "--if-needed")
+	if got := e.readProfile("work").Credentials.AccessToken; got != "old-access" {
+		t.Fatalf("refresh --if-needed replaced a token with an hour left: %q", got)
+	}
+
+	// ...but not with a 2 hour one
+	t.Setenv("CLAUDE_SWITCH_EXPIRY_BUFFER", "2h")
+	e.mustRun("refresh", "work", "--if-needed")
+	if got := e.readProfile("work").Credentials.AccessToken; got != "new-access" {
+		t.Errorf("with a 2h buffer, refresh --if-needed left the token as %q, want new-access", got)
+	}
+}
@@ -0,0 +1,31 @@
+//go:build unix
+
+package main
+
+import (
+	"fmt"
+	"os"
+	"path/filepath"
+	"syscall"
+)
+
+// ownerHint explains a permission error on path by naming the file's owner
+// when it isn't the current user, the usual result of running under sudo.
+func ownerHint(path string) string {
+	info, err := os.Stat(path)
+	if err != nil {
+		if info, err = os.Stat(filepath.Dir(path)); err != nil {
+			return ""
+		}
+		path = filepath.Dir(path)
+	}
+	st, ok := info.Sys().(*syscall.Stat_t)
+	if !ok {
+		return ""
+	}
+	uid := os.Getuid()
+	if int(st.Uid) == uid {
+		return fmt.Sprintf(" (check the permissions of %s)", path)
+	}
+	return fmt.Sprintf(" (%s is owned by uid %d, not you (uid %d); fix it with: sudo chown %d %s)", path, st.Uid, uid, uid, path)
+}
@@ -0,0 +1,141 @@
+//go:build unix
+
+package main
+
+import (
+	"errors"
+	"fmt"
+	"io/fs"
+	"os"
+	"path/filepath"
+	"strings"
+	"syscall"
+	"testing"
+)
+
+func TestOwnerHint(t *testing.T) {
+	dir := t.TempDir()
+	path := filepath.Join(dir, "state.json")
+	if err := os.WriteFile(path, nil, 0o600); err != nil {
+		t.Fatal(err)
+	}
+	if got, want := ownerHint(path), fmt.Sprintf(" (check the permissions of %s)", path); got != want {
+		t.Errorf("ownerHint for our own file = %q, want %q", got, want)
+	}
+	// A file that doesn't exist yet is explained by its directory
+	if got, want := ownerHint(filepath.Join(dir, "new.json")), fmt.Sprintf(" (check the permissions of %s)", dir); got != want {
+		t.Errorf("ownerHint for a missing file = %q, want %q", got, want)
+	}
+	if os.Getuid() != 0 {
+		if got := ownerHint("/"); !strings.Contains(got, "owned by uid 0") || !strings.Contains(got, "sudo chown") {
+			t.Errorf("ownerHint for root's directory = %q, want the owner and a chown to fix it", got)
+		}
+	}
+}
+
+func TestExplainWriteError(t *testing.T) {
+	dir := t.TempDir()
+	err := explainWriteError(dir, &fs.PathError{Op: "open", Path: dir, Err: fs.ErrPermission})
+	if want := fmt.Sprintf("cannot write %s: permission denied (check the permissions of %s)", dir, dir); err.Error() != want {
+		t.Errorf("explainWriteError = %q, want %q", err, want)
+	}
+	other := errors.New("disk full")
+	if err := explainWriteError(dir, other); err != other {
+		t.Errorf("explainWriteError changed an unrelated error to %q", err)
+	}
+}
+
+func TestImportIntoUnwritableConfigDir(t *testing.T) {
+	if os.Getuid() == 0 {
+		t.Skip("root can write anywhere")
+	}
+	e := newTestEnv(t)
+	e.login(testCreds("work-access", "work-refresh"), testAccount("me@acme.com", "11111111-1111-1111-1111-111111111111"))
+	profiles := filepath.Join(e.dataDir, "profiles")
+	if err := os.MkdirAll(profiles, 0o500); err != nil {
+		t.Fatal(err)
+	}
+	t.Cleanup(func() { os.Chmod(profiles, 0o700) })
+
+	r := e.run("", "import", "work")
+	if r.code == 0 || !strings.Contains(r.stderr, "permission denied (check the permissions of") {
+		t.Errorf("import into a read-only profiles directory exited with %d (stderr %q), want the permission error explained", r.code, r.stderr)
+	}
+}
+
+func TestUnreadableProfileIsNotReportedMissing(t *testing.T) {
+	if os.Getuid() == 0 {
+		t.Skip("root can read anything")
+	}
+	e := newTestEnv(t)
+	e.writeProfile("work", &Profile{Type: "oauth", Credentials: testCreds("work-access", "work-refresh")})
+	if err := os.Chmod(e.profilePath("work"), 0); err != nil {
+		t.Fatal(err)
+	}
+
+	r := e.run("", "token", "work")
+	if r.code == exitNotFound || r.code == 0 || !strings.Contains(r.stderr, "permission denied") {
+		t.Errorf("token for an unreadable profile exited with %d (stderr %q), want a permission error, not 'not found'", r.code, r.stderr)
+	}
+}
+
+func TestSwitchLeavesEveryFilePrivate(t *testing.T) {
+	e := newTestEnv(t)
+	e.login(testCreds("old-access", "old-refresh"), testAccount("old@example.com", "11111111-1111-1111-1111-111111111111"))
+	claudeFiles := []string{filepath.Join(e.claudeDir, ".credentials.json"), filepath.Join(e.home, ".claude.json")}
+	for _, path := range claudeFiles {
+		if err := os.Chmod(path, 0o644); err != nil {
+			t.Fatal(err)
+		}
+	}
+	e.writeProfile("work", &Profile{Type: "oauth", Credentials: testCreds("work-access", "work-refresh")})
+
+	r := e.mustRun("use", "work")
+	if strings.Contains(r.stderr, "doesn't enforce permissions") {
+		t.Errorf("use warned about a filesystem that enforces permissions:\n%s", r.stderr)
+	}
+	for _, path := range append(claudeFiles, filepath.Join(e.dataDir, "state.json")) {
+		if info, err := os.Stat(path); err != nil {
+			t.Error(err)
+		} else if info.Mode().Perm() != 0o600 {
+			t.Errorf("after use, %s has mode %04o, want 0600", path, info.Mode().Perm())
+		}
+	}
+}
+
+func TestExplainReadOnlyFilesystem(t *testing.T) {
+	e := newTestEnv(t)
+	erofs := func(path string) error {
+		return explainWriteError(path, &fs.PathError{Op: "open", Path: path, Err: syscall.EROFS})
+	}
+	own := filepath.Join(e.dataDir, "state.json")
+	if err := erofs(own); !strings.Contains(err.Error(), "set CLAUDE_SWITCH_HOME or pass --config-dir") {
+		t.Errorf("read-only error for claude-switch's own file = %q, want the ways to move it", err)
+	}
+	claude := filepath.Join(e.claudeDir, ".credentials.json")
+	if err := erofs(claude); !strings.Contains(err.Error(), "Claude's config must be writable") {
+		t.Errorf("read-only error for Claude's file = %q, want it explained", err)
+	}
+}
+
+func TestConfigDirCanBeMoved(t *testing.T) {
+	e := newTestEnv(t)
+	e.login(testCreds("work-access", "work-refresh"), testAccount("me@acme.com", "11111111-1111-1111-1111-111111111111"))
+	flagDir := filepath.Join(e.root, "flag-dir")
+	envDir := filepath.Join(e.root, "env-dir")
+
+	e.mustRun("--config-dir", flagDir, "import", "work")
+	t.Setenv("CLAUDE_SWITCH_HOME", envDir)
+	e.mustRun("import", "personal")
+	// The flag wins over the environment
+	e.mustRun("--config-dir="+flagDir, "token", "work")
+
+	for dir, name := range map[string]string{flagDir: "work", envDir: "personal"} {
+		if _, err := os.Stat(filepath.Join(dir, "profiles", name+".json")); err != nil {
+			t.Errorf("%s wasn't saved in %s: %v", name, dir, err)
+		}
+	}
+	if e.hasProfile("work") || e.hasProfile("personal") {
+		t.Error("a profile was saved in the default config directory")
+	}
+}
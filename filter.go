@@ -0,0 +1,104 @@
+package main
+
+import (
+	"fmt"
+	"sort"
+	"strings"
+)
+
+// --- list --filter expressions ---
+//
+// A filter is a set of comparisons joined by && and ||, with && binding
+// tighter, e.g. "type==oauth && plan==max || org~=Acme". Comparisons are
+// case-insensitive: == and != test equality, ~= tests for a substring.
+
+var filterFields = map[string]func(name string, p *Profile) string{
+	"name":  func(name string, _ *Profile) string { return name },
+	"type":  func(_ string, p *Profile) string { return p.DisplayType() },
+	"email": func(_ string, p *Profile) string { return p.DisplayEmail() },
+	"org":   func(_ string, p *Profile) string { return p.DisplayOrg() },
+	"role":  func(_ string, p *Profile) string { return p.DisplayOrgRole() },
+	"plan":  func(_ string, p *Profile) string { return p.DisplaySub() },
+}
+
+type filterTerm struct {
+	field string
+	op    string
+	value string
+}
+
+// profileFilter is a parsed expression: it matches when every term of any
+// one clause matches.
+type profileFilter [][]filterTerm
+
+func parseFilter(expr string) (profileFilter, error) {
+	var filter profileFilter
+	for _, clause := range strings.Split(expr, "||") {
+		var terms []filterTerm
+		for _, raw := range strings.Split(clause, "&&") {
+			term, err := parseFilterTerm(strings.TrimSpace(raw))
+			if err != nil {
+				return nil, fmt.Errorf("invalid filter '%s': %w", expr, err)
+			}
+			terms = append(terms, term)
+		}
+		filter = append(filter, terms)
+	}
+	return filter, nil
+}
+
+func parseFilterTerm(s string) (filterTerm, error) {
+	if s == "" {
+		return filterTerm{}, fmt.Errorf("empty comparison")
+	}
+	for _, op := range []string{"==", "!=", "~="} {
+		field, value, ok := strings.Cut(s, op)
+		if !ok {
+			continue
+		}
+		field = strings.ToLower(strings.TrimSpace(field))
+		if _, known := filterFields[field]; !known {
+			return filterTerm{}, fmt.Errorf("unknown field '%s' (expected one of %s)", field, strings.Join(filterFieldNames(), ", "))
+		}
+		value = strings.Trim(strings.TrimSpace(value), `"'`)
+		return filterTerm{field: field, op: op, value: value}, nil
+	}
+	return filterTerm{}, fmt.Errorf("'%s' is not a comparison (use ==, != or ~=)", s)
+}
+
+func filterFieldNames() []string {
+	names := make([]string, 0, len(filterFields))
+	for name := range filterFields {
+		names = append(names, name)
+	}
+	sort.Strings(names)
+	return names
+}
+
+func (t filterTerm) match(name string, p *Profile) bool {
+	got := filterFields[t.field](name, p)
+	switch t.op {
+	case "==":
+		return strings.EqualFold(got, t.value)
+	case "!=":
+		return !strings.EqualFold(got, t.value)
+	default:
+		return strings.Contains(strings.ToLower(got), strings.ToLower(t.value))
+	}
+}
+
+func (f profileFilter) match(name string, p *Profile) bool {
+	for _, clause := range f {
+		matched := true
+		for _, t := range clause {
+			if !t.match(name, p) {
+				matched = false
+				break
+			}
+		}
+		if matched {
+			return true
+		}
+	}
+	return false
+}
@@ -0,0 +1,85 @@
+package main
+
+import (
+	"strings"
+	"testing"
+)
+
+func TestParseFilter(t *testing.T) {
+	f, err := parseFilter(` type==oauth && Plan == "max" || org~='Acme' `)
+	if err != nil {
+		t.Fatal(err)
+	}
+	want := profileFilter{
+		{{field: "type", op: "==", value: "oauth"}, {field: "plan", op: "==", value: "max"}},
+		{{field: "org", op: "~=", value: "Acme"}},
+	}
+	if len(f) != len(want) {
+		t.Fatalf("parsed %d clauses, want %d: %+v", len(f), len(want), f)
+	}
+	for i := range want {
+		if len(f[i]) != len(want[i]) {
+			t.Fatalf("clause %d has %d terms, want %d: %+v", i, len(f[i]), len(want[i]), f[i])
+		}
+		for j := range want[i] {
+			if f[i][j] != want[i][j] {
+				t.Errorf("clause %d term %d = %+v, want %+v", i, j, f[i][j], want[i][j])
+			}
+		}
+	}
+}
+
+func TestParseFilterRejects(t *testing.T) {
+	tests := []struct{ expr, want string }{
+		{"", "empty comparison"},
+		{"type==oauth &&", "empty comparison"},
+		{"|| plan==max", "empty comparison"},
+		{"colour==red", "unknown field 'colour'"},
+		{"type=oauth", "is not a comparison"},
+		{"oauth", "is not a comparison"},
+	}
+	for _, tt := range tests {
+		_, err := parseFilter(tt.expr)
+		if err == nil || !strings.Contains(err.Error(), tt.want) {
+			t.Errorf("parseFilter(%q) error = %v, want one containing %q", tt.expr, err, tt.want)
+		}
+	}
+}
+
+func TestFilterMatch(t *testing.T) {
+	plan := "max"
+	acme := &Profile{
+		Type:        "oauth",
+		Credentials: &OAuthCredentials{SubscriptionType: &plan},
+		Account:     mustJSON(map[string]string{"emailAddress": "me@acme.com", "organizationName": "Acme Corp", "organizationRole": "admin"}),
+	}
+	key := &Profile{Type: "api_key", ApiKey: "sk-ant-test"}
+
+	tests := []struct {
+		expr      string
+		work, api bool
+	}{
+		{"type==oauth", true, false},
+		{"TYPE==OAUTH", true, false},
+		{"type!=oauth", false, true},
+		{"name==work", true, false},
+		{"org~=acme", true, false},
+		{"email~=@ACME.", true, false},
+		{"plan==max && role==admin", true, false},
+		{"plan==max && role==member", false, false},
+		{"plan==pro || type==api_key", false, true},
+		{"org==- && type==api_key || name==work", true, true},
+	}
+	for _, tt := range tests {
+		f, err := parseFilter(tt.expr)
+		if err != nil {
+			t.Fatalf("parseFilter(%q): %v", tt.expr, err)
+		}
+		if got := f.match("work", acme); got != tt.work {
+			t.Errorf("%q matched the OAuth profile: %v, want %v", tt.expr, got, tt.work)
+		}
+		if got := f.match("ci", key); got != tt.api {
+			t.Errorf("%q matched the API key profile: %v, want %v", tt.expr, got, tt.api)
+		}
+	}
+}
@@ -0,0 +1,28 @@
+package main
+
+import (
+	"slices"
+	"testing"
+)
+
+func TestParseGlobalFlagsLeavesExecCommandAlone(t *testing.T) {
+	t.Cleanup(func() { forceWrite, quiet = false, false })
+	tests := []struct {
+		args      []string
+		want      []string
+		wantForce bool
+	}{
+		{[]string{"exec", "work", "git", "push", "--force"}, []string{"exec", "work", "git", "push", "--force"}, false},
+		{[]string{"exec", "--timeout", "5s", "work", "git", "push", "-q"}, []string{"exec", "--timeout", "5s", "work", "git", "push", "-q"}, false},
+		{[]string{"exec", "work", "--force", "--", "git", "push"}, []string{"exec", "work", "--", "git", "push"}, true},
+		{[]string{"--force", "exec", "work", "git"}, []string{"exec", "work", "git"}, true},
+		{[]string{"import", "work", "--force"}, []string{"import", "work"}, true},
+	}
+	for _, tt := range tests {
+		forceWrite = false
+		got := parseGlobalFlags(tt.args)
+		if !slices.Equal(got, tt.want) || forceWrite != tt.wantForce {
+			t.Errorf("parseGlobalFlags(%q) = %q with force %v, want %q with force %v", tt.args, got, forceWrite, tt.want, tt.wantForce)
+		}
+	}
+}
@@ -0,0 +1,281 @@
+package main
+
+import (
+	"bytes"
+	"encoding/base64"
+	"encoding/json"
+	"errors"
+	"net/http"
+	"net/http/httptest"
+	"os"
+	"os/exec"
+	"path/filepath"
+	"runtime"
+	"strings"
+	"testing"
+	"time"
+)
+
+// TestMain lets the test binary stand in for claude-switch: started with
+// CLAUDE_SWITCH_TEST_MAIN set, it runs main instead of the tests. End-to-end
+// tests drive the real CLI that way, exit codes included, without a separate
+// build.
+func TestMain(m *testing.M) {
+	if os.Getenv("CLAUDE_SWITCH_TEST_MAIN") != "" {
+		main()
+		os.Exit(0)
+	}
+	os.Exit(m.Run())
+}
+
+// testEnv is a throwaway HOME, XDG_CONFIG_HOME and CLAUDE_CONFIG_DIR with a
+// stub claude binary, so tests never touch the real accounts.
+type testEnv struct {
+	t         *testing.T
+	root      string
+	home      string // HOME, holding ~/.claude.json
+	claudeDir string // CLAUDE_CONFIG_DIR, holding .credentials.json
+	dataDir   string // claude-switch's own config directory
+}
+
+// stubClaude answers --version and, for /login, copies the files set up by
+// stubLogin or stubAPIKeyLogin into place the way a real login would. With
+// STUB_LOGIN=api-key it logs in with an API key, which Claude keeps only in
+// ~/.claude.json.
+const stubClaude = `#!/bin/sh
+case "$1" in
+--version)
+    echo "2.1.0 (Claude Code)"
+    ;;
+/login)
+    if [ "$STUB_LOGIN" = api-key ]; then
+        cp "$STUB_DIR/login-claude.json" "$HOME/.claude.json"
+        exit 0
+    fi
+    [ -f "$STUB_DIR/login-credentials.json" ] || exit 1
+    cp "$STUB_DIR/login-credentials.json" "$CLAUDE_CONFIG_DIR/.credentials.json"
+    cp "$STUB_DIR/login-claude.json" "$HOME/.claude.json"
+    ;;
+esac
+`
+
+func newTestEnv(t *testing.T) *testEnv {
+	t.Helper()
+	if runtime.GOOS == "windows" {
+		t.Skip("the stub claude is a shell script")
+	}
+	root := t.TempDir()
+	e := &testEnv{
+		t:         t,
+		root:      root,
+		home:      filepath.Join(root, "home"),
+		claudeDir: filepath.Join(root, "home", ".claude"),
+		dataDir:   filepath.Join(root, "xdg", "claude-switch"),
+	}
+	if err := os.MkdirAll(e.claudeDir, 0o700); err != nil {
+		t.Fatal(err)
+	}
+	stub := filepath.Join(root, "claude")
+	if err := os.WriteFile(stub, []byte(stubClaude), 0o755); err != nil {
+		t.Fatal(err)
+	}
+
+	t.Setenv("HOME", e.home)
+	t.Setenv("XDG_CONFIG_HOME", filepath.Join(root, "xdg"))
+	t.Setenv("CLAUDE_CONFIG_DIR", e.claudeDir)
+	t.Setenv("CLAUDE_SWITCH_CLAUDE_BIN", stub)
+	t.Setenv("STUB_DIR", root)
+	t.Setenv("STUB_LOGIN", "")
+	// Nothing from the developer's own setup leaks in: no keychain (it is
+	// keyed by USER), no real token endpoint, no retries and no colour
+	t.Setenv("USER", "")
+	t.Setenv("CLAUDE_SWITCH_HOME", "")
+	t.Setenv("CLAUDE_SWITCH_PASSPHRASE", "")
+	t.Setenv("CLAUDE_SWITCH_NOTIFY", "")
+	t.Setenv("CLAUDE_SWITCH_EXPIRY_BUFFER", "")
+	t.Setenv("CLAUDE_SWITCH_CHECK_IDENTITY", "")
+	t.Setenv("CLAUDE_SWITCH_TOKEN_URL", "http://127.0.0.1:1/token")
+	t.Setenv("CLAUDE_SWITCH_HTTP_ATTEMPTS", "1")
+	t.Setenv("CLAUDE_SWITCH_PROXY", "")
+	t.Setenv("NO_COLOR", "1")
+	return e
+}
+
+// result is what one claude-switch run printed and how it exited.
+type result struct {
+	stdout, stderr string
+	code           int
+}
+
+// run invokes claude-switch with args, feeding it stdin.
+func (e *testEnv) run(stdin string, args ...string) result {
+	e.t.Helper()
+	cmd := exec.Command(os.Args[0], args...)
+	cmd.Env = append(os.Environ(), "CLAUDE_SWITCH_TEST_MAIN=1")
+	cmd.Stdin = strings.NewReader(stdin)
+	var stdout, stderr bytes.Buffer
+	cmd.Stdout = &stdout
+	cmd.Stderr = &stderr
+	err := cmd.Run()
+	var exitErr *exec.ExitError
+	if err != nil && !errors.As(err, &exitErr) {
+		e.t.Fatalf("running claude-switch %v: %v", args, err)
+	}
+	return result{stdout.String(), stderr.String(), cmd.ProcessState.ExitCode()}
+}
+
+// mustRun is run for commands expected to succeed.
+func (e *testEnv) mustRun(args ...string) result {
+	e.t.Helper()
+	r := e.run("", args...)
+	if r.code != 0 {
+		e.t.Fatalf("claude-switch %v exited with %d\nstdout: %s\nstderr: %s", args, r.code, r.stdout, r.stderr)
+	}
+	return r
+}
+
+// testCreds returns OAuth credentials that expire in an hour.
+func testCreds(access, refresh string) *OAuthCredentials {
+	return &OAuthCredentials{
+		AccessToken:  access,
+		RefreshToken: refresh,
+		ExpiresAt:    uint64(time.Now().Add(time.Hour).UnixMilli()),
+		Scopes:       []string{"user:profile", "user:inference"},
+	}
+}
+
+// testAccount returns an oauthAccount entry as Claude writes it.
+func testAccount(email, uuid string) json.RawMessage {
+	return mustJSON(map[string]string{"emailAddress": email, "accountUuid": uuid})
+}
+
+// testJWT builds an unsigned JWT with the given claims.
+func testJWT(claims map[string]any) string {
+	enc := base64.RawURLEncoding
+	return enc.EncodeToString([]byte(`{"alg":"none"}`)) + "." + enc.EncodeToString(mustJSON(claims)) + ".sig"
+}
+
+func mustJSON(v any) []byte {
+	data, err := json.Marshal(v)
+	if err != nil {
+		panic(err)
+	}
+	return data
+}
+
+func (e *testEnv) writeFile(path string, data []byte) {
+	e.t.Helper()
+	if err := os.MkdirAll(filepath.Dir(path), 0o700); err != nil {
+		e.t.Fatal(err)
+	}
+	if err := os.WriteFile(path, data, 0o600); err != nil {
+		e.t.Fatal(err)
+	}
+}
+
+// claudeFiles returns what Claude's credentials file and ~/.claude.json hold
+// when logged in with creds and account.
+func claudeFiles(creds *OAuthCredentials, account json.RawMessage) (credentials, claudeJSON []byte) {
+	return mustJSON(map[string]any{"claudeAiOauth": creds}),
+		mustJSON(map[string]any{"oauthAccount": account, "numStartups": 1})
+}
+
+// login puts Claude in the state it is in after logging in with creds.
+func (e *testEnv) login(creds *OAuthCredentials, account json.RawMessage) {
+	credentials, claudeJSON := claudeFiles(creds, account)
+	e.writeFile(filepath.Join(e.claudeDir, ".credentials.json"), credentials)
+	e.writeFile(filepath.Join(e.home, ".claude.json"), claudeJSON)
+}
+
+// stubLogin sets what the stub claude's /login produces.
+func (e *testEnv) stubLogin(creds *OAuthCredentials, account json.RawMessage) {
+	credentials, claudeJSON := claudeFiles(creds, account)
+	e.writeFile(filepath.Join(e.root, "login-credentials.json"), credentials)
+	e.writeFile(filepath.Join(e.root, "login-claude.json"), claudeJSON)
+}
+
+// stubAPIKeyLogin makes the stub claude's /login sign in with an API key.
+func (e *testEnv) stubAPIKeyLogin(key string) {
+	e.writeFile(filepath.Join(e.root, "login-claude.json"), mustJSON(map[string]any{"primaryApiKey": key, "numStartups": 1}))
+	e.t.Setenv("STUB_LOGIN", "api-key")
+}
+
+// liveCredentials reads the OAuth credentials Claude would use.
+func (e *testEnv) liveCredentials() *OAuthCredentials {
+	e.t.Helper()
+	data, err := os.ReadFile(filepath.Join(e.claudeDir, ".credentials.json"))
+	if err != nil {
+		e.t.Fatal(err)
+	}
+	var doc struct {
+		ClaudeAiOauth *OAuthCredentials `json:"claudeAiOauth"`
+	}
+	if err := json.Unmarshal(data, &doc); err != nil {
+		e.t.Fatal(err)
+	}
+	return doc.ClaudeAiOauth
+}
+
+func (e *testEnv) profilePath(name string) string {
+	return filepath.Join(e.dataDir, "profiles", name+".json")
+}
+
+func (e *testEnv) hasProfile(name string) bool {
+	_, err := os.Stat(e.profilePath(name))
+	return err == nil
+}
+
+func (e *testEnv) readProfile(name string) *Profile {
+	e.t.Helper()
+	data, err := os.ReadFile(e.profilePath(name))
+	if err != nil {
+		e.t.Fatal(err)
+	}
+	var p Profile
+	if err := json.Unmarshal(data, &p); err != nil {
+		e.t.Fatal(err)
+	}
+	return &p
+}
+
+func (e *testEnv) writeProfile(name string, p *Profile) {
+	e.t.Helper()
+	e.writeFile(e.profilePath(name), mustJSON(p))
+}
+
+func (e *testEnv) readState() State {
+	e.t.Helper()
+	data, err := os.ReadFile(filepath.Join(e.dataDir, "state.json"))
+	if err != nil {
+		e.t.Fatal(err)
+	}
+	var state State
+	if err := json.Unmarshal(data, &state); err != nil {
+		e.t.Fatal(err)
+	}
+	return state
+}
+
+func (e *testEnv) writeState(state State) {
+	e.t.Helper()
+	e.writeFile(filepath.Join(e.dataDir, "state.json"), mustJSON(&state))
+}
+
+// tokenServer points refreshes at handler for the rest of the test.
+func (e *testEnv) tokenServer(handler http.HandlerFunc) {
+	srv := httptest.NewServer(handler)
+	e.t.Cleanup(srv.Close)
+	e.t.Setenv("CLAUDE_SWITCH_TOKEN_URL", srv.URL+"/token")
+}
+
+// grantTokens answers every refresh with new tokens valid for an hour.
+func grantTokens(access, refresh string) http.HandlerFunc {
+	return func(w http.ResponseWriter, r *http.Request) {
+		w.Header().Set("Content-Type", "application/json")
+		json.NewEncoder(w).Encode(map[string]any{
+			"access_token":  access,
+			"refresh_token": refresh,
+			"expires_in":    3600,
+		})
+	}
+}
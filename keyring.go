@@ -0,0 +1,208 @@
+package main
+
+import (
+	"encoding/base64"
+	"errors"
+	"fmt"
+	"io/fs"
+	"os"
+	"os/exec"
+	"slices"
+	"strings"
+)
+
+// keyringService names the OS keyring entries holding profiles, one per
+// profile keyed by its name. The secret is the profile file's bytes, base64
+// encoded so every keyring tool stores them verbatim.
+const keyringService = "claude-switch"
+
+// useKeyring is set by the --keyring global flag. It stores profiles saved
+// during this run in the OS keyring, as `storage keyring` does permanently.
+var useKeyring bool
+
+func keyringEnabled() bool {
+	state := loadState()
+	return useKeyring || state.Storage == "keyring"
+}
+
+// inKeyring reports whether a profile lives in the keyring rather than in
+// the profiles directory.
+func inKeyring(name string) bool {
+	state := loadState()
+	return slices.Contains(state.KeyringProfiles, name)
+}
+
+// profileStored reports whether either backend holds a profile, whether or
+// not it loads.
+func profileStored(name string) bool {
+	if inKeyring(name) {
+		return true
+	}
+	_, err := os.Stat(profilePath(name))
+	return err == nil
+}
+
+// profileLocation describes where a profile is stored, for info.
+func profileLocation(name string) string {
+	if inKeyring(name) {
+		return fmt.Sprintf("OS keyring (service %s)", keyringService)
+	}
+	return profilePath(name)
+}
+
+// readProfileData returns a profile's stored bytes from whichever backend
+// holds it. A missing file is reported as fs.ErrNotExist.
+func readProfileData(name string) ([]byte, error) {
+	if !inKeyring(name) {
+		return os.ReadFile(profilePath(name))
+	}
+	secret, err := keyringGet(name)
+	if err != nil {
+		return nil, err
+	}
+	return base64.StdEncoding.DecodeString(secret)
+}
+
+// writeProfileData saves a profile's bytes to the keyring when it is enabled
+// or already holds the profile, and to the profiles directory otherwise.
+func writeProfileData(name string, data []byte) error {
+	return storeProfileData(name, data, keyringEnabled() || inKeyring(name))
+}
+
+// storeProfileData saves a profile's bytes to the chosen backend and then
+// drops any copy in the other one, which is how profiles migrate.
+func storeProfileData(name string, data []byte, toKeyring bool) error {
+	if !toKeyring {
+		if err := writeSecure(profilePath(name), data); err != nil {
+			return err
+		}
+		if inKeyring(name) {
+			return deleteFromKeyring(name)
+		}
+		return nil
+	}
+
+	if err := requireWritable("saving profile '" + name + "' to the keyring"); err != nil {
+		return err
+	}
+	if err := keyringSet(name, base64.StdEncoding.EncodeToString(data)); err != nil {
+		return err
+	}
+	if err := indexKeyring(name, true); err != nil {
+		return err
+	}
+	if err := os.Remove(profilePath(name)); err != nil && !errors.Is(err, fs.ErrNotExist) {
+		return explainWriteError(profilePath(name), err)
+	}
+	return nil
+}
+
+// deleteProfileData removes a profile from whichever backend holds it.
+func deleteProfileData(name string) error {
+	if inKeyring(name) {
+		return deleteFromKeyring(name)
+	}
+	if err := os.Remove(profilePath(name)); err != nil {
+		return explainWriteError(profilePath(name), err)
+	}
+	return nil
+}
+
+func deleteFromKeyring(name string) error {
+	if err := requireWritable("deleting profile '" + name + "' from the keyring"); err != nil {
+		return err
+	}
+	if err := keyringDelete(name); err != nil {
+		return err
+	}
+	return indexKeyring(name, false)
+}
+
+// indexKeyring adds or removes a name in state.json's list of keyring
+// profiles, since keyrings can't be listed portably.
+func indexKeyring(name string, add bool) error {
+	return updateState(func(state *State) bool {
+		i := slices.Index(state.KeyringProfiles, name)
+		switch {
+		case add && i < 0:
+			state.KeyringProfiles = append(state.KeyringProfiles, name)
+			slices.Sort(state.KeyringProfiles)
+		case !add && i >= 0:
+			state.KeyringProfiles = slices.Delete(state.KeyringProfiles, i, i+1)
+		default:
+			return false
+		}
+		return true
+	})
+}
+
+// keyringError reports a failed keyring tool, preferring what it printed.
+func keyringError(action, name string, err error) error {
+	var exitErr *exec.ExitError
+	if errors.As(err, &exitErr) && len(exitErr.Stderr) > 0 {
+		return fmt.Errorf("%s profile '%s' in the keyring: %s", action, name, strings.TrimSpace(string(exitErr.Stderr)))
+	}
+	return fmt.Errorf("%s profile '%s' in the keyring: %w", action, name, err)
+}
+
+// cmdStorage shows or changes where profiles are stored. Without --migrate
+// existing profiles stay where they are and move as they are next saved.
+func cmdStorage(args []string) error {
+	parsed, err := parseArgs(args, "--migrate")
+	if err != nil {
+		return err
+	}
+	state := loadState()
+	if len(parsed.pos) == 0 {
+		backend := "file"
+		if state.Storage == "keyring" {
+			backend = "keyring"
+		}
+		fmt.Printf("%s (%d profile(s) in the keyring)\n", backend, len(state.KeyringProfiles))
+		return nil
+	}
+
+	backend := parsed.pos[0]
+	if backend != "file" && backend != "keyring" {
+		return fmt.Errorf("unknown storage '%s': expected file or keyring", backend)
+	}
+	if err := requireWritable("storage"); err != nil {
+		return err
+	}
+	state.Storage = backend
+	if backend == "file" {
+		state.Storage = ""
+	}
+	if err := saveState(&state); err != nil {
+		return err
+	}
+	where := "profiles directory"
+	if backend == "keyring" {
+		where = "OS keyring"
+	}
+	logf("New profiles will be stored in the %s\n", where)
+
+	if !parsed.has("--migrate") {
+		return nil
+	}
+	names, err := listProfiles()
+	if err != nil {
+		return err
+	}
+	moved := 0
+	for _, name := range names {
+		if inKeyring(name) == (backend == "keyring") {
+			continue
+		}
+		data, err := readProfileData(name)
+		if err != nil {
+			return fmt.Errorf("profile '%s': %w", name, err)
+		}
+		if err := storeProfileData(name, data, backend == "keyring"); err != nil {
+			return err
+		}
+		moved++
+	}
+	logf("Moved %d profile(s)\n", moved)
+	return nil
+}
@@ -0,0 +1,43 @@
+//go:build darwin
+
+package main
+
+import (
+	"errors"
+	"os/exec"
+	"strings"
+)
+
+// securityNotFound is the exit status of `security` when no item matches.
+const securityNotFound = 44
+
+func keyringGet(name string) (string, error) {
+	out, err := exec.Command("security", "find-generic-password",
+		"-s", keyringService, "-a", name, "-w").Output()
+	if err != nil {
+		return "", keyringError("reading", name, err)
+	}
+	return strings.TrimSpace(string(out)), nil
+}
+
+func keyringSet(name, secret string) error {
+	cmd := exec.Command("security", "add-generic-password",
+		"-U", "-s", keyringService, "-a", name, "-w", secret)
+	if _, err := cmd.Output(); err != nil {
+		return keyringError("saving", name, err)
+	}
+	return nil
+}
+
+func keyringDelete(name string) error {
+	_, err := exec.Command("security", "delete-generic-password",
+		"-s", keyringService, "-a", name).Output()
+	var exitErr *exec.ExitError
+	if errors.As(err, &exitErr) && exitErr.ExitCode() == securityNotFound {
+		return nil
+	}
+	if err != nil {
+		return keyringError("deleting", name, err)
+	}
+	return nil
+}
@@ -0,0 +1,54 @@
+//go:build !darwin
+
+package main
+
+import (
+	"fmt"
+	"os/exec"
+	"strings"
+)
+
+// Outside macOS the keyring is the Secret Service (GNOME Keyring, KWallet),
+// reached through libsecret's secret-tool.
+func secretTool(args ...string) (*exec.Cmd, error) {
+	if _, err := exec.LookPath("secret-tool"); err != nil {
+		return nil, fmt.Errorf("the keyring needs secret-tool (from libsecret) in PATH")
+	}
+	return exec.Command("secret-tool", args...), nil
+}
+
+func keyringGet(name string) (string, error) {
+	cmd, err := secretTool("lookup", "service", keyringService, "profile", name)
+	if err != nil {
+		return "", err
+	}
+	out, err := cmd.Output()
+	if err != nil {
+		return "", keyringError("reading", name, err)
+	}
+	return strings.TrimSpace(string(out)), nil
+}
+
+func keyringSet(name, secret string) error {
+	cmd, err := secretTool("store", "--label", "claude-switch profile "+name,
+		"service", keyringService, "profile", name)
+	if err != nil {
+		return err
+	}
+	cmd.Stdin = strings.NewReader(secret)
+	if _, err := cmd.Output(); err != nil {
+		return keyringError("saving", name, err)
+	}
+	return nil
+}
+
+func keyringDelete(name string) error {
+	cmd, err := secretTool("clear", "service", keyringService, "profile", name)
+	if err != nil {
+		return err
+	}
+	if _, err := cmd.Output(); err != nil {
+		return keyringError("deleting", name, err)
+	}
+	return nil
+}
@@ -0,0 +1,293 @@
+package main
+
+import (
+	"encoding/json"
+	"strings"
+	"testing"
+)
+
+// listRow returns the line of list's table that shows name.
+func listRow(t *testing.T, out, name string) string {
+	t.Helper()
+	for _, line := range strings.Split(out, "\n") {
+		fields := strings.Fields(line)
+		if len(fields) > 0 && fields[0] == "*" {
+			fields = fields[1:]
+		}
+		if len(fields) > 0 && fields[0] == name {
+			return line
+		}
+	}
+	t.Fatalf("list shows no row for %q:\n%s", name, out)
+	return ""
+}
+
+func TestListShowTokenAge(t *testing.T) {
+	e := newTestEnv(t)
+	refreshedAt := nowMs() - 3*60*60*1000
+	e.writeProfile("old", &Profile{Type: "oauth", Credentials: testCreds("old-access", "old-refresh"), LastRefreshedAt: &refreshedAt})
+	e.writeProfile("never", &Profile{Type: "oauth", Credentials: testCreds("never-access", "never-refresh")})
+
+	if out := e.mustRun("list").stdout; strings.Contains(out, "AGE") {
+		t.Errorf("list shows an AGE column without --show-token-age:\n%s", out)
+	}
+	out := e.mustRun("list", "--show-token-age").stdout
+	if !strings.Contains(out, "AGE") {
+		t.Errorf("list --show-token-age has no AGE column:\n%s", out)
+	}
+	if row := strings.TrimSpace(listRow(t, out, "old")); !strings.HasSuffix(row, " 3h") {
+		t.Errorf("a token refreshed 3 hours ago shows %q, want an age of 3h", row)
+	}
+	if row := strings.TrimSpace(listRow(t, out, "never")); !strings.HasSuffix(row, " -") {
+		t.Errorf("a token claude-switch never refreshed shows %q, want an age of -", row)
+	}
+}
+
+func TestListShowRole(t *testing.T) {
+	e := newTestEnv(t)
+	e.writeProfile("work", &Profile{
+		Type:        "oauth",
+		Credentials: testCreds("work-access", "work-refresh"),
+		Account: mustJSON(map[string]string{
+			"emailAddress":     "me@acme.com",
+			"organizationName": "Acme",
+			"organizationRole": "admin",
+		}),
+	})
+
+	if out := e.mustRun("list").stdout; strings.Contains(out, "ROLE") || strings.Contains(out, "admin") {
+		t.Errorf("list shows roles without --show-role:\n%s", out)
+	}
+	out := e.mustRun("list", "--show-role").stdout
+	if !strings.Contains(out, "ROLE") {
+		t.Errorf("list --show-role has no ROLE column:\n%s", out)
+	}
+	if fields := strings.Fields(listRow(t, out, "work")); len(fields) < 5 || fields[4] != "admin" {
+		t.Errorf("list --show-role row is %q, want admin right after the org", fields)
+	}
+}
+
+func TestListSkipInvalid(t *testing.T) {
+	e := newTestEnv(t)
+	e.writeProfile("work", &Profile{Type: "oauth", Credentials: testCreds("work-access", "work-refresh")})
+	e.writeFile(e.profilePath("backup"), []byte("{not a profile"))
+
+	out := e.mustRun("list").stdout
+	if row := listRow(t, out, "backup"); !strings.Contains(row, "error") {
+		t.Errorf("list shows the invalid file as %q, want an error row", row)
+	}
+	out = e.mustRun("list", "--skip-invalid").stdout
+	if strings.Contains(out, "backup") {
+		t.Errorf("list --skip-invalid still shows the invalid file:\n%s", out)
+	}
+	listRow(t, out, "work")
+}
+
+func TestListLimit(t *testing.T) {
+	e := newTestEnv(t)
+	for _, name := range []string{"a", "b", "c", "d"} {
+		e.writeProfile(name, &Profile{Type: "oauth", Credentials: testCreds(name+"-access", name+"-refresh")})
+	}
+	e.mustRun("use", "b")
+	if used := e.readState().LastUsed["b"]; used == 0 {
+		t.Error("use didn't record when the profile was last used")
+	}
+	active := "a"
+	e.writeState(State{ActiveProfile: &active, LastUsed: map[string]uint64{"b": 100, "c": 300, "d": 200}})
+
+	out := e.mustRun("list", "--limit", "1").stdout
+	listRow(t, out, "a")
+	listRow(t, out, "c")
+	for _, name := range []string{"b", "d"} {
+		if strings.Contains(out, " "+name+" ") {
+			t.Errorf("list --limit 1 shows %s, which was used less recently than c:\n%s", name, out)
+		}
+	}
+	if !strings.Contains(out, "... and 2 more") {
+		t.Errorf("list --limit 1 doesn't count the hidden profiles:\n%s", out)
+	}
+	if r := e.run("", "list", "--limit", "-1"); r.code != exitError {
+		t.Errorf("list --limit -1 exited with %d, want %d", r.code, exitError)
+	}
+}
+
+func TestListErrorsOnly(t *testing.T) {
+	e := newTestEnv(t)
+	e.writeProfile("work", &Profile{Type: "oauth", Credentials: testCreds("work-access", "work-refresh")})
+	if r := e.mustRun("list", "--errors-only"); r.stdout != "" || !strings.Contains(r.stderr, "All profiles load cleanly") {
+		t.Errorf("list --errors-only with no broken profiles printed %q, stderr %q", r.stdout, r.stderr)
+	}
+
+	e.writeFile(e.profilePath("broken"), []byte("{not a profile"))
+	r := e.run("", "list", "--errors-only")
+	if r.code != exitError || !strings.Contains(r.stderr, "1 profile(s) failed to load") {
+		t.Errorf("list --errors-only with a broken profile exited with %d (stderr %q), want a failure counting it", r.code, r.stderr)
+	}
+	if !strings.Contains(r.stdout, "ERROR") || !strings.HasPrefix(listRow(t, r.stdout, "broken"), "broken ") {
+		t.Errorf("list --errors-only doesn't show the broken profile:\n%s", r.stdout)
+	}
+	if strings.Contains(r.stdout, "work") {
+		t.Errorf("list --errors-only shows a profile that loads:\n%s", r.stdout)
+	}
+}
+
+func TestListMaxWidth(t *testing.T) {
+	e := newTestEnv(t)
+	long := "someone.with.a.very.long.address@subsidiary.example.com"
+	e.writeProfile("work", &Profile{Type: "oauth", Credentials: testCreds("work-access", "work-refresh"), Account: testAccount(long, "11111111-1111-1111-1111-111111111111")})
+
+	email := func(args ...string) string {
+		fields := strings.Fields(listRow(t, e.mustRun(append([]string{"list"}, args...)...).stdout, "work"))
+		return fields[2]
+	}
+	if got := email(); got != long[:31]+"…" {
+		t.Errorf("list shows the email as %q, want it cut to 32 characters", got)
+	}
+	if got := email("--max-width", "10"); got != long[:9]+"…" {
+		t.Errorf("list --max-width 10 shows the email as %q, want it cut to 10 characters", got)
+	}
+	if got := email("--max-width", "0"); got != long {
+		t.Errorf("list --max-width 0 shows the email as %q, want it in full", got)
+	}
+}
+
+func TestListNDJSON(t *testing.T) {
+	e := newTestEnv(t)
+	e.writeProfile("work", &Profile{Type: "oauth", Credentials: testCreds("work-access", "work-refresh"), Account: testAccount("me@acme.com", "11111111-1111-1111-1111-111111111111")})
+	e.writeProfile("ci", &Profile{Type: "api_key", ApiKey: "sk-ant-api03-test"})
+	e.writeFile(e.profilePath("broken"), []byte("{not a profile"))
+	e.mustRun("use", "work")
+
+	var got []listRecord
+	for _, line := range strings.Split(strings.TrimSpace(e.mustRun("list", "--ndjson").stdout), "\n") {
+		var r listRecord
+		if err := json.Unmarshal([]byte(line), &r); err != nil {
+			t.Fatalf("list --ndjson printed a line that isn't one JSON object: %q", line)
+		}
+		got = append(got, r)
+	}
+	if len(got) != 3 {
+		t.Fatalf("list --ndjson printed %d records, want 3: %+v", len(got), got)
+	}
+	broken, ci, work := got[0], got[1], got[2]
+	if broken.Name != "broken" || broken.Status != "error" || broken.Error == "" {
+		t.Errorf("broken profile record = %+v, want status error with the reason", broken)
+	}
+	if ci.Name != "ci" || ci.Type != "api_key" || ci.Active {
+		t.Errorf("ci record = %+v", ci)
+	}
+	if work.Name != "work" || work.Status != "ok" || !work.Active || work.Email != "me@acme.com" || work.ExpiresAt == nil {
+		t.Errorf("work record = %+v, want the active profile with its email and expiry", work)
+	}
+
+	for _, args := range [][]string{{"list", "--ndjson", "--json"}, {"list", "--ndjson", "--limit", "1"}} {
+		if r := e.run("", args...); r.code != exitError {
+			t.Errorf("%v exited with %d, want %d", args, r.code, exitError)
+		}
+	}
+}
+
+func TestDisableHidesProfile(t *testing.T) {
+	e := newTestEnv(t)
+	e.tokenServer(grantTokens("new-access", "new-refresh"))
+	for _, name := range []string{"old", "work"} {
+		e.writeProfile(name, &Profile{Type: "oauth", Credentials: testCreds(name+"-access", name+"-refresh")})
+	}
+	if r := e.run("", "disable", "nope"); r.code != exitNotFound {
+		t.Errorf("disable of a missing profile exited with %d, want %d", r.code, exitNotFound)
+	}
+
+	e.mustRun("disable", "old")
+	if out := e.mustRun("list").stdout; strings.Contains(out, "old") {
+		t.Errorf("list shows a disabled profile:\n%s", out)
+	}
+	if row := listRow(t, e.mustRun("list", "--all").stdout, "old"); !strings.Contains(row, "(disabled)") {
+		t.Errorf("list --all shows the disabled profile as %q, want it marked", row)
+	}
+	e.mustRun("rotate", "--all")
+	if got := e.readProfile("old").Credentials.RefreshToken; got != "old-refresh" {
+		t.Errorf("rotate --all rotated a disabled profile to %s", got)
+	}
+	if got := e.readProfile("work").Credentials.RefreshToken; got != "new-refresh" {
+		t.Errorf("rotate --all left work's refresh token at %s", got)
+	}
+	// A disabled profile can still be used by name
+	e.mustRun("use", "old")
+
+	e.mustRun("enable", "old")
+	listRow(t, e.mustRun("list").stdout, "old")
+}
+
+func TestNickname(t *testing.T) {
+	e := newTestEnv(t)
+	e.writeProfile("work", &Profile{Type: "oauth", Credentials: testCreds("work-access", "work-refresh")})
+	e.mustRun("use", "work")
+
+	e.mustRun("nick", "work", "Work", "Max")
+	if row := listRow(t, e.mustRun("list").stdout, "Work"); !strings.Contains(row, "Work Max (work)") {
+		t.Errorf("list shows the nicknamed profile as %q, want the nickname next to the name", row)
+	}
+	if r := e.mustRun("prompt"); r.stdout != "Work Max\n" {
+		t.Errorf("prompt printed %q, want the nickname", r.stdout)
+	}
+	// Commands still take the plain name
+	e.mustRun("use", "work")
+
+	e.mustRun("nick", "work")
+	if r := e.mustRun("prompt"); r.stdout != "work\n" {
+		t.Errorf("after clearing the nickname, prompt printed %q, want work", r.stdout)
+	}
+	if r := e.run("", "nick", "nope", "x"); r.code != exitNotFound {
+		t.Errorf("nick of a missing profile exited with %d, want %d", r.code, exitNotFound)
+	}
+}
+
+func TestListEscapesControlCharacters(t *testing.T) {
+	e := newTestEnv(t)
+	// Created behind claude-switch's back, as import refuses such names
+	e.writeProfile("evil\x1b[2J", &Profile{Type: "oauth", Credentials: testCreds("evil-access", "evil-refresh")})
+	e.login(testCreds("work-access", "work-refresh"), testAccount("me@acme.com", "11111111-1111-1111-1111-111111111111"))
+	if r := e.run("", "import", "evil\x1b[2J"); r.code == 0 || !strings.Contains(r.stderr, "control characters are not allowed") {
+		t.Errorf("import of a name with an escape sequence exited with %d (stderr %q), want it refused", r.code, r.stderr)
+	}
+
+	for _, args := range [][]string{{"list"}, {"list", "--color=always"}} {
+		out := e.mustRun(args...).stdout
+		if strings.Contains(out, "\x1b[2J") {
+			t.Errorf("%v printed the name's escape sequence raw:\n%q", args, out)
+		}
+		if !strings.Contains(out, `evil\x1b[2J`) {
+			t.Errorf("%v doesn't show the escaped name:\n%q", args, out)
+		}
+	}
+}
+
+func TestListFlagsDriftFromLiveCredentials(t *testing.T) {
+	e := newTestEnv(t)
+	const uuid = "11111111-1111-1111-1111-111111111111"
+	e.login(testCreds("work-access", "work-refresh"), testAccount("me@acme.com", uuid))
+	e.mustRun("import", "work")
+	const warning = "Claude's live credentials don't belong to the active profile 'work'"
+
+	tests := []struct {
+		when    string
+		creds   *OAuthCredentials
+		account string
+		drift   bool
+	}{
+		{"Claude refreshed the token itself", testCreds("claude-access", "work-refresh"), uuid, false},
+		{"the same account logged in again", testCreds("again-access", "again-refresh"), uuid, false},
+		{"another account logged in directly", testCreds("other-access", "other-refresh"), "22222222-2222-2222-2222-222222222222", true},
+	}
+	for _, tt := range tests {
+		e.login(tt.creds, testAccount("me@acme.com", tt.account))
+		r := e.mustRun("list")
+		row := listRow(t, r.stdout, "work")
+		if drift := strings.Contains(r.stderr, warning); drift != tt.drift {
+			t.Errorf("when %s, list warned about drift: %v, want %v\nstderr: %s", tt.when, drift, tt.drift, r.stderr)
+		}
+		if marked := strings.Contains(row, "oauth?"); marked != tt.drift {
+			t.Errorf("when %s, list shows the active row as %q", tt.when, row)
+		}
+	}
+}
@@ -0,0 +1,134 @@
+package main
+
+import (
+	"fmt"
+	"os"
+	"path/filepath"
+	"sync"
+	"time"
+)
+
+// defaultLockTimeoutMs is how long a command waits for another run to let
+// go of the lock, unless CLAUDE_SWITCH_LOCK_TIMEOUT says otherwise.
+const defaultLockTimeoutMs = 10000
+
+const lockPoll = 100 * time.Millisecond
+
+// lockedCommands change profiles, state or Claude's credential files, so two
+// of them running at once could leave those inconsistent. They hold the lock
+// for their whole run. Other commands take it through withLock only while
+// they write, such as when exec refreshes a token or list --refresh does.
+var lockedCommands = map[string]bool{
+	"add":         true,
+	"import":      true,
+	"import-dir":  true,
+	"import-url":  true,
+	"use":         true,
+	"auto":        true,
+	"back":        true,
+	"revert":      true,
+	"remove":      true,
+	"prune":       true,
+	"rename":      true,
+	"nick":        true,
+	"label":       true,
+	"set-default": true,
+	"disable":     true,
+	"enable":      true,
+	"refresh":     true,
+	"rotate":      true,
+	"trash":       true,
+	"lock":        true,
+	"unlock":      true,
+	"storage":     true,
+	"restore":     true,
+	"purge":       true,
+}
+
+// lockFile keeps the locked file open, and so locked, while lockHolders is
+// above zero. A command in lockedCommands never lets go, so it holds the lock
+// until the process exits, however it exits. lockMu guards both, as batch
+// commands refresh profiles in parallel.
+var (
+	lockMu      sync.Mutex
+	lockFile    *os.File
+	lockHolders int
+)
+
+func lockPath() string {
+	return filepath.Join(configDir(), "lock")
+}
+
+// holdLock takes the advisory lock shared by every claude-switch run, waiting
+// for another run to finish if need be, unless this run already holds it.
+// Each call must be matched by releaseLock for the lock to be let go.
+func holdLock() error {
+	lockMu.Lock()
+	defer lockMu.Unlock()
+	if lockFile == nil {
+		if err := acquireLock(); err != nil {
+			return err
+		}
+	}
+	lockHolders++
+	return nil
+}
+
+func releaseLock() {
+	lockMu.Lock()
+	defer lockMu.Unlock()
+	lockHolders--
+	if lockHolders == 0 {
+		lockFile.Close()
+		lockFile = nil
+	}
+}
+
+// withLock runs fn holding the lock, taking it for just that long if the
+// command doesn't hold it already. With --no-state-write nothing is written,
+// so the lock file isn't created either.
+func withLock(fn func() error) error {
+	if noStateWrite {
+		return fn()
+	}
+	if err := holdLock(); err != nil {
+		return err
+	}
+	defer releaseLock()
+	return fn()
+}
+
+func acquireLock() error {
+	path := lockPath()
+	if err := ensureDir(filepath.Dir(path)); err != nil {
+		return err
+	}
+	f, err := os.OpenFile(path, os.O_RDWR|os.O_CREATE, 0o600)
+	if err != nil {
+		return explainWriteError(path, err)
+	}
+
+	timeout := envDurationMs("CLAUDE_SWITCH_LOCK_TIMEOUT", defaultLockTimeoutMs)
+	deadline := time.Now().Add(time.Duration(timeout) * time.Millisecond)
+	waiting := false
+	for {
+		ok, err := tryLock(f)
+		if err != nil {
+			f.Close()
+			return fmt.Errorf("locking %s: %w", path, err)
+		}
+		if ok {
+			lockFile = f
+			return nil
+		}
+		if time.Now().After(deadline) {
+			f.Close()
+			return fmt.Errorf("another claude-switch command has held %s for over %s; try again once it finishes", path, formatDuration(timeout))
+		}
+		if !waiting {
+			logf("Waiting for another claude-switch command to finish...\n")
+			waiting = true
+		}
+		time.Sleep(lockPoll)
+	}
+}
@@ -0,0 +1,11 @@
+//go:build !unix
+
+package main
+
+import "os"
+
+// tryLock always succeeds: the standard library has no file locking here,
+// so concurrent runs aren't kept apart on these platforms.
+func tryLock(_ *os.File) (bool, error) {
+	return true, nil
+}
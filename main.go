@@ -1,10 +1,22 @@
 package main
 
 import (
+	"bufio"
+	"crypto/sha256"
+	"encoding/hex"
 	"encoding/json"
+	"errors"
 	"fmt"
+	"io"
+	"maps"
+	"net/url"
 	"os"
 	"os/exec"
+	"os/signal"
+	"path/filepath"
+	"slices"
+	"sort"
+	"strconv"
 	"strings"
 	"syscall"
 	"text/tabwriter"
@@ -17,377 +29,2965 @@ Usage: claude-switch <command> [arguments]
 
 Commands:
   add <name>              Add a new profile (logs out, launches auth flow, imports result)
+      [--expect-email <e>]  Refuse to save unless the login was for this email
+      [--default-command <cmd>]
+                          Command for 'exec <name>' to run when none is given
+      [--with-ui-prefs]   Save Claude's theme and UI settings, re-applied on use
+      [--no-activate]     Save the profile, then switch back to the previously active one
+      [--capture-only]    Save the profile, then restore Claude's previous login exactly,
+                          even if the add fails
+      [--verify]          Check the credentials against the API and refuse to save if rejected
   import <name>           Import currently active Claude Code credentials as a named profile
-  use <name> [-k|--kill]  Switch to a named profile (--kill terminates running Claude sessions)
+      [--fetch-account]   Look up missing email/org info from the API
+      [--default-command <cmd>]
+                          Command for 'exec <name>' to run when none is given
+      [--with-ui-prefs]   Save Claude's theme and UI settings, re-applied on use
+      [--no-activate]     Save the profile without marking it active
+      [--workspace <id>]  For an API key, the Console workspace 'exec' sets as ANTHROPIC_WORKSPACE
+      [--from-file <path>]
+                          Import credentials copied from another machine instead (not activated)
+      [--from-stdin]      Same, reading the JSON from stdin
+      [--verify]          Check the credentials against the API and refuse to save if rejected
+  import-dir <dir>        Import each subdirectory of <dir> holding a Claude config as a profile
+      [--prefix <p>]      Prepend <p> to the subdirectory names
+  import-url <name> <url> Download a profile JSON over HTTPS and save it as <name>
+      [--sha256 <hex>]    Refuse it unless the download has this SHA-256 checksum
+  use [<name>] [-k|--kill]
+                          Switch to a named profile (--kill terminates running Claude
+                          sessions); without a name, pick one from a menu on a terminal,
+                          or else switch to the default one
+      [--for <duration>]  Switch temporarily, reverting to the previous profile afterwards
+      [--restore-previous]
+                          Switch back to the profile that was active before the last switch
+      [--write-key]       For API key profiles, store the key in Claude's config
+      [--show-key]        For API key profiles, print the key unmasked
+      [--no-hint]         For API key profiles, skip the usage guidance
+      [--prefer <auth>]   For profiles with both, write "oauth" or "api_key" and clear the other
+  back [-k|--kill]        Same as use --restore-previous
+  revert                  End a temporary switch now and restore the previous profile
   list                    List all profiles
-  remove <name>           Remove a profile
-  exec <name> -- <cmd>    Run a command with a profile's credentials injected
+      [--show-role]       Add each account's role in its organization
+      [--show-token-age]  Add the time since each token was last refreshed
+      [--absolute]        Show expiry as a UTC timestamp instead of relative to now
+      [--color <when>]    Colour the table: auto (default, only on a terminal and
+                          without NO_COLOR), always or never
+      [--filter <expr>]   Only show matching profiles, e.g. "type==oauth && org~=acme"
+      [--skip-invalid]    Hide files in the profiles directory that aren't valid profiles
+      [--limit <n>]       Show the active profile plus the n most recently used others
+      [--all]             Include disabled profiles
+      [--refresh]         Refresh expired tokens first (the only list option using the network)
+      [-j|--parallel <n>] With --refresh, make up to n token requests at once (default 4)
+      [--errors-only]     Show only profiles that fail to load, and why
+      [--json]            Print the profiles as a JSON array
+      [--ndjson]          Print one JSON object per profile, as each is loaded
+      [--max-width <n>]   Shorten emails and org names beyond n characters
+                          (default 32, 0 for no limit)
+      [--watch]           Redraw the list until Ctrl-C, every --interval (default 5s);
+                          with --refresh, refresh at most every --refresh-every (default 1m)
+  disable <name>          Hide a profile from list and batch commands; it still works with use
+  enable <name>           Undo disable
+  nick <name> [<text>]    Show <text> in list and prompt instead of the name (no text clears it)
+  label <name> [<text>]   Attach a note to a profile, shown by list and info (no text clears it)
+  set-default [<name>]    Set the profile 'use' switches to when given no name (no name shows it)
+      [--clear]           Unset the default profile
+  auto [-k|--kill]        Switch to the profile named in the nearest .claude-switch file,
+                          or the default profile
+  current|whoami          Show the active profile and check it matches Claude's live login
+  summary                 Count profiles by type and health, without using the network
+      [--json]            Print the counts as a single JSON object
+  info|show <name>        Show everything stored about a profile, with secrets masked
+      [--json]            Print it as JSON
+      [--show-secrets]    Include tokens and keys in full
+  rename <old> <new>      Rename a profile
+  remove <name>...        Remove profiles (they are kept in the trash for 30 days)
+  prune                   Remove profiles that fail to load or whose refresh token was revoked,
+                          refreshing expired ones to find out
+      [--dry-run]         Only list what would be removed
+      [--yes]             Don't ask for confirmation
+      [-j|--parallel <n>] Make up to n token requests at once (default 4)
+  trash list              List removed profiles
+  trash restore <name>    Restore the most recently removed copy of a profile
+  trash empty             Permanently delete everything in the trash
+  refresh <name>...       Refresh profiles' tokens without switching to them
+      [--if-needed]       Only if the token has expired or is about to
+      [-j|--parallel <n>] Make up to n token requests at once (default 4)
+  rotate <name>...        Refresh now to obtain a new refresh token, retiring the old one
+      [--all]             Rotate every enabled OAuth profile
+      [-j|--parallel <n>] Make up to n token requests at once (default 4)
+  exec <name> [-- <cmd>]  Run a command with a profile's credentials injected
+                          (without <cmd>, runs the profile's default command)
+      [--timeout <duration>]
+                          Stop the command (SIGTERM, then SIGKILL) if it runs longer
+  prompt                  Print the active profile's name for a shell prompt
+      [--exit-code]       Print nothing; exit 0 if healthy, 1 if none is active, 2 if expired
+  token <name>            Print just the access token or API key, refreshing it if needed
+      [--no-refresh]      Print the stored token even if it has expired
+  env <name>              Print the variable exec would set, refreshing the token if needed
+      [--format <f>]      shell (default), fish or dotenv
+  activate <name>         Print shell code that sets a profile's variable and unsets the others,
+                          for eval "$(claude-switch activate <name>)"
+      [--shell <shell>]   bash, zsh, sh, dash, ksh or fish (default: from $SHELL)
+  export <name> [file]    Write a profile as JSON to <file> (mode 0600) or stdout
+      [--redact]          Replace tokens and keys with placeholders, for bug reports
+      [--include-secrets] Keep the secrets without warning, to move the profile elsewhere
+  lock                    Encrypt stored profiles with a passphrase
+  unlock                  Decrypt stored profiles again
+  storage [file|keyring]  Show or set where new profiles are stored
+      [--migrate]         Also move existing profiles to that backend
+  backup <file>           Write every profile and the state to one file (mode 0600)
+  restore <file>          Restore the profiles in a backup file
+      [--overwrite]       Replace profiles that already exist
+  purge                   Delete all claude-switch data: profiles, trash and state
+      [--yes]             Don't ask for confirmation
+      [--logout]          Also log Claude out by clearing its credentials
+  completions <shell>     Print a completion script for bash, zsh or fish
+  doctor                  Check claude-switch's files and setup for problems
+      [--fix]             Correct the problems that can be fixed automatically
+  validate                Check every profile offline and fail if any is invalid
+
+Batch commands (remove, rotate) read profile names from stdin, one per line,
+when given '-' as a name.
+
+Global flags:
+  --no-state-write        Refuse to write any file; mutating commands fail instead
+  --strict-json           Reject profile files containing unrecognised fields
+  --force                 Edit Claude config files even if they don't look like Claude's,
+                          and save an account already saved under another name
+  --keyring               Store profiles saved by this command in the OS keyring
+  --config-dir <dir>      Keep claude-switch's data in <dir> (or set CLAUDE_SWITCH_HOME)
+  -q, --quiet             Don't print status messages; warnings and errors still show
+  -v, --verbose           Also print the paths in use and the status of HTTP requests
+  --json-errors           On failure, print {"error": ..., "kind": ...} on stdout instead
+
+Exit codes:
+  1    General error
+  3    Profile not found
+  4    Refresh token revoked and re-authentication failed or is needed
+  5    Network error while refreshing a token
+  124  exec --timeout ran out and the command was stopped
 `
 
+// Exit codes, so scripts can tell failure modes apart.
+const (
+	exitError    = 1
+	exitNotFound = 3
+	exitReauth   = 4
+	exitNetwork  = 5
+	exitTimeout  = 124
+)
+
+// skipStartupChecks lists the commands that don't first end an expired
+// `use --for` switch or look for credentials changed behind claude-switch's
+// back: revert itself, and those run from prompts and shell completion,
+// which must stay fast, quiet and offline.
+var skipStartupChecks = map[string]bool{
+	"revert":               true,
+	"prompt":               true,
+	"completions":          true,
+	"__complete":           true,
+	"__list_profile_names": true,
+}
+
 func main() {
-	if len(os.Args) < 2 {
+	args := parseGlobalFlags(os.Args[1:])
+	if len(args) < 1 {
 		fmt.Fprint(os.Stderr, usage)
 		os.Exit(1)
 	}
 
+	debugf("claude-switch data: %s\n", configDir())
+	debugf("Claude credentials: %s\n", credentialsPath())
+	debugf("Claude config: %s\n", claudeJSONPath())
+
+	// Taken before the startup checks, as reverting a switch writes too
+	if !noStateWrite && lockedCommands[args[0]] {
+		if err := holdLock(); err != nil {
+			fail(err)
+		}
+	}
+
+	// There is no daemon to end `use --for` switches, so every invocation
+	// first checks whether one has run out.
+	if !noStateWrite && !skipStartupChecks[args[0]] {
+		if err := revertExpiredSwitch(); err != nil {
+			fmt.Fprintf(os.Stderr, "Warning: failed to revert temporary switch: %v\n", err)
+		}
+	}
+	if !skipStartupChecks[args[0]] {
+		warnIfCredentialsDrifted()
+	}
+
 	var err error
-	switch os.Args[1] {
+	switch args[0] {
 	case "add":
-		err = requireName("add", cmdAdd)
+		err = cmdAdd(args[1:])
 	case "import":
-		err = requireName("import", cmdImport)
+		err = cmdImport(args[1:])
 	case "use":
-		kill := false
-		args := os.Args[2:]
-		filtered := args[:0]
-		for _, a := range args {
-			if a == "--kill" || a == "-k" {
-				kill = true
-			} else {
-				filtered = append(filtered, a)
-			}
-		}
-		if len(filtered) == 0 {
-			err = fmt.Errorf("use requires a profile name")
-		} else {
-			err = cmdUse(filtered[0], kill)
-		}
+		err = cmdUse(args[1:])
+	case "back":
+		err = cmdUse(append([]string{"--restore-previous"}, args[1:]...))
+	case "revert":
+		err = cmdRevert()
 	case "list":
-		err = cmdList()
+		err = cmdList(args[1:])
 	case "remove":
-		err = requireName("remove", cmdRemove)
+		err = cmdRemove(args[1:])
+	case "prune":
+		err = cmdPrune(args[1:])
 	case "exec":
-		err = cmdExec()
+		err = cmdExec(args[1:])
+	case "token":
+		err = cmdToken(args[1:])
+	case "env":
+		err = cmdEnv(args[1:])
+	case "activate":
+		err = cmdActivate(args[1:])
+	case "export":
+		err = cmdExport(args[1:])
+	case "prompt":
+		err = cmdPrompt(args[1:])
+	case "info", "show":
+		err = cmdInfo(args[1:])
+	case "summary":
+		err = cmdSummary(args[1:])
+	case "current", "whoami":
+		err = cmdCurrent()
+	case "disable", "enable":
+		err = cmdSetDisabled(args)
+	case "nick":
+		err = cmdNick(args[1:])
+	case "set-default":
+		err = cmdSetDefault(args[1:])
+	case "auto":
+		err = cmdAuto(args[1:])
+	case "label":
+		err = cmdLabel(args[1:])
+	case "rename":
+		err = cmdRename(args[1:])
+	case "import-dir":
+		err = cmdImportDir(args[1:])
+	case "import-url":
+		err = cmdImportURL(args[1:])
+	case "rotate":
+		err = cmdRotate(args[1:])
+	case "refresh":
+		err = cmdRefresh(args[1:])
+	case "trash":
+		err = cmdTrash(args[1:])
+	case "doctor":
+		err = cmdDoctor(args[1:])
+	case "validate":
+		err = cmdValidate(args[1:])
+	case "backup":
+		err = cmdBackup(args[1:])
+	case "restore":
+		err = cmdRestore(args[1:])
+	case "purge":
+		err = cmdPurge(args[1:])
+	case "lock":
+		err = cmdLock()
+	case "unlock":
+		err = cmdUnlock()
+	case "storage":
+		err = cmdStorage(args[1:])
+	case "completions":
+		err = cmdCompletions(args[1:])
+	case "__complete", "__list_profile_names":
+		err = cmdComplete(args[1:])
 	case "-h", "--help", "help":
 		fmt.Fprint(os.Stderr, usage)
 		os.Exit(0)
 	default:
-		fmt.Fprintf(os.Stderr, "unknown command: %s\n\n%s", os.Args[1], usage)
+		fmt.Fprintf(os.Stderr, "unknown command: %s\n\n%s", args[0], usage)
 		os.Exit(1)
 	}
 
+	// A command run by exec has already reported its own failure
+	var childErr *childExitError
+	if errors.As(err, &childErr) {
+		os.Exit(childErr.code)
+	}
 	if err != nil {
+		fail(err)
+	}
+}
+
+// jsonErrors is set by the --json-errors global flag.
+var jsonErrors bool
+
+// fail reports err and exits with its exit code. With --json-errors the
+// report is a JSON object on stdout, whose kind scripts can branch on.
+func fail(err error) {
+	if jsonErrors {
+		out, _ := json.Marshal(map[string]string{"error": err.Error(), "kind": errorKind(err)})
+		fmt.Println(string(out))
+	} else {
 		fmt.Fprintf(os.Stderr, "error: %v\n", err)
-		os.Exit(1)
 	}
+	os.Exit(exitCode(err))
+}
+
+// errorKind names the kind of failure for --json-errors. It is finer grained
+// than the exit code.
+func errorKind(err error) string {
+	var re *RefreshError
+	switch {
+	case errors.Is(err, errNotFound):
+		return "profile_not_found"
+	case errors.Is(err, errExists):
+		return "profile_exists"
+	case errors.Is(err, errInvalidName):
+		return "invalid_name"
+	case errors.As(err, &re) && re.Kind == refreshInvalidGrant:
+		return "reauth_required"
+	case errors.As(err, &re):
+		return "refresh_failed"
+	case errors.Is(err, errTimeout):
+		return "timeout"
+	}
+	return "error"
+}
+
+func exitCode(err error) int {
+	var re *RefreshError
+	switch {
+	case errors.Is(err, errNotFound):
+		return exitNotFound
+	case errors.As(err, &re) && re.Kind == refreshInvalidGrant:
+		return exitReauth
+	case errors.As(err, &re) && re.Kind == refreshNetwork:
+		return exitNetwork
+	case errors.Is(err, errTimeout):
+		return exitTimeout
+	}
+	return exitError
+}
+
+// parseGlobalFlags strips the flags that apply to every command. It stops at
+// "--", and at the command exec runs, which starts at the first word after
+// the profile name, so the arguments of an exec'd command are left alone.
+func parseGlobalFlags(args []string) []string {
+	var rest []string
+	command := ""
+	sawExecName := false
+	for i := 0; i < len(args); i++ {
+		a := args[i]
+		if a == "--" {
+			return append(rest, args[i:]...)
+		}
+		if command == "exec" && !strings.HasPrefix(a, "-") && args[i-1] != "--timeout" {
+			if sawExecName {
+				return append(rest, args[i:]...)
+			}
+			sawExecName = true
+		}
+		if dir, ok := strings.CutPrefix(a, "--config-dir="); ok {
+			configDirFlag = dir
+			continue
+		}
+		switch a {
+		case "--config-dir":
+			if i+1 < len(args) {
+				i++
+				configDirFlag = args[i]
+			}
+		case "--no-state-write":
+			noStateWrite = true
+		case "--strict-json":
+			strictJSON = true
+		case "--force":
+			forceWrite = true
+		case "--keyring":
+			useKeyring = true
+		case "-q", "--quiet":
+			quiet = true
+		case "-v", "--verbose":
+			verbose = true
+		case "--json-errors":
+			jsonErrors = true
+		default:
+			if command == "" && !strings.HasPrefix(a, "-") {
+				command = a
+			}
+			rest = append(rest, a)
+		}
+	}
+	return rest
+}
+
+// cmdArgs holds a command's positional arguments and the flags it was given.
+type cmdArgs struct {
+	pos   []string
+	flags map[string]string
+}
+
+func (c cmdArgs) has(flag string) bool {
+	_, ok := c.flags[flag]
+	return ok
+}
+
+func (c cmdArgs) value(flag string) string {
+	return c.flags[flag]
+}
+
+// parseArgs separates flags from positional arguments. Each spec entry names a
+// flag with optional aliases ("--kill|-k"); a trailing "=" marks a flag that
+// takes a value ("--for="). Flags are recorded under their first name.
+func parseArgs(args []string, spec ...string) (cmdArgs, error) {
+	canonical := make(map[string]string)
+	takesValue := make(map[string]bool)
+	for _, s := range spec {
+		valued := strings.HasSuffix(s, "=")
+		names := strings.Split(strings.TrimSuffix(s, "="), "|")
+		for _, n := range names {
+			canonical[n] = names[0]
+			takesValue[n] = valued
+		}
+	}
+
+	parsed := cmdArgs{flags: make(map[string]string)}
+	for i := 0; i < len(args); i++ {
+		a := args[i]
+		if len(a) < 2 || a[0] != '-' {
+			parsed.pos = append(parsed.pos, a)
+			continue
+		}
+		flag, value, hasValue := strings.Cut(a, "=")
+		key, ok := canonical[flag]
+		if !ok {
+			return parsed, fmt.Errorf("unknown flag: %s", flag)
+		}
+		if takesValue[flag] && !hasValue {
+			if i+1 >= len(args) {
+				return parsed, fmt.Errorf("%s requires a value", flag)
+			}
+			i++
+			value = args[i]
+		} else if !takesValue[flag] && hasValue {
+			return parsed, fmt.Errorf("%s does not take a value", flag)
+		}
+		parsed.flags[key] = value
+	}
+	return parsed, nil
+}
+
+// parseJobs reads -j/--parallel, the number of network requests a batch
+// command may have in flight at once.
+func parseJobs(parsed cmdArgs) (int, error) {
+	if !parsed.has("--parallel") {
+		return defaultJobs, nil
+	}
+	n, err := strconv.Atoi(parsed.value("--parallel"))
+	if err != nil || n < 1 {
+		return 0, fmt.Errorf("invalid -j value '%s': expected a positive number", parsed.value("--parallel"))
+	}
+	return n, nil
+}
+
+// readNames expands a "-" among a batch command's profile names into the
+// names read from stdin, one per line, so names can be piped in.
+func readNames(args []string) ([]string, error) {
+	var names []string
+	for _, a := range args {
+		if a != "-" {
+			names = append(names, a)
+			continue
+		}
+		data, err := io.ReadAll(os.Stdin)
+		if err != nil {
+			return nil, fmt.Errorf("reading profile names from stdin: %w", err)
+		}
+		for i, line := range strings.Split(string(data), "\n") {
+			line = strings.TrimSpace(line)
+			if line == "" {
+				continue
+			}
+			if err := validateProfileName(line); err != nil {
+				return nil, fmt.Errorf("stdin line %d: %w", i+1, err)
+			}
+			names = append(names, line)
+		}
+	}
+	return names, nil
+}
+
+func cmdAdd(args []string) (err error) {
+	parsed, err := parseArgs(args, "--expect-email=", "--default-command=", "--with-ui-prefs", "--no-activate", "--capture-only", "--verify")
+	if err != nil {
+		return err
+	}
+	if len(parsed.pos) == 0 {
+		return fmt.Errorf("add requires a profile name")
+	}
+	name := parsed.pos[0]
+
+	if profileExists(name) {
+		return fmt.Errorf("profile '%s' %w (use 'remove' first)", name, errExists)
+	}
+
+	if warning := claudeVersionWarning(); warning != "" {
+		fmt.Fprintf(os.Stderr, "Warning: %s\n", warning)
+	}
+	previous := loadState().ActiveProfile
+
+	// --capture-only puts the login Claude had back exactly, whether or not
+	// the new profile makes it
+	captureOnly := parsed.has("--capture-only")
+	if captureOnly {
+		snapshot := snapshotAuth()
+		defer func() {
+			if restoreErr := snapshot.restore(); restoreErr != nil {
+				if err == nil {
+					err = fmt.Errorf("restoring the previous login: %w", restoreErr)
+				} else {
+					fmt.Fprintf(os.Stderr, "Warning: couldn't restore the previous login: %v\n", restoreErr)
+				}
+			}
+		}()
+	}
+
+	// Clear Claude's auth so the CLI triggers its first-run login flow
+	if err := clearAuth(); err != nil {
+		return err
+	}
+
+	if err := runLogin(); err != nil {
+		return fmt.Errorf("claude exited with error — use 'claude-switch use <profile>' to restore your previous session: %w", err)
+	}
+
+	profile, err := importCurrentCredentials()
+	if err != nil {
+		return err
+	}
+
+	// Catch "logged into the wrong account" before it becomes a profile
+	if expected := parsed.value("--expect-email"); expected != "" {
+		if got := profile.DisplayEmail(); !strings.EqualFold(got, expected) {
+			return fmt.Errorf("logged in as %s, expected %s — profile '%s' was not saved (use 'claude-switch use <profile>' to restore your previous session)", got, expected, name)
+		}
+	}
+	if other, ok := findDuplicate(profile); ok && !forceWrite {
+		return fmt.Errorf("this account is already saved as '%s' — profile '%s' was not saved (pass --force to save it again, or use 'claude-switch use <profile>' to restore your previous session)", other, name)
+	}
+	if parsed.has("--verify") {
+		if err := verifyProfile(profile); err != nil {
+			return fmt.Errorf("%w — profile '%s' was not saved (use 'claude-switch use <profile>' to restore your previous session)", err, name)
+		}
+	}
+	profile.DefaultCommand = strings.Fields(parsed.value("--default-command"))
+	if parsed.has("--with-ui-prefs") {
+		profile.UIPrefs = readUIPrefs(claudeJSONPath())
+	}
+
+	if err := saveProfile(name, profile); err != nil {
+		return err
+	}
+	printProfileSaved("Saved", name, profile)
+	if captureOnly {
+		logf("Restored Claude's previous login\n")
+		return nil
+	}
+
+	// The login replaced Claude's live credentials, so put the previous
+	// profile's back
+	if parsed.has("--no-activate") {
+		if previous == nil {
+			logf("No profile was active before, so Claude stays logged in as '%s'\n", name)
+			return nil
+		}
+		return switchProfile(*previous, switchOptions{noHint: true})
+	}
+
+	state := loadState()
+	state.ActiveProfile = &name
+	return saveState(&state)
+}
+
+func cmdImport(args []string) error {
+	parsed, err := parseArgs(args, "--fetch-account", "--default-command=", "--with-ui-prefs", "--no-activate", "--workspace=",
+		"--from-file=", "--from-stdin", "--verify")
+	if err != nil {
+		return err
+	}
+	if len(parsed.pos) == 0 {
+		return fmt.Errorf("import requires a profile name")
+	}
+	if parsed.has("--workspace") && parsed.value("--workspace") == "" {
+		return fmt.Errorf("--workspace requires a workspace id")
+	}
+	if parsed.has("--from-file") && parsed.has("--from-stdin") {
+		return fmt.Errorf("--from-file and --from-stdin can't be combined")
+	}
+	name := parsed.pos[0]
+
+	if profileExists(name) {
+		return fmt.Errorf("profile '%s' %w (use 'remove' first)", name, errExists)
+	}
+
+	// Credentials from elsewhere don't touch Claude's, so they aren't activated
+	source := "current session"
+	var profile *Profile
+	switch {
+	case parsed.has("--from-file"):
+		source = parsed.value("--from-file")
+		profile, err = importSnippetFile(source)
+	case parsed.has("--from-stdin"):
+		source = "stdin"
+		profile, err = importSnippet(io.LimitReader(os.Stdin, maxProfileDownload), source)
+	default:
+		profile, err = importCurrentCredentials()
+		if errors.Is(err, errNoCredentials) {
+			return fmt.Errorf("no credentials found — is Claude Code logged in?")
+		}
+	}
+	if err != nil {
+		return err
+	}
+	fromElsewhere := source != "current session"
+
+	// ~/.claude.json doesn't always carry oauthAccount; ask the API instead
+	if parsed.has("--fetch-account") && profile.Type == "oauth" && accountField(profile.Account, "emailAddress") == "" {
+		account, err := fetchAccount(profile.Credentials)
+		if err != nil {
+			fmt.Fprintf(os.Stderr, "Warning: couldn't fetch account info: %v\n", err)
+		} else {
+			profile.Account = account
+		}
+	}
+	profile.DefaultCommand = strings.Fields(parsed.value("--default-command"))
+	if parsed.has("--with-ui-prefs") {
+		profile.UIPrefs = readUIPrefs(claudeJSONPath())
+	}
+	// Only Console API keys are scoped to a workspace
+	if parsed.has("--workspace") {
+		if profile.Type == "oauth" {
+			return fmt.Errorf("--workspace only applies to API key profiles, and the %s uses OAuth", source)
+		}
+		profile.Workspace = parsed.value("--workspace")
+	}
+	if other, ok := findDuplicate(profile); ok && !forceWrite {
+		return fmt.Errorf("this account is already saved as '%s' (pass --force to import it as '%s' anyway)", other, name)
+	}
+	if parsed.has("--verify") {
+		if err := verifyProfile(profile); err != nil {
+			return fmt.Errorf("%w — profile '%s' was not saved", err, name)
+		}
+	}
+
+	if err := saveProfile(name, profile); err != nil {
+		return err
+	}
+
+	if !parsed.has("--no-activate") && !fromElsewhere {
+		state := loadState()
+		state.ActiveProfile = &name
+		if err := saveState(&state); err != nil {
+			return err
+		}
+	}
+
+	if profile.Type == "oauth" {
+		email := profile.DisplayEmail()
+		sub := profile.DisplaySub()
+		logf("Imported %s as '%s' (%s, %s)\n", source, name, email, sub)
+	} else if profile.Workspace != "" {
+		logf("Imported %s as '%s' (API key, workspace %s)\n", source, name, profile.Workspace)
+	} else {
+		logf("Imported %s as '%s' (API key)\n", source, name)
+	}
+	return nil
 }
 
-func requireName(cmd string, fn func(string) error) error {
-	if len(os.Args) < 3 {
-		return fmt.Errorf("%s requires a profile name", cmd)
+// verifyProfile checks the credentials against the API before they're saved.
+// An expired token is skipped rather than refreshed: refreshing would rotate
+// the refresh token out from under whoever else holds it.
+func verifyProfile(profile *Profile) error {
+	if profile.Type == "oauth" && isExpired(profile.Credentials, 0) {
+		fmt.Fprintf(os.Stderr, "Warning: the access token has expired, so it wasn't verified\n")
+		return nil
+	}
+
+	var err error
+	if profile.Type == "oauth" {
+		err = verifyToken(profile.Credentials)
+	} else {
+		err = verifyAPIKey(profile.ApiKey)
 	}
-	return fn(os.Args[2])
+	if errors.Is(err, errCredentialsRejected) {
+		return err
+	} else if err != nil {
+		return fmt.Errorf("couldn't verify the credentials: %w", err)
+	}
+	debugf("credentials verified\n")
+	return nil
 }
 
-func cmdAdd(name string) error {
+// maxProfileDownload caps how much import-url reads; a profile is a few KB.
+const maxProfileDownload = 1 << 20
+
+// cmdImportURL imports a profile file published for team provisioning. It is
+// saved but not activated.
+func cmdImportURL(args []string) error {
+	parsed, err := parseArgs(args, "--sha256=")
+	if err != nil {
+		return err
+	}
+	if len(parsed.pos) != 2 {
+		return fmt.Errorf("import-url requires a profile name and a URL")
+	}
+	name, rawURL := parsed.pos[0], parsed.pos[1]
+
+	if err := validateProfileName(name); err != nil {
+		return err
+	}
 	if profileExists(name) {
-		return fmt.Errorf("profile '%s' already exists (use 'remove' first)", name)
+		return fmt.Errorf("profile '%s' %w (use 'remove' first)", name, errExists)
+	}
+	u, err := url.Parse(rawURL)
+	if err != nil || u.Scheme != "https" || u.Host == "" {
+		return fmt.Errorf("import-url only accepts https:// URLs, since the profile holds secrets")
+	}
+
+	data, err := downloadProfile(u.String())
+	if err != nil {
+		return err
+	}
+	if want := parsed.value("--sha256"); want != "" {
+		sum := sha256.Sum256(data)
+		if got := hex.EncodeToString(sum[:]); !strings.EqualFold(got, want) {
+			return fmt.Errorf("checksum mismatch: downloaded profile has SHA-256 %s, expected %s", got, want)
+		}
+	}
+
+	profile, err := decodeProfile(data)
+	if err != nil {
+		return fmt.Errorf("downloaded file isn't a valid profile: %w", err)
+	}
+	if err := checkProfile(profile); err != nil {
+		return fmt.Errorf("downloaded profile is unusable: %w", err)
+	}
+	if err := saveProfile(name, profile); err != nil {
+		return err
+	}
+	printProfileSaved("Imported", name, profile)
+	return nil
+}
+
+// cmdImportDir bulk-imports a directory of Claude configs, one per
+// subdirectory in the layout a per-account CLAUDE_CONFIG_DIR leaves behind.
+// Profiles are named after their subdirectory and are not activated.
+func cmdImportDir(args []string) error {
+	parsed, err := parseArgs(args, "--prefix=")
+	if err != nil {
+		return err
+	}
+	if len(parsed.pos) == 0 {
+		return fmt.Errorf("import-dir requires a directory")
+	}
+	dir := parsed.pos[0]
+
+	entries, err := os.ReadDir(dir)
+	if err != nil {
+		return err
+	}
+
+	imported := 0
+	for _, e := range entries {
+		if !e.IsDir() {
+			continue
+		}
+		sub := filepath.Join(dir, e.Name())
+		name := parsed.value("--prefix") + e.Name()
+
+		oauthRaw := readOAuthCredentialsFile(filepath.Join(sub, ".credentials.json"))
+		profile, err := importCredentials(oauthRaw, filepath.Join(sub, ".claude.json"))
+		switch {
+		case err != nil:
+			logf("  %s: skipped (%v)\n", e.Name(), err)
+		case profileExists(name):
+			logf("  %s: skipped (profile '%s' already exists)\n", e.Name(), name)
+		default:
+			if err := saveProfile(name, profile); err != nil {
+				fmt.Fprintf(os.Stderr, "  %s: failed (%v)\n", e.Name(), err)
+				continue
+			}
+			logf("  %s: imported as '%s' (%s)\n", e.Name(), name, profile.DisplayEmail())
+			imported++
+		}
+	}
+
+	logf("Imported %d profile(s) from %s\n", imported, dir)
+	return nil
+}
+
+func claudePIDs() []int {
+	out, err := exec.Command("pgrep", "-x", "claude").Output()
+	if err != nil {
+		return nil
+	}
+	var pids []int
+	for _, s := range strings.Fields(string(out)) {
+		var pid int
+		if _, err := fmt.Sscanf(s, "%d", &pid); err == nil {
+			pids = append(pids, pid)
+		}
+	}
+	return pids
+}
+
+func cmdUse(args []string) error {
+	parsed, err := parseArgs(args, "--kill|-k", "--for=", "--restore-previous", "--show-key", "--write-key", "--no-hint", "--prefer=")
+	if err != nil {
+		return err
+	}
+
+	var name string
+	switch {
+	case parsed.has("--restore-previous"):
+		previous := loadState().PreviousProfile
+		if previous == nil {
+			return fmt.Errorf("no previous profile to switch back to")
+		}
+		name = *previous
+	case len(parsed.pos) > 0:
+		name = parsed.pos[0]
+	case isTerminal(os.Stdin) && isTerminal(os.Stderr):
+		if name, err = pickProfile(); err != nil {
+			return err
+		}
+	default:
+		def := loadState().DefaultProfile
+		if def == nil {
+			return fmt.Errorf("use requires a profile name, or a default set with 'claude-switch set-default <name>'")
+		}
+		name = *def
+	}
+
+	var forMs uint64
+	if parsed.has("--for") {
+		forMs, err = parseDuration(parsed.value("--for"))
+		if err != nil {
+			return fmt.Errorf("--for: %w", err)
+		}
+		if forMs == 0 {
+			return fmt.Errorf("--for: duration must be greater than zero")
+		}
+	}
+
+	if err := requireWritable("use"); err != nil {
+		return err
+	}
+
+	if pids := claudePIDs(); len(pids) > 0 {
+		if parsed.has("--kill") {
+			exec.Command("pkill", "-x", "claude").Run()
+			logf("Terminated running Claude sessions.\n")
+		} else {
+			fmt.Fprintln(os.Stderr, "Warning: Claude is running. It may overwrite the switched credentials.")
+			fmt.Fprintln(os.Stderr, "Re-run with --kill to terminate existing sessions before switching.")
+		}
+	}
+
+	state := loadState()
+	previous := state.ActiveProfile
+	// Another --for switch on top of one still running ends where the first
+	// began, not on the temporary profile
+	if forMs > 0 && state.TempSwitch != nil {
+		previous = state.TempSwitch.Previous
+	}
+	opts := switchOptions{
+		showKey:  parsed.has("--show-key"),
+		writeKey: parsed.has("--write-key"),
+		noHint:   parsed.has("--no-hint"),
+		prefer:   parsed.value("--prefer"),
+	}
+	if err := switchProfile(name, opts); err != nil {
+		return err
+	}
+
+	if forMs > 0 {
+		revertAt := nowMs() + forMs
+		state = loadState()
+		state.TempSwitch = &TempSwitch{Previous: previous, RevertAt: revertAt}
+		if err := saveState(&state); err != nil {
+			return err
+		}
+		until := time.UnixMilli(int64(revertAt)).Format("15:04")
+		if previous != nil {
+			logf("Will switch back to '%s' after %s\n", *previous, until)
+		} else {
+			logf("Temporary switch ends at %s\n", until)
+		}
+	}
+	return nil
+}
+
+// switchOptions tweaks how switchProfile treats API key profiles.
+type switchOptions struct {
+	showKey  bool
+	writeKey bool
+	noHint   bool
+
+	// prefer picks "oauth" or "api_key" for a profile that carries both
+	prefer string
+}
+
+// switchProfile makes name the active profile. OAuth credentials are written
+// into Claude's config files; API key profiles only print how to use them
+// unless opts.writeKey is set.
+func switchProfile(name string, opts switchOptions) error {
+	profile, err := loadProfile(name)
+	if err != nil {
+		return err
+	}
+
+	useOAuth := profile.Type == "oauth"
+	switch opts.prefer {
+	case "":
+	case "oauth":
+		if profile.Credentials == nil {
+			return fmt.Errorf("profile '%s' has no OAuth credentials", name)
+		}
+		useOAuth = true
+	case "api_key":
+		if profile.ApiKey == "" {
+			return fmt.Errorf("profile '%s' has no API key", name)
+		}
+		useOAuth = false
+		opts.writeKey = true
+	default:
+		return fmt.Errorf("invalid --prefer value '%s': expected oauth or api_key", opts.prefer)
+	}
+
+	reauthed := false
+	if useOAuth {
+		profile, reauthed, err = ensureFresh(name, profile)
+		if err != nil {
+			return err
+		}
+
+		if err := writeCredentials(profile.Credentials); err != nil {
+			return err
+		}
+		if err := writeKeychainCredentials(profile.Credentials); err != nil {
+			return err
+		}
+		if err := writeOAuthAccount(profile.Account); err != nil {
+			return err
+		}
+		// Leave Claude only one way to authenticate
+		if profile.ApiKey != "" {
+			if err := clearAPIKey(); err != nil {
+				return err
+			}
+		}
+	} else if opts.writeKey {
+		if err := writeAPIKey(profile.ApiKey); err != nil {
+			return err
+		}
+	}
+	if err := writeUIPrefs(profile.UIPrefs); err != nil {
+		return err
+	}
+
+	state := loadState()
+	if state.ActiveProfile != nil && *state.ActiveProfile != name {
+		state.PreviousProfile = state.ActiveProfile
+	}
+	state.ActiveProfile = &name
+	state.TempSwitch = nil
+	if state.LastUsed == nil {
+		state.LastUsed = make(map[string]uint64)
+	}
+	state.LastUsed[name] = nowMs()
+	if err := saveState(&state); err != nil {
+		return err
+	}
+	notifySwitched(name, profile)
+
+	switch {
+	case !useOAuth && opts.writeKey:
+		logf("Switched to '%s' (API key written to %s)\n", name, claudeJSONPath())
+	case !useOAuth && opts.noHint:
+		logf("Switched to '%s' (API key not written)\n", name)
+	case !useOAuth:
+		// Printing the full key would leave it in scrollback and logs
+		key := maskSecret(profile.ApiKey)
+		if opts.showKey {
+			key = profile.ApiKey
+		}
+		logf("API key profiles aren't written to Claude's config files by default.\n")
+		logf("Use one of these instead:\n")
+		logf("\n")
+		logf("  claude-switch exec %s -- claude\n", name)
+		logf("  claude-switch use %s --write-key\n", name)
+		logf("  export ANTHROPIC_API_KEY=%s\n", key)
+		if !opts.showKey {
+			logf("\n")
+			logf("The key is masked; re-run with --show-key to print it in full.\n")
+		}
+	case reauthed:
+		logf("Switched to '%s' (re-authenticated)\n", name)
+	default:
+		logf("Switched to '%s'\n", name)
+	}
+	return nil
+}
+
+// notifySwitched shows a desktop notification naming the account just
+// switched to, when CLAUDE_SWITCH_NOTIFY is set. Where notifications aren't
+// available nothing happens.
+func notifySwitched(name string, profile *Profile) {
+	if os.Getenv("CLAUDE_SWITCH_NOTIFY") == "" {
+		return
+	}
+	title, body := switchNotification(name, profile)
+	sendNotification(title, body)
+}
+
+// switchNotification builds the title and text of the switch notification.
+func switchNotification(name string, profile *Profile) (string, string) {
+	if profile.Type != "oauth" {
+		return "Switched to " + name, "API key"
+	}
+	body := profile.DisplayEmail()
+	if org := profile.DisplayOrg(); org != "-" {
+		body += " (" + org + ")"
+	}
+	return "Switched to " + name, body
+}
+
+func cmdRevert() error {
+	state := loadState()
+	if state.TempSwitch == nil {
+		return fmt.Errorf("no temporary switch is in effect")
+	}
+	return revertTempSwitch(state.TempSwitch)
+}
+
+// revertExpiredSwitch ends a `use --for` switch whose deadline has passed.
+func revertExpiredSwitch() error {
+	if state := loadState(); state.TempSwitch == nil || nowMs() < state.TempSwitch.RevertAt {
+		return nil
+	}
+	// Checked again under the lock, as another run may have reverted it
+	return withLock(func() error {
+		state := loadState()
+		if state.TempSwitch == nil || nowMs() < state.TempSwitch.RevertAt {
+			return nil
+		}
+		return revertTempSwitch(state.TempSwitch)
+	})
+}
+
+func revertTempSwitch(ts *TempSwitch) error {
+	if ts.Previous == nil {
+		state := loadState()
+		state.TempSwitch = nil
+		if err := saveState(&state); err != nil {
+			return err
+		}
+		logf("Temporary switch ended; there was no previous profile to restore.\n")
+		return nil
+	}
+
+	logf("Temporary switch ended, switching back to '%s'\n", *ts.Previous)
+	if err := switchProfile(*ts.Previous, switchOptions{}); err != nil {
+		// Drop the record so a missing profile doesn't fail every command
+		state := loadState()
+		state.TempSwitch = nil
+		saveState(&state)
+		return err
+	}
+	return nil
+}
+
+// pickProfile asks which profile to switch to, for 'use' with no name on a
+// terminal. It takes a number or a name, and Enter alone picks the default
+// profile when one is set.
+func pickProfile() (string, error) {
+	names, err := listProfiles()
+	if err != nil {
+		return "", err
+	}
+	state := loadState()
+	var choices []string
+	for _, name := range names {
+		if !state.isDisabled(name) {
+			choices = append(choices, name)
+		}
+	}
+	if len(choices) == 0 {
+		return "", fmt.Errorf("no profiles to choose from; add one with 'claude-switch add <name>'")
+	}
+
+	w := tabwriter.NewWriter(os.Stderr, 0, 0, 2, ' ', 0)
+	for i, name := range choices {
+		marker := " "
+		if state.isActive(name) {
+			marker = "*"
+		}
+		label := escapeControl(state.displayName(name))
+		if state.DefaultProfile != nil && *state.DefaultProfile == name {
+			label += " (default)"
+		}
+		email, org := "error", "-"
+		if profile, err := loadProfile(name); err == nil {
+			email = truncate(profile.DisplayEmail(), defaultMaxWidth)
+			org = truncate(profile.DisplayOrg(), defaultMaxWidth)
+		}
+		fmt.Fprintf(w, "%s %d)\t%s\t%s\t%s\n", marker, i+1, label, email, org)
+	}
+	w.Flush()
+
+	if def := state.DefaultProfile; def != nil {
+		fmt.Fprintf(os.Stderr, "Switch to [1-%d, Enter for '%s']: ", len(choices), escapeControl(*def))
+	} else {
+		fmt.Fprintf(os.Stderr, "Switch to [1-%d]: ", len(choices))
+	}
+	answer, _ := bufio.NewReader(os.Stdin).ReadString('\n')
+	answer = strings.TrimSpace(answer)
+	if answer == "" && state.DefaultProfile != nil {
+		return *state.DefaultProfile, nil
+	}
+	if n, err := strconv.Atoi(answer); err == nil && n >= 1 && n <= len(choices) {
+		return choices[n-1], nil
+	}
+	for _, name := range choices {
+		if answer == name || answer == state.Nicknames[name] {
+			return name, nil
+		}
+	}
+	if answer == "" {
+		return "", fmt.Errorf("no profile chosen")
+	}
+	return "", fmt.Errorf("'%s' isn't one of the profiles listed", escapeControl(answer))
+}
+
+// ANSI colour helpers
+const (
+	ansiReset  = "\033[0m"
+	ansiBold   = "\033[1m"
+	ansiGreen  = "\033[32m"
+	ansiRed    = "\033[31m"
+	ansiYellow = "\033[33m"
+)
+
+// listEntry is one profile as loaded by the list command.
+type listEntry struct {
+	name    string
+	profile *Profile
+	err     error
+
+	// refreshErr is set when list --refresh couldn't refresh the token
+	refreshErr error
+}
+
+func cmdList(args []string) error {
+	parsed, err := parseArgs(args, "--show-token-age", "--show-role", "--filter=", "--skip-invalid", "--limit=", "--errors-only", "--max-width=", "--ndjson", "--all", "--refresh", "--parallel|-j=", "--json", "--watch", "--interval=", "--refresh-every=", "--absolute", "--color=")
+	if err != nil {
+		return err
+	}
+	if _, err := listColour(parsed); err != nil {
+		return err
+	}
+	if parsed.has("--watch") {
+		return watchList(parsed)
+	}
+	return runList(parsed)
+}
+
+// Defaults for list --watch: how often the table is redrawn, and the least
+// time between two refresh passes, so a fast redraw doesn't hammer the token
+// endpoint. Shorter values than the minimums would make a busy loop.
+const (
+	defaultWatchIntervalMs   = 5 * 1000
+	defaultRefreshIntervalMs = 60 * 1000
+	minWatchIntervalMs       = 1000
+	minRefreshIntervalMs     = 60 * 1000
+)
+
+// watchList redraws the list until interrupted. With --refresh it also keeps
+// tokens warm, refreshing the expiring ones at most every --refresh-every.
+// Ctrl-C takes effect between redraws, so a refresh is never cut off before
+// its new tokens are saved.
+func watchList(parsed cmdArgs) error {
+	if parsed.has("--json") || parsed.has("--ndjson") {
+		return fmt.Errorf("--watch redraws a table and can't be combined with --json or --ndjson")
+	}
+	interval := uint64(defaultWatchIntervalMs)
+	if parsed.has("--interval") {
+		d, err := parseDuration(parsed.value("--interval"))
+		if err != nil {
+			return err
+		}
+		if d < minWatchIntervalMs {
+			return fmt.Errorf("--interval must be at least %s", formatDuration(minWatchIntervalMs))
+		}
+		interval = d
+	}
+	refreshEvery := uint64(defaultRefreshIntervalMs)
+	if parsed.has("--refresh-every") {
+		d, err := parseDuration(parsed.value("--refresh-every"))
+		if err != nil {
+			return err
+		}
+		if d < minRefreshIntervalMs {
+			return fmt.Errorf("--refresh-every must be at least %s, to spare the token endpoint", formatDuration(minRefreshIntervalMs))
+		}
+		refreshEvery = d
+	}
+	refresh := parsed.has("--refresh")
+
+	interrupted := make(chan os.Signal, 1)
+	signal.Notify(interrupted, os.Interrupt, syscall.SIGTERM)
+	defer signal.Stop(interrupted)
+
+	var lastRefresh uint64
+	for {
+		tick := cmdArgs{pos: parsed.pos, flags: maps.Clone(parsed.flags)}
+		delete(tick.flags, "--refresh")
+		if refresh && nowMs()-lastRefresh >= refreshEvery {
+			tick.flags["--refresh"] = ""
+			lastRefresh = nowMs()
+		}
+
+		fmt.Print("\033[H\033[2J")
+		fmt.Printf("Every %s, updated %s (Ctrl-C to quit)\n\n", formatDuration(interval), time.Now().Format("15:04:05"))
+		if err := runList(tick); err != nil {
+			fmt.Fprintf(os.Stderr, "error: %v\n", err)
+		}
+
+		select {
+		case <-interrupted:
+			return nil
+		case <-time.After(time.Duration(interval) * time.Millisecond):
+		}
+	}
+}
+
+func runList(parsed cmdArgs) error {
+	var err error
+	showAge := parsed.has("--show-token-age")
+	showRole := parsed.has("--show-role")
+	absolute := parsed.has("--absolute")
+	colour, err := listColour(parsed)
+	if err != nil {
+		return err
+	}
+
+	var filter profileFilter
+	if parsed.has("--filter") {
+		if filter, err = parseFilter(parsed.value("--filter")); err != nil {
+			return err
+		}
+	}
+
+	maxWidth := defaultMaxWidth
+	if parsed.has("--max-width") {
+		maxWidth, err = strconv.Atoi(parsed.value("--max-width"))
+		if err != nil || maxWidth < 0 {
+			return fmt.Errorf("--max-width requires a non-negative number")
+		}
+	}
+
+	limit := -1
+	if parsed.has("--limit") {
+		limit, err = strconv.Atoi(parsed.value("--limit"))
+		if err != nil || limit < 0 {
+			return fmt.Errorf("--limit requires a non-negative number")
+		}
+	}
+
+	ndjson := parsed.has("--ndjson")
+	if ndjson && parsed.has("--json") {
+		return fmt.Errorf("--json and --ndjson can't be combined")
+	}
+	if ndjson && limit >= 0 {
+		return fmt.Errorf("--ndjson streams every profile and can't be combined with --limit")
+	}
+
+	refresh := parsed.has("--refresh")
+	jobs, err := parseJobs(parsed)
+	if err != nil {
+		return err
+	}
+	if refresh {
+		if err := requireWritable("list --refresh"); err != nil {
+			return err
+		}
+	}
+
+	names, err := listProfiles()
+	if err != nil {
+		return err
+	}
+	if parsed.has("--errors-only") {
+		return listErrors(names, colour)
+	}
+	if len(names) == 0 && !ndjson && !parsed.has("--json") {
+		logf("No profiles. Use 'claude-switch add <name>' or 'claude-switch import <name>' to create one.\n")
+		return nil
+	}
+
+	state := loadState()
+
+	// With --ndjson each profile is written as soon as it is loaded
+	enc := json.NewEncoder(os.Stdout)
+	var entries []listEntry
+	for _, name := range names {
+		profile, err := loadProfile(name)
+		if filter != nil && (err != nil || !filter.match(name, profile)) {
+			continue
+		}
+		if err != nil && parsed.has("--skip-invalid") {
+			continue
+		}
+		if state.isDisabled(name) && !parsed.has("--all") {
+			continue
+		}
+		e := listEntry{name: name, profile: profile, err: err}
+		if ndjson {
+			if refresh && err == nil {
+				e.refreshErr = refreshIfExpired(name, profile)
+			}
+			if err := enc.Encode(e.record(&state)); err != nil {
+				return err
+			}
+			continue
+		}
+		entries = append(entries, e)
+	}
+	if ndjson {
+		return nil
+	}
+
+	hidden := 0
+	if limit >= 0 {
+		entries, hidden = limitEntries(entries, &state, limit)
+	}
+
+	if refresh {
+		runParallel(len(entries), jobs, func(i int) {
+			if entries[i].err == nil {
+				entries[i].refreshErr = refreshIfExpired(entries[i].name, entries[i].profile)
+			}
+		})
+	}
+
+	if parsed.has("--json") {
+		records := make([]listRecord, 0, len(entries))
+		for _, e := range entries {
+			records = append(records, e.record(&state))
+		}
+		out, err := json.MarshalIndent(records, "", "  ")
+		if err != nil {
+			return err
+		}
+		fmt.Println(string(out))
+		return nil
+	}
+
+	headers := []string{" ", "NAME", "TYPE", "EMAIL", "ORG"}
+	if showRole {
+		headers = append(headers, "ROLE")
+	}
+	headers = append(headers, "PLAN", "EXPIRES")
+	if showAge {
+		headers = append(headers, "AGE")
+	}
+	// The LABEL column only appears once some profile has a label
+	showLabel := false
+	for _, e := range entries {
+		if e.err == nil && e.profile.Label != nil {
+			showLabel = true
+		}
+	}
+	if showLabel {
+		headers = append(headers, "LABEL")
+	}
+	for i, h := range headers {
+		headers[i] = ansiBold + h + ansiReset
+	}
+
+	var warnings []string
+	w := tabwriter.NewWriter(os.Stdout, 0, 0, 2, ' ', 0)
+	// Colour codes are stripped before tabwriter sees them, so the columns
+	// still line up without them
+	printRow := func(cells []string) {
+		line := strings.Join(cells, "\t")
+		if !colour {
+			line = stripANSI(line)
+		}
+		fmt.Fprintln(w, line)
+	}
+	printRow(headers)
+
+	for _, e := range entries {
+		name, profile := e.name, e.profile
+		isActive := state.isActive(name)
+		// Names come from filenames, so escape anything that could drive the terminal
+		shown := escapeControl(name)
+
+		if e.err != nil {
+			active := " "
+			if isActive {
+				active = "*"
+			}
+			row := []string{active, shown, ansiRed + "error" + ansiReset}
+			for len(row) < len(headers) {
+				row = append(row, "-")
+			}
+			printRow(row)
+			continue
+		}
+
+		expiry := "-"
+		if ts := profile.ExpiresAt(); ts != nil {
+			expiry = listExpiry(*ts, absolute)
+		}
+		if e.refreshErr != nil {
+			expiry = ansiRed + "refresh failed" + ansiReset
+			warnings = append(warnings, fmt.Sprintf("couldn't refresh '%s': %v", shown, e.refreshErr))
+		}
+		if profile.Credentials != nil && implausibleExpiry(profile.Credentials) {
+			at := time.UnixMilli(int64(profile.Credentials.ExpiresAt)).UTC().Format("2006-01-02 15:04 UTC")
+			warnings = append(warnings, fmt.Sprintf("'%s' claims to expire at %s, which is implausibly far away; the timestamp is probably malformed, so re-import the profile", shown, at))
+		}
+
+		kind := profile.DisplayType()
+		if profile.Credentials != nil && profile.Credentials.LacksInference() {
+			kind += ansiRed + "!" + ansiReset
+			warnings = append(warnings, fmt.Sprintf("'%s' lacks the user:inference scope, so it can't run Claude; re-authenticate it with 'claude-switch remove %s && claude-switch add %s'", shown, shown, shown))
+		}
+		if isActive {
+			if matches, known := activeMatchesLive(profile); known && !matches {
+				kind += ansiRed + "?" + ansiReset
+				warnings = append(warnings, fmt.Sprintf("Claude's live credentials don't belong to the active profile '%s'; Claude was probably logged in directly since the last switch, so 'use' a profile or 'import' the new login", shown))
+			}
+		}
+
+		label := shown
+		if nick := state.displayName(name); nick != name {
+			label = fmt.Sprintf("%s (%s)", escapeControl(nick), shown)
+		}
+		marker := " "
+		if isActive {
+			marker = ansiGreen + ansiBold + "*" + ansiReset
+			label = ansiGreen + ansiBold + label + ansiReset
+		}
+		if state.DefaultProfile != nil && *state.DefaultProfile == name {
+			label += " (default)"
+		}
+		if state.isDisabled(name) {
+			label += " (disabled)"
+		}
+		row := []string{
+			marker,
+			label,
+			kind,
+			truncate(profile.DisplayEmail(), maxWidth),
+			truncate(profile.DisplayOrg(), maxWidth),
+		}
+		if showRole {
+			row = append(row, profile.DisplayOrgRole())
+		}
+		row = append(row, profile.DisplaySub(), expiry)
+		if showAge {
+			row = append(row, profile.DisplayTokenAge())
+		}
+		if showLabel {
+			note := "-"
+			if profile.Label != nil {
+				note = truncate(escapeControl(*profile.Label), maxWidth)
+			}
+			row = append(row, note)
+		}
+		printRow(row)
+	}
+
+	w.Flush()
+	if hidden > 0 {
+		fmt.Printf("... and %d more\n", hidden)
+	}
+	for _, msg := range warnings {
+		fmt.Fprintf(os.Stderr, "Warning: %s\n", msg)
+	}
+	return nil
+}
+
+// listColour decides whether list colours its table from --color: "always",
+// "never", or "auto" (the default), which colours only a terminal and
+// honours NO_COLOR.
+func listColour(parsed cmdArgs) (bool, error) {
+	switch mode := parsed.value("--color"); mode {
+	case "always":
+		return true, nil
+	case "never":
+		return false, nil
+	case "", "auto":
+		return stdoutColour(), nil
+	default:
+		return false, fmt.Errorf("invalid --color value '%s': expected auto, always or never", mode)
+	}
+}
+
+// listExpiry renders the EXPIRES column: relative to now ("in 37m",
+// "expired 2h ago"), or as a UTC timestamp when absolute is set. It is green
+// while more than the refresh buffer remains, yellow within the buffer and
+// red once expired.
+func listExpiry(ms uint64, absolute bool) string {
+	now := nowMs()
+	text := time.UnixMilli(int64(ms)).UTC().Format("2006-01-02 15:04 UTC")
+	if !absolute {
+		if ms > now {
+			text = "in " + formatDuration(ms-now)
+		} else {
+			text = "expired " + formatDuration(now-ms) + " ago"
+		}
+	}
+
+	colour := ansiGreen
+	switch {
+	case ms <= now:
+		colour = ansiRed
+	case ms <= now+expiryBuffer():
+		colour = ansiYellow
+	}
+	return colour + text + ansiReset
+}
+
+// listRecord is one profile as list reports it in JSON.
+type listRecord struct {
+	Name         string  `json:"name"`
+	Nickname     string  `json:"nickname,omitempty"`
+	Label        string  `json:"label,omitempty"`
+	Status       string  `json:"status"`
+	Active       bool    `json:"active"`
+	Type         string  `json:"type,omitempty"`
+	Email        string  `json:"email,omitempty"`
+	Org          string  `json:"org,omitempty"`
+	Role         string  `json:"role,omitempty"`
+	Plan         string  `json:"plan,omitempty"`
+	ExpiresAt    *uint64 `json:"expires_at,omitempty"`
+	ExpiresAtISO string  `json:"expires_at_iso,omitempty"`
+	Disabled     bool    `json:"disabled,omitempty"`
+	Error        string  `json:"error,omitempty"`
+
+	RefreshError string `json:"refresh_error,omitempty"`
+}
+
+func (e listEntry) record(state *State) listRecord {
+	r := listRecord{
+		Name:     e.name,
+		Nickname: state.Nicknames[e.name],
+		Status:   "ok",
+		Active:   state.isActive(e.name),
+		Disabled: state.isDisabled(e.name),
+	}
+	if e.err != nil {
+		r.Status = "error"
+		r.Error = e.err.Error()
+		return r
+	}
+	p := e.profile
+	if p.Label != nil {
+		r.Label = *p.Label
+	}
+	r.Type = p.Type
+	r.Email = accountField(p.Account, "emailAddress")
+	r.Org = accountField(p.Account, "organizationName")
+	r.Role = accountField(p.Account, "organizationRole")
+	if p.Credentials != nil && p.Credentials.SubscriptionType != nil {
+		r.Plan = *p.Credentials.SubscriptionType
+	}
+	if r.ExpiresAt = p.ExpiresAt(); r.ExpiresAt != nil {
+		r.ExpiresAtISO = time.UnixMilli(int64(*r.ExpiresAt)).UTC().Format(time.RFC3339)
+	}
+	if e.refreshErr != nil {
+		r.RefreshError = e.refreshErr.Error()
+	}
+	return r
+}
+
+// defaultMaxWidth is how wide list lets the email and org columns grow before
+// shortening them, so long values don't wrap the table.
+const defaultMaxWidth = 32
+
+func cmdCurrent() error {
+	state := loadState()
+	if state.ActiveProfile == nil {
+		return fmt.Errorf("no active profile")
+	}
+	name := *state.ActiveProfile
+	profile, err := loadProfile(name)
+	if err != nil {
+		return err
+	}
+
+	if profile.Type == "oauth" {
+		fmt.Printf("%s\t%s\t%s\t%s\n", state.displayName(name), profile.DisplayEmail(), profile.DisplayOrg(), profile.DisplaySub())
+	} else {
+		fmt.Printf("%s\t(API key)\n", state.displayName(name))
+	}
+
+	if matches, known := activeMatchesLive(profile); known && !matches {
+		fmt.Fprintf(os.Stderr, "Warning: Claude's live credentials don't match '%s'; Claude may have been logged in some other way since the last switch\n", name)
+	}
+	return nil
+}
+
+// warnIfCredentialsDrifted reports when Claude's credentials file changed
+// since claude-switch last wrote it and no longer holds the active profile,
+// e.g. after a direct `claude /login`. Changes from Claude refreshing the
+// active profile's token are accepted quietly. Either way the new contents
+// are recorded, so each change is reported once.
+func warnIfCredentialsDrifted() {
+	state := loadState()
+	if state.CredentialsHash == "" || state.ActiveProfile == nil {
+		return
+	}
+	live := liveCredentialsHash()
+	if live == state.CredentialsHash {
+		return
+	}
+	// Checking would ask for the passphrase on every command
+	if state.Locked && os.Getenv("CLAUDE_SWITCH_PASSPHRASE") == "" {
+		return
+	}
+
+	profile, err := loadProfile(*state.ActiveProfile)
+	if err != nil {
+		return
+	}
+	if matches, known := activeMatchesLive(profile); known && !matches {
+		fmt.Fprintf(os.Stderr, "Warning: Claude's credentials were changed outside claude-switch (by 'claude /login'?) and no longer belong to '%s'; check with 'claude-switch current'\n", *state.ActiveProfile)
+	}
+	if !noStateWrite {
+		state.CredentialsHash = live
+		saveState(&state)
+	}
+}
+
+// profileSummary is the aggregate view `summary` reports, everything a status
+// dashboard needs in one object.
+type profileSummary struct {
+	Active      *string  `json:"active"`
+	Profiles    int      `json:"profiles"`
+	OAuth       int      `json:"oauth"`
+	ApiKey      int      `json:"api_key"`
+	Expired     int      `json:"expired"`
+	Invalid     int      `json:"invalid"`
+	NeedsReauth []string `json:"needs_reauth"`
+}
+
+// needsReauth reports whether an OAuth profile can't be fixed by a refresh,
+// as far as can be told without the network.
+func needsReauth(p *Profile) bool {
+	if p.Type != "oauth" || p.Credentials == nil {
+		return false
+	}
+	return p.Credentials.RefreshToken == "" || p.Credentials.LacksInference()
+}
+
+func cmdSummary(args []string) error {
+	parsed, err := parseArgs(args, "--json")
+	if err != nil {
+		return err
+	}
+	names, err := listProfiles()
+	if err != nil {
+		return err
+	}
+	state := loadState()
+
+	sum := profileSummary{Active: state.ActiveProfile, Profiles: len(names), NeedsReauth: []string{}}
+	for _, name := range names {
+		profile, err := loadProfile(name)
+		if err != nil {
+			sum.Invalid++
+			continue
+		}
+		switch profile.Type {
+		case "oauth":
+			sum.OAuth++
+			if isExpired(profile.Credentials, expiryBuffer()) {
+				sum.Expired++
+			}
+		case "api_key":
+			sum.ApiKey++
+		}
+		if needsReauth(profile) {
+			sum.NeedsReauth = append(sum.NeedsReauth, name)
+		}
+	}
+
+	if parsed.has("--json") {
+		out, err := json.Marshal(sum)
+		if err != nil {
+			return err
+		}
+		fmt.Println(string(out))
+		return nil
+	}
+
+	active := "(none)"
+	if sum.Active != nil {
+		active = *sum.Active
+	}
+	fmt.Printf("Active:   %s\n", active)
+	fmt.Printf("Profiles: %d (%d OAuth, %d API key, %d invalid)\n", sum.Profiles, sum.OAuth, sum.ApiKey, sum.Invalid)
+	fmt.Printf("Expired:  %d\n", sum.Expired)
+	if len(sum.NeedsReauth) > 0 {
+		fmt.Printf("Needs re-authentication: %s\n", strings.Join(sum.NeedsReauth, ", "))
+	}
+	return nil
+}
+
+// listErrors shows only the profiles that fail to load, with the reason, and
+// fails if there are any so it can gate scripts.
+func listErrors(names []string, colour bool) error {
+	tw := tabwriter.NewWriter(os.Stdout, 0, 0, 2, ' ', 0)
+	w := colourWriter(tw, colour)
+	broken := 0
+	for _, name := range names {
+		_, err := loadProfile(name)
+		if err == nil {
+			continue
+		}
+		if broken == 0 {
+			fmt.Fprintf(w, "%s\t%s\n", ansiBold+"NAME"+ansiReset, ansiBold+"ERROR"+ansiReset)
+		}
+		broken++
+		fmt.Fprintf(w, "%s\t%v\n", escapeControl(name), err)
+	}
+	tw.Flush()
+
+	if broken > 0 {
+		return fmt.Errorf("%d profile(s) failed to load", broken)
+	}
+	logf("All profiles load cleanly.\n")
+	return nil
+}
+
+// limitEntries keeps the active profile plus the n most recently used others,
+// in their original order, and reports how many were dropped.
+func limitEntries(entries []listEntry, state *State, n int) ([]listEntry, int) {
+	var others []int
+	for i, e := range entries {
+		if !state.isActive(e.name) {
+			others = append(others, i)
+		}
+	}
+	sort.SliceStable(others, func(a, b int) bool {
+		return state.LastUsed[entries[others[a]].name] > state.LastUsed[entries[others[b]].name]
+	})
+
+	keep := make(map[int]bool)
+	for rank, i := range others {
+		if rank < n {
+			keep[i] = true
+		}
+	}
+
+	var kept []listEntry
+	for i, e := range entries {
+		if state.isActive(e.name) || keep[i] {
+			kept = append(kept, e)
+		}
+	}
+	return kept, len(entries) - len(kept)
+}
+
+// profileInfo is everything `info` reports about one profile.
+type profileInfo struct {
+	Name             string          `json:"name"`
+	Type             string          `json:"type"`
+	Path             string          `json:"path"`
+	Active           bool            `json:"active"`
+	Label            *string         `json:"label,omitempty"`
+	Account          json.RawMessage `json:"account,omitempty"`
+	Scopes           []string        `json:"scopes,omitempty"`
+	SubscriptionType *string         `json:"subscription_type,omitempty"`
+	RateLimitTier    *string         `json:"rate_limit_tier,omitempty"`
+	ExpiresAt        *uint64         `json:"expires_at,omitempty"`
+	ClaimedExpiresAt *uint64         `json:"claimed_expires_at,omitempty"`
+	LastUsed         *uint64         `json:"last_used,omitempty"`
+	LastRefreshedAt  *uint64         `json:"last_refreshed_at,omitempty"`
+	DefaultCommand   []string        `json:"default_command,omitempty"`
+	AccessToken      string          `json:"access_token,omitempty"`
+	RefreshToken     string          `json:"refresh_token,omitempty"`
+	ApiKey           string          `json:"api_key,omitempty"`
+	Workspace        string          `json:"workspace,omitempty"`
+}
+
+func cmdInfo(args []string) error {
+	parsed, err := parseArgs(args, "--json", "--show-secrets")
+	if err != nil {
+		return err
+	}
+	if len(parsed.pos) == 0 {
+		return fmt.Errorf("info requires a profile name")
+	}
+	name := parsed.pos[0]
+
+	profile, err := loadProfile(name)
+	if err != nil {
+		return err
+	}
+	state := loadState()
+
+	secret := maskSecret
+	if parsed.has("--show-secrets") {
+		secret = func(s string) string { return s }
+	}
+
+	info := profileInfo{
+		Name:            name,
+		Type:            profile.Type,
+		Path:            profileLocation(name),
+		Active:          state.isActive(name),
+		Label:           profile.Label,
+		Account:         profile.Account,
+		LastRefreshedAt: profile.LastRefreshedAt,
+		DefaultCommand:  profile.DefaultCommand,
+	}
+	if ts, ok := state.LastUsed[name]; ok {
+		info.LastUsed = &ts
+	}
+	if c := profile.Credentials; c != nil {
+		info.Scopes = c.Scopes
+		info.SubscriptionType = c.SubscriptionType
+		info.RateLimitTier = c.RateLimitTier
+		info.ExpiresAt = &c.ExpiresAt
+		if claimed, mismatch := claimedExpiry(c); mismatch {
+			info.ClaimedExpiresAt = &claimed
+		}
+		info.AccessToken = secret(c.AccessToken)
+		info.RefreshToken = secret(c.RefreshToken)
+	}
+	if profile.ApiKey != "" {
+		info.ApiKey = secret(profile.ApiKey)
+		info.Workspace = profile.Workspace
+	}
+
+	if parsed.has("--json") {
+		out, err := json.MarshalIndent(info, "", "  ")
+		if err != nil {
+			return err
+		}
+		fmt.Println(string(out))
+		return nil
+	}
+
+	tw := tabwriter.NewWriter(os.Stdout, 0, 0, 2, ' ', 0)
+	w := colourWriter(tw, stdoutColour())
+	field := func(key, value string) {
+		if value != "" {
+			fmt.Fprintf(w, "%s\t%s\n", ansiBold+key+ansiReset, value)
+		}
+	}
+	field("Name", info.Name)
+	field("Type", info.Type)
+	field("Active", strconv.FormatBool(info.Active))
+	field("File", info.Path)
+	if info.Label != nil {
+		field("Label", *info.Label)
+	}
+
+	var account map[string]any
+	if json.Unmarshal(info.Account, &account) == nil {
+		keys := make([]string, 0, len(account))
+		for k := range account {
+			keys = append(keys, k)
+		}
+		sort.Strings(keys)
+		for _, k := range keys {
+			field(k, fmt.Sprint(account[k]))
+		}
+	}
+
+	field("Scopes", strings.Join(info.Scopes, " "))
+	field("Plan", profile.DisplaySub())
+	if info.RateLimitTier != nil {
+		field("Rate limit tier", *info.RateLimitTier)
+	}
+	if info.ExpiresAt != nil {
+		expires := formatTimestamp(*info.ExpiresAt)
+		if *info.ExpiresAt < nowMs() {
+			expires = strings.Replace(expires, "(", "(expired ", 1)
+		}
+		field("Expires", expires)
+	}
+	if info.ClaimedExpiresAt != nil {
+		field("Token claims", "expires "+formatTimestamp(*info.ClaimedExpiresAt)+", not matching the stored expiry")
+	}
+	if info.LastRefreshedAt != nil {
+		field("Last refreshed", formatTimestamp(*info.LastRefreshedAt))
+	}
+	if info.LastUsed != nil {
+		field("Last used", formatTimestamp(*info.LastUsed))
+	}
+	field("Default command", strings.Join(info.DefaultCommand, " "))
+	field("Access token", info.AccessToken)
+	field("Refresh token", info.RefreshToken)
+	field("API key", info.ApiKey)
+	field("Workspace", info.Workspace)
+	return tw.Flush()
+}
+
+// cmdSetDisabled handles both disable and enable, named by args[0].
+func cmdSetDisabled(args []string) error {
+	if len(args) < 2 {
+		return fmt.Errorf("%s requires a profile name", args[0])
+	}
+	name := args[1]
+	disable := args[0] == "disable"
+
+	if _, err := loadProfile(name); err != nil {
+		return err
+	}
+	state := loadState()
+	if state.isDisabled(name) == disable {
+		logf("Profile '%s' is already %sd\n", name, args[0])
+		return nil
+	}
+	if disable {
+		if state.Disabled == nil {
+			state.Disabled = make(map[string]bool)
+		}
+		state.Disabled[name] = true
+	} else {
+		delete(state.Disabled, name)
+	}
+	if err := saveState(&state); err != nil {
+		return err
+	}
+	logf("Profile '%s' %sd\n", name, args[0])
+	return nil
+}
+
+// markerFile names the file that pins a directory tree to a profile for
+// `auto`.
+const markerFile = ".claude-switch"
+
+// findMarker walks up from dir to the root and returns the first marker
+// file, or "" if there is none.
+func findMarker(dir string) string {
+	for {
+		path := filepath.Join(dir, markerFile)
+		if info, err := os.Stat(path); err == nil && !info.IsDir() {
+			return path
+		}
+		parent := filepath.Dir(dir)
+		if parent == dir {
+			return ""
+		}
+		dir = parent
+	}
+}
+
+// readMarker returns the profile named by a marker file: its first line
+// that isn't blank or a # comment.
+func readMarker(path string) (string, error) {
+	f, err := os.Open(path)
+	if err != nil {
+		return "", err
+	}
+	defer f.Close()
+	scanner := bufio.NewScanner(io.LimitReader(f, 4096))
+	for scanner.Scan() {
+		line := strings.TrimSpace(scanner.Text())
+		if line == "" || strings.HasPrefix(line, "#") {
+			continue
+		}
+		if err := validateProfileName(line); err != nil {
+			return "", err
+		}
+		return line, nil
+	}
+	if err := scanner.Err(); err != nil {
+		return "", err
+	}
+	return "", fmt.Errorf("it names no profile")
+}
+
+// cmdAuto switches to the profile pinned by the nearest .claude-switch file,
+// or else the default profile. It does nothing when that profile is already
+// active, so it is cheap enough to run from a shell's cd hook.
+func cmdAuto(args []string) error {
+	parsed, err := parseArgs(args, "--kill|-k")
+	if err != nil {
+		return err
+	}
+	if len(parsed.pos) > 0 {
+		return fmt.Errorf("unexpected argument: %s", parsed.pos[0])
+	}
+	cwd, err := os.Getwd()
+	if err != nil {
+		return err
+	}
+
+	state := loadState()
+	var name string
+	if marker := findMarker(cwd); marker != "" {
+		// A broken marker stops here rather than falling back, which could
+		// switch the project to the wrong account
+		if name, err = readMarker(marker); err != nil {
+			return fmt.Errorf("%s: %w", marker, err)
+		}
+		debugf("%s names '%s'\n", marker, name)
+	} else if state.DefaultProfile != nil {
+		name = *state.DefaultProfile
+		debugf("no %s found; using the default profile '%s'\n", markerFile, name)
+	} else {
+		logf("No %s file here or above, and no default profile; nothing to do\n", markerFile)
+		return nil
+	}
+
+	if state.isActive(name) {
+		return nil
+	}
+	useArgs := []string{name}
+	if parsed.has("--kill") {
+		useArgs = append(useArgs, "--kill")
+	}
+	return cmdUse(useArgs)
+}
+
+// cmdSetDefault sets the profile `use` falls back to when given no name.
+// Without arguments it prints the current default.
+func cmdSetDefault(args []string) error {
+	parsed, err := parseArgs(args, "--clear")
+	if err != nil {
+		return err
+	}
+	state := loadState()
+	if parsed.has("--clear") {
+		if state.DefaultProfile == nil {
+			logf("No default profile is set\n")
+			return nil
+		}
+		state.DefaultProfile = nil
+		if err := saveState(&state); err != nil {
+			return err
+		}
+		logf("Cleared the default profile\n")
+		return nil
+	}
+	if len(parsed.pos) == 0 {
+		if state.DefaultProfile == nil {
+			return fmt.Errorf("no default profile is set")
+		}
+		fmt.Println(*state.DefaultProfile)
+		return nil
+	}
+
+	name := parsed.pos[0]
+	if _, err := loadProfile(name); err != nil {
+		return err
+	}
+	state.DefaultProfile = &name
+	if err := saveState(&state); err != nil {
+		return err
+	}
+	logf("'%s' is now the default profile\n", name)
+	return nil
+}
+
+func cmdNick(args []string) error {
+	if len(args) == 0 {
+		return fmt.Errorf("nick requires a profile name")
+	}
+	name := args[0]
+	nick := strings.TrimSpace(strings.Join(args[1:], " "))
+
+	if _, err := loadProfile(name); err != nil {
+		return err
+	}
+	state := loadState()
+	if nick == "" {
+		delete(state.Nicknames, name)
+	} else {
+		if state.Nicknames == nil {
+			state.Nicknames = make(map[string]string)
+		}
+		state.Nicknames[name] = nick
+	}
+	if err := saveState(&state); err != nil {
+		return err
+	}
+
+	if nick == "" {
+		logf("Cleared the nickname of '%s'\n", name)
+	} else {
+		logf("Profile '%s' will be shown as '%s'\n", name, nick)
+	}
+	return nil
+}
+
+// cmdLabel sets or clears a profile's label, a free-form note stored in the
+// profile itself and shown by list and info.
+func cmdLabel(args []string) error {
+	if len(args) == 0 {
+		return fmt.Errorf("label requires a profile name")
+	}
+	name := args[0]
+	text := strings.TrimSpace(strings.Join(args[1:], " "))
+
+	profile, err := loadProfile(name)
+	if err != nil {
+		return err
+	}
+	if text == "" {
+		profile.Label = nil
+	} else {
+		profile.Label = &text
+	}
+	if err := saveProfile(name, profile); err != nil {
+		return err
+	}
+
+	if text == "" {
+		logf("Cleared the label of '%s'\n", name)
+	} else {
+		logf("Labelled '%s': %s\n", name, text)
+	}
+	return nil
+}
+
+func cmdRename(args []string) error {
+	if len(args) != 2 {
+		return fmt.Errorf("rename requires the current and new profile names")
+	}
+	if err := renameProfile(args[0], args[1]); err != nil {
+		return err
+	}
+	logf("Renamed profile '%s' to '%s'\n", args[0], args[1])
+	return nil
+}
+
+// cmdPurge deletes the files claude-switch owns in configDir, and with
+// --logout Claude's auth keys too. configDir can be pointed anywhere, so
+// anything it doesn't recognise is left alone, as are the directory itself
+// and the lock file, which is held while purging.
+func cmdPurge(args []string) error {
+	parsed, err := parseArgs(args, "--yes", "--logout")
+	if err != nil {
+		return err
+	}
+	if err := requireWritable("purge"); err != nil {
+		return err
+	}
+
+	dir := configDir()
+	paths := ownedPaths()
+	state := loadState()
+	if len(paths) == 0 && len(state.KeyringProfiles) == 0 && !parsed.has("--logout") {
+		logf("Nothing to purge in %s\n", dir)
+		return nil
+	}
+
+	if !parsed.has("--yes") {
+		fmt.Fprintf(os.Stderr, "This permanently deletes every profile (including the trash) and all state in %s.\n", dir)
+		fmt.Fprint(os.Stderr, "Type 'purge' to continue: ")
+		answer, _ := bufio.NewReader(os.Stdin).ReadString('\n')
+		if strings.TrimSpace(answer) != "purge" {
+			return fmt.Errorf("purge cancelled")
+		}
+	}
+
+	// Keyring entries first, while state.json still indexes them
+	for _, name := range state.KeyringProfiles {
+		if err := keyringDelete(name); err != nil {
+			return err
+		}
+		logf("Removed '%s' from the keyring\n", name)
+	}
+	for _, path := range paths {
+		if err := os.RemoveAll(path); err != nil {
+			return explainWriteError(path, err)
+		}
+		logf("Removed %s\n", path)
+	}
+
+	if parsed.has("--logout") {
+		if err := clearAuth(); err != nil {
+			return err
+		}
+		logf("Cleared Claude's credentials from %s and %s\n", credentialsPath(), claudeJSONPath())
+	}
+	return nil
+}
+
+// ownedPaths lists what purge deletes that exists: the profiles and trash
+// directories, the state file and claude-switch's temp files.
+func ownedPaths() []string {
+	var paths []string
+	for _, path := range []string{profilesDir(), trashDir(), statePath()} {
+		if _, err := os.Lstat(path); err == nil {
+			paths = append(paths, path)
+		}
+	}
+	entries, _ := os.ReadDir(configDir())
+	for _, e := range entries {
+		if !e.IsDir() && isTempFile(e.Name()) {
+			paths = append(paths, filepath.Join(configDir(), e.Name()))
+		}
+	}
+	return paths
+}
+
+func cmdRemove(args []string) error {
+	names, err := readNames(args)
+	if err != nil {
+		return err
+	}
+	if len(names) == 0 {
+		return fmt.Errorf("remove requires a profile name")
+	}
+
+	failed := 0
+	for _, name := range names {
+		if err := removeProfile(name); err != nil {
+			if len(names) == 1 {
+				return err
+			}
+			fmt.Fprintf(os.Stderr, "Failed to remove '%s': %v\n", name, err)
+			failed++
+			continue
+		}
+		logf("Removed profile '%s' (undo with 'claude-switch trash restore %s')\n", name, name)
+	}
+	if failed > 0 {
+		return fmt.Errorf("%d of %d profiles failed to remove", failed, len(names))
+	}
+	return nil
+}
+
+// cmdPrune removes profiles that can't be used any more: those that fail to
+// parse, and OAuth profiles whose expired token is refused with invalid_grant.
+// Expired profiles that still refresh keep their new tokens. Profiles that
+// can't be read or decrypted, or fail to refresh for another reason, are
+// kept, since that says nothing about the profile itself.
+func cmdPrune(args []string) error {
+	parsed, err := parseArgs(args, "--dry-run", "--yes", "--parallel|-j=")
+	if err != nil {
+		return err
+	}
+	jobs, err := parseJobs(parsed)
+	if err != nil {
+		return err
+	}
+	if err := requireWritable("prune"); err != nil {
+		return err
+	}
+	names, err := listProfiles()
+	if err != nil {
+		return err
+	}
+
+	reasons := make(map[string]string)
+	var expired []string
+	profiles := make(map[string]*Profile)
+	for _, name := range names {
+		data, err := readProfileData(name)
+		if err == nil && isEncrypted(data) {
+			data, err = decryptProfile(data)
+		}
+		if err != nil {
+			fmt.Fprintf(os.Stderr, "Warning: skipping '%s': %v\n", name, err)
+			continue
+		}
+		profile, err := decodeProfile(data)
+		if err != nil {
+			reasons[name] = fmt.Sprintf("doesn't parse: %v", err)
+			continue
+		}
+		if profile.Type == "oauth" && profile.Credentials != nil && isExpired(profile.Credentials, expiryBuffer()) {
+			expired = append(expired, name)
+			profiles[name] = profile
+		}
+	}
+
+	errs := make([]error, len(expired))
+	runParallel(len(expired), jobs, func(i int) {
+		errs[i] = refreshProfile(expired[i], profiles[expired[i]])
+	})
+	for i, err := range errs {
+		var re *RefreshError
+		switch {
+		case errors.As(err, &re) && re.Kind == refreshInvalidGrant:
+			reasons[expired[i]] = "refresh token was revoked"
+		case err != nil:
+			fmt.Fprintf(os.Stderr, "Warning: keeping '%s', which couldn't be refreshed: %v\n", expired[i], err)
+		default:
+			logf("Refreshed '%s'\n", expired[i])
+		}
+	}
+
+	if len(reasons) == 0 {
+		logf("Nothing to prune\n")
+		return nil
+	}
+	var dead []string
+	for _, name := range names {
+		if reason, ok := reasons[name]; ok {
+			dead = append(dead, name)
+			fmt.Printf("%s: %s\n", escapeControl(name), reason)
+		}
+	}
+	if parsed.has("--dry-run") {
+		return nil
+	}
+
+	if !parsed.has("--yes") {
+		fmt.Fprintf(os.Stderr, "Remove %d profile(s)? They are kept in the trash. [y/N] ", len(dead))
+		answer, _ := bufio.NewReader(os.Stdin).ReadString('\n')
+		if a := strings.ToLower(strings.TrimSpace(answer)); a != "y" && a != "yes" {
+			return fmt.Errorf("prune cancelled")
+		}
+	}
+	failed := 0
+	for _, name := range dead {
+		if err := removeProfile(name); err != nil {
+			fmt.Fprintf(os.Stderr, "Failed to remove '%s': %v\n", name, err)
+			failed++
+			continue
+		}
+		logf("Removed profile '%s' (undo with 'claude-switch trash restore %s')\n", name, name)
+	}
+	if failed > 0 {
+		return fmt.Errorf("%d of %d profiles failed to remove", failed, len(dead))
+	}
+	return nil
+}
+
+// cmdComplete prints the profile names that start with a prefix, one per
+// line. It is hidden from the usage text and backs shell completion.
+func cmdComplete(args []string) error {
+	prefix := ""
+	if len(args) > 0 {
+		prefix = args[0]
+	}
+	names, err := listProfiles()
+	if err != nil {
+		return nil
+	}
+	for _, name := range names {
+		if strings.HasPrefix(name, prefix) {
+			fmt.Println(name)
+		}
+	}
+	return nil
+}
+
+// cmdRotate rotates the refresh token of each named profile, or of every
+// OAuth profile with --all, making up to -j token requests at once.
+func cmdRotate(args []string) error {
+	parsed, err := parseArgs(args, "--all", "--parallel|-j=")
+	if err != nil {
+		return err
+	}
+	jobs, err := parseJobs(parsed)
+	if err != nil {
+		return err
+	}
+
+	names, err := readNames(parsed.pos)
+	if err != nil {
+		return err
+	}
+	if parsed.has("--all") {
+		if len(names) > 0 {
+			return fmt.Errorf("rotate takes either profile names or --all, not both")
+		}
+		all, err := listProfiles()
+		if err != nil {
+			return err
+		}
+		state := loadState()
+		for _, name := range all {
+			if state.isDisabled(name) {
+				continue
+			}
+			if profile, err := loadProfile(name); err == nil && profile.Type == "oauth" {
+				names = append(names, name)
+			}
+		}
+		if len(names) == 0 {
+			return fmt.Errorf("no OAuth profiles to rotate")
+		}
+	}
+	if len(names) == 0 {
+		return fmt.Errorf("rotate requires a profile name (or --all)")
+	}
+	if err := requireWritable("rotate"); err != nil {
+		return err
+	}
+
+	errs := make([]error, len(names))
+	runParallel(len(names), jobs, func(i int) {
+		errs[i] = rotateProfile(names[i])
+	})
+	if len(names) == 1 {
+		return errs[0]
+	}
+	failed := 0
+	for i, err := range errs {
+		if err != nil {
+			fmt.Fprintf(os.Stderr, "Failed to rotate '%s': %v\n", names[i], err)
+			failed++
+		}
+	}
+	if failed > 0 {
+		return fmt.Errorf("%d of %d profiles failed to rotate", failed, len(names))
+	}
+	return nil
+}
+
+func rotateProfile(name string) error {
+	profile, err := loadProfile(name)
+	if err != nil {
+		return err
+	}
+	if profile.Type != "oauth" {
+		return fmt.Errorf("profile '%s' uses an API key; there is no refresh token to rotate", name)
+	}
+
+	oldToken := profile.Credentials.RefreshToken
+	if err := refreshProfile(name, profile); err != nil {
+		return err
+	}
+
+	if profile.Credentials.RefreshToken != oldToken {
+		logf("Rotated refresh token for '%s'\n", name)
+	} else {
+		logf("Refreshed '%s', but the server kept the same refresh token\n", name)
+	}
+	return nil
+}
+
+// cmdRefresh refreshes the named profiles' tokens without switching to
+// them, for scripts that keep tokens fresh. Unlike use, a revoked refresh
+// token is reported rather than answered with an interactive login.
+func cmdRefresh(args []string) error {
+	parsed, err := parseArgs(args, "--if-needed", "--parallel|-j=")
+	if err != nil {
+		return err
+	}
+	jobs, err := parseJobs(parsed)
+	if err != nil {
+		return err
+	}
+	names, err := readNames(parsed.pos)
+	if err != nil {
+		return err
+	}
+	if len(names) == 0 {
+		return fmt.Errorf("refresh requires a profile name")
+	}
+
+	errs := make([]error, len(names))
+	runParallel(len(names), jobs, func(i int) {
+		errs[i] = refreshNamed(names[i], parsed.has("--if-needed"))
+	})
+	if len(names) == 1 {
+		return errs[0]
+	}
+	failed := 0
+	for i, err := range errs {
+		if err != nil {
+			fmt.Fprintf(os.Stderr, "Failed to refresh '%s': %v\n", names[i], err)
+			failed++
+		}
+	}
+	if failed > 0 {
+		return fmt.Errorf("%d of %d profiles failed to refresh", failed, len(names))
+	}
+	return nil
+}
+
+func refreshNamed(name string, ifNeeded bool) error {
+	profile, err := loadProfile(name)
+	if err != nil {
+		return err
+	}
+	if profile.Type != "oauth" {
+		logf("Profile '%s' uses an API key; there is no token to refresh\n", name)
+		return nil
+	}
+	if ifNeeded && !isExpired(profile.Credentials, expiryBuffer()) {
+		logf("Token for '%s' is still valid; expires %s\n", name, formatTimestamp(profile.Credentials.ExpiresAt))
+		return nil
+	}
+	if err := requireWritable("refresh"); err != nil {
+		return err
+	}
+
+	if err := refreshProfile(name, profile); err != nil {
+		return explainRevoked(name, err)
+	}
+	logf("Refreshed '%s'; expires %s\n", name, formatTimestamp(profile.Credentials.ExpiresAt))
+	return nil
+}
+
+// explainRevoked turns a revoked refresh token into an error that says how
+// to re-authenticate, for commands that mustn't start a login themselves.
+func explainRevoked(name string, err error) error {
+	var re *RefreshError
+	if errors.As(err, &re) && re.Kind == refreshInvalidGrant {
+		return &RefreshError{
+			Kind:    refreshInvalidGrant,
+			Message: fmt.Sprintf("refresh token for '%s' was revoked; re-authenticate with 'claude-switch remove %s && claude-switch add %s'", name, name, name),
+		}
+	}
+	return err
+}
+
+func cmdTrash(args []string) error {
+	if len(args) == 0 {
+		return fmt.Errorf("trash requires a subcommand: list, restore <name> or empty")
+	}
+
+	switch args[0] {
+	case "list":
+		trashed, err := listTrash()
+		if err != nil {
+			return err
+		}
+		if len(trashed) == 0 {
+			logf("Trash is empty.\n")
+			return nil
+		}
+		tw := tabwriter.NewWriter(os.Stdout, 0, 0, 2, ' ', 0)
+		w := colourWriter(tw, stdoutColour())
+		fmt.Fprintf(w, "%s\t%s\n", ansiBold+"NAME"+ansiReset, ansiBold+"REMOVED"+ansiReset)
+		for _, t := range trashed {
+			removed := time.UnixMilli(int64(t.RemovedAt)).UTC().Format("2006-01-02 15:04 UTC")
+			fmt.Fprintf(w, "%s\t%s\n", escapeControl(t.Name), removed)
+		}
+		tw.Flush()
+	case "restore":
+		if len(args) < 2 {
+			return fmt.Errorf("trash restore requires a profile name")
+		}
+		if err := restoreFromTrash(args[1]); err != nil {
+			return err
+		}
+		logf("Restored profile '%s'\n", args[1])
+	case "empty":
+		n, err := emptyTrash()
+		if err != nil {
+			return err
+		}
+		logf("Permanently deleted %d trashed profile(s)\n", n)
+	default:
+		return fmt.Errorf("unknown trash subcommand: %s", args[0])
+	}
+	return nil
+}
+
+func cmdExec(args []string) error {
+	// exec's own flags come before the command; everything from the first
+	// word after the name (or after "--") belongs to the command
+	var name string
+	var cmdArgs []string
+	var timeout uint64
+scan:
+	for i := 0; i < len(args); i++ {
+		a := args[i]
+		switch {
+		case a == "--":
+			cmdArgs = args[i+1:]
+			break scan
+		case a == "--timeout" || strings.HasPrefix(a, "--timeout="):
+			value, ok := strings.CutPrefix(a, "--timeout=")
+			if !ok {
+				if i+1 >= len(args) {
+					return fmt.Errorf("--timeout requires a value")
+				}
+				i++
+				value = args[i]
+			}
+			ms, err := parseDuration(value)
+			if err != nil {
+				return err
+			}
+			if ms == 0 {
+				return fmt.Errorf("--timeout must be longer than zero")
+			}
+			timeout = ms
+		case name == "":
+			name = a
+		default:
+			cmdArgs = args[i:]
+			break scan
+		}
+	}
+	if name == "" {
+		return fmt.Errorf("exec requires a profile name")
+	}
+
+	profile, err := loadProfile(name)
+	if err != nil {
+		return err
+	}
+
+	if len(cmdArgs) == 0 {
+		if len(profile.DefaultCommand) == 0 {
+			return fmt.Errorf("no command specified, and profile '%s' has no default command", name)
+		}
+		cmdArgs = profile.DefaultCommand
+	}
+
+	vars, err := profileEnv(name, profile)
+	if err != nil {
+		return err
+	}
+	return execWithEnv(cmdArgs, vars, timeout)
+}
+
+type envVar struct {
+	key, value string
+}
+
+// profileEnv returns the environment variables that hand a profile's
+// credentials to Claude, refreshing an expired OAuth token first. Its callers
+// print to stdout or hand it to another program, so a revoked refresh token
+// is reported rather than answered with an interactive login.
+func profileEnv(name string, profile *Profile) ([]envVar, error) {
+	if profile.Type != "oauth" {
+		vars := []envVar{{"ANTHROPIC_API_KEY", profile.ApiKey}}
+		if profile.Workspace != "" {
+			vars = append(vars, envVar{"ANTHROPIC_WORKSPACE", profile.Workspace})
+		}
+		return vars, nil
+	}
+	if err := refreshIfExpired(name, profile); err != nil {
+		return nil, explainRevoked(name, err)
+	}
+	return []envVar{{"CLAUDE_CODE_OAUTH_TOKEN", profile.Credentials.AccessToken}}, nil
+}
+
+// cmdEnv prints the variable exec would set, for tools that read a .env file
+// or for eval "$(claude-switch env <name>)".
+func cmdEnv(args []string) error {
+	parsed, err := parseArgs(args, "--format=")
+	if err != nil {
+		return err
+	}
+	if len(parsed.pos) != 1 {
+		return fmt.Errorf("env requires a profile name")
+	}
+	name := parsed.pos[0]
+	format := "shell"
+	if parsed.has("--format") {
+		format = parsed.value("--format")
+	}
+	if format != "shell" && format != "fish" && format != "dotenv" {
+		return fmt.Errorf("unknown format '%s': expected shell, fish or dotenv", format)
+	}
+
+	profile, err := loadProfile(name)
+	if err != nil {
+		return err
+	}
+	vars, err := profileEnv(name, profile)
+	if err != nil {
+		return err
+	}
+
+	printEnv(vars, format)
+	return nil
+}
+
+// printEnv prints vars as shell, fish or dotenv assignments.
+func printEnv(vars []envVar, format string) {
+	for _, v := range vars {
+		switch format {
+		case "shell":
+			fmt.Printf("export %s='%s'\n", v.key, strings.ReplaceAll(v.value, "'", `'\''`))
+		case "fish":
+			value := strings.NewReplacer(`\`, `\\`, "'", `\'`).Replace(v.value)
+			fmt.Printf("set -gx %s '%s'\n", v.key, value)
+		case "dotenv":
+			fmt.Printf("%s=%s\n", v.key, v.value)
+		}
+	}
+}
+
+// credentialVars are every variable profileEnv can set. activate unsets the
+// ones a profile doesn't use, so switching from an API key profile to an
+// OAuth one doesn't leave the key behind.
+var credentialVars = []string{"CLAUDE_CODE_OAUTH_TOKEN", "ANTHROPIC_API_KEY", "ANTHROPIC_WORKSPACE"}
+
+// cmdActivate prints shell code that points the current shell at a profile,
+// for eval "$(claude-switch activate <name>)". Unlike use it leaves Claude's
+// config files alone and works the same for both profile types.
+func cmdActivate(args []string) error {
+	parsed, err := parseArgs(args, "--shell=")
+	if err != nil {
+		return err
+	}
+	if len(parsed.pos) != 1 {
+		return fmt.Errorf("activate requires a profile name")
+	}
+	name := parsed.pos[0]
+
+	shell := parsed.value("--shell")
+	if shell == "" {
+		shell = filepath.Base(os.Getenv("SHELL"))
+	}
+	format := "shell"
+	switch shell {
+	case "fish":
+		format = "fish"
+	case "bash", "zsh", "sh", "dash", "ksh":
+	default:
+		if parsed.has("--shell") {
+			return fmt.Errorf("unsupported shell '%s': expected bash, zsh, sh, dash, ksh or fish", shell)
+		}
+		debugf("unrecognised $SHELL '%s', printing POSIX shell code\n", shell)
+	}
+
+	profile, err := loadProfile(name)
+	if err != nil {
+		return err
+	}
+	vars, err := profileEnv(name, profile)
+	if err != nil {
+		return err
+	}
+
+	for _, key := range credentialVars {
+		if slices.ContainsFunc(vars, func(v envVar) bool { return v.key == key }) {
+			continue
+		}
+		if format == "fish" {
+			fmt.Printf("set -e %s\n", key)
+		} else {
+			fmt.Printf("unset %s\n", key)
+		}
+	}
+	printEnv(vars, format)
+	return nil
+}
+
+// killGrace is how long a timed-out command gets to exit after SIGTERM
+// before it is killed outright.
+const killGrace = 5 * time.Second
+
+var errTimeout = errors.New("command timed out")
+
+// childExitError carries a child's non-zero exit status back to main, which
+// exits with it.
+type childExitError struct {
+	code int
+}
+
+func (e *childExitError) Error() string {
+	return fmt.Sprintf("command exited with status %d", e.code)
+}
+
+// execWithEnv replaces claude-switch with the command where the platform
+// allows. With a timeout it runs the command as a child instead, so it can
+// be stopped if it runs too long.
+func execWithEnv(args []string, vars []envVar, timeout uint64) error {
+	binary, err := exec.LookPath(args[0])
+	if err != nil {
+		return fmt.Errorf("exec failed: %w", err)
 	}
-
-	// Clear Claude's auth so the CLI triggers its first-run login flow
-	if err := clearAuth(); err != nil {
-		return err
+	env := os.Environ()
+	for _, v := range vars {
+		env = append(env, v.key+"="+v.value)
+	}
+	if timeout == 0 {
+		return replaceProcess(binary, args, env)
 	}
 
-	cmd := exec.Command("claude", "/login")
+	cmd := exec.Command(binary, args[1:]...)
+	cmd.Env = env
 	cmd.Stdin = os.Stdin
 	cmd.Stdout = os.Stdout
 	cmd.Stderr = os.Stderr
-	if err := cmd.Run(); err != nil {
-		return fmt.Errorf("claude exited with error — use 'claude-switch use <profile>' to restore your previous session: %w", err)
+	if err := cmd.Start(); err != nil {
+		return fmt.Errorf("exec failed: %w", err)
 	}
+	done := make(chan error, 1)
+	go func() { done <- cmd.Wait() }()
 
-	profile, err := importCurrentCredentials()
-	if err != nil {
+	select {
+	case err := <-done:
+		var exitErr *exec.ExitError
+		if errors.As(err, &exitErr) {
+			code := exitErr.ExitCode()
+			if code < 0 {
+				code = exitError
+			}
+			return &childExitError{code: code}
+		}
 		return err
+	case <-time.After(time.Duration(timeout) * time.Millisecond):
 	}
 
-	if err := saveProfile(name, profile); err != nil {
-		return err
+	logf("Command still running after %s; stopping it\n", formatDuration(timeout))
+	// Windows can't deliver SIGTERM, so the command is killed straight away
+	if cmd.Process.Signal(syscall.SIGTERM) != nil {
+		cmd.Process.Kill()
 	}
-
-	state := loadState()
-	state.ActiveProfile = &name
-	if err := saveState(&state); err != nil {
-		return err
+	select {
+	case <-done:
+	case <-time.After(killGrace):
+		cmd.Process.Kill()
+		<-done
 	}
-
-	printProfileSaved("Saved", name, profile)
-	return nil
+	return errTimeout
 }
 
-func cmdImport(name string) error {
-	if profileExists(name) {
-		return fmt.Errorf("profile '%s' already exists (use 'remove' first)", name)
-	}
+// Exit codes of `prompt --exit-code`.
+const (
+	promptHealthy  = 0
+	promptNoActive = 1
+	promptExpired  = 2
+)
 
-	profile, err := importCurrentCredentials()
+// cmdPrompt is called on every shell prompt, so it never touches the network
+// and never writes anything.
+func cmdPrompt(args []string) error {
+	parsed, err := parseArgs(args, "--exit-code")
 	if err != nil {
-		return fmt.Errorf("no credentials found — is Claude Code logged in?")
-	}
-
-	if err := saveProfile(name, profile); err != nil {
 		return err
 	}
 
 	state := loadState()
-	state.ActiveProfile = &name
-	if err := saveState(&state); err != nil {
-		return err
+	health := promptNoActive
+	var profile *Profile
+	if state.ActiveProfile != nil {
+		// A shell prompt must never stop to ask for the passphrase
+		if state.Locked && os.Getenv("CLAUDE_SWITCH_PASSPHRASE") == "" {
+			if profileExists(*state.ActiveProfile) {
+				health = promptHealthy
+			}
+		} else if profile, err = loadProfile(*state.ActiveProfile); err == nil {
+			health = promptHealthy
+			if profile.Credentials != nil && isExpired(profile.Credentials, expiryBuffer()) {
+				health = promptExpired
+			}
+		}
 	}
 
-	if profile.Type == "oauth" {
-		email := profile.DisplayEmail()
-		sub := profile.DisplaySub()
-		fmt.Fprintf(os.Stderr, "Imported current session as '%s' (%s, %s)\n", name, email, sub)
-	} else {
-		fmt.Fprintf(os.Stderr, "Imported current session as '%s' (API key)\n", name)
+	if parsed.has("--exit-code") {
+		os.Exit(health)
+	}
+	switch health {
+	case promptHealthy:
+		fmt.Println(state.displayName(*state.ActiveProfile))
+	case promptExpired:
+		fmt.Println(state.displayName(*state.ActiveProfile) + " (expired)")
 	}
 	return nil
 }
 
-func claudePIDs() []int {
-	out, err := exec.Command("pgrep", "-x", "claude").Output()
+// cmdToken prints a profile's bearer token or API key, and nothing else, on
+// stdout for scripts that call the API themselves.
+func cmdToken(args []string) error {
+	parsed, err := parseArgs(args, "--no-refresh")
 	if err != nil {
-		return nil
-	}
-	var pids []int
-	for _, s := range strings.Fields(string(out)) {
-		var pid int
-		if _, err := fmt.Sscanf(s, "%d", &pid); err == nil {
-			pids = append(pids, pid)
-		}
+		return err
 	}
-	return pids
-}
-
-func cmdUse(name string, kill bool) error {
-	if pids := claudePIDs(); len(pids) > 0 {
-		if kill {
-			exec.Command("pkill", "-x", "claude").Run()
-			fmt.Fprintln(os.Stderr, "Terminated running Claude sessions.")
-		} else {
-			fmt.Fprintln(os.Stderr, "Warning: Claude is running. It may overwrite the switched credentials.")
-			fmt.Fprintln(os.Stderr, "Re-run with --kill to terminate existing sessions before switching.")
-		}
+	if len(parsed.pos) == 0 {
+		return fmt.Errorf("token requires a profile name")
 	}
+	name := parsed.pos[0]
 
 	profile, err := loadProfile(name)
 	if err != nil {
 		return err
 	}
 
+	secret := profile.ApiKey
 	if profile.Type == "oauth" {
-		if isExpired(profile.Credentials) {
-			fmt.Fprintln(os.Stderr, "Token expired, refreshing...")
-			refreshed, err := refreshToken(profile.Credentials)
-			if err != nil {
-				if re, ok := err.(*RefreshError); ok && re.Kind == refreshInvalidGrant {
-					newProfile, err := reauthenticateProfile(name)
-					if err != nil {
-						return err
-					}
-					if newProfile.Type != "oauth" {
-						return fmt.Errorf("re-authentication resulted in non-OAuth profile")
-					}
-					if err := writeCredentials(newProfile.Credentials); err != nil {
-						return err
-					}
-					if err := writeKeychainCredentials(newProfile.Credentials); err != nil {
-						return err
-					}
-					if err := writeOAuthAccount(newProfile.Account); err != nil {
-						return err
-					}
-					state := loadState()
-					state.ActiveProfile = &name
-					if err := saveState(&state); err != nil {
-						return err
-					}
-					fmt.Fprintf(os.Stderr, "Switched to '%s' (re-authenticated)\n", name)
-					return nil
-				}
-				return err
+		if parsed.has("--no-refresh") {
+			if isExpired(profile.Credentials, expiryBuffer()) {
+				fmt.Fprintf(os.Stderr, "Warning: the token for '%s' has expired or is about to\n", name)
 			}
-			profile.Credentials = refreshed
-			if err := saveProfile(name, profile); err != nil {
-				return err
-			}
-		}
-
-		if err := writeCredentials(profile.Credentials); err != nil {
-			return err
-		}
-		if err := writeKeychainCredentials(profile.Credentials); err != nil {
-			return err
-		}
-		if err := writeOAuthAccount(profile.Account); err != nil {
-			return err
-		}
-
-		state := loadState()
-		state.ActiveProfile = &name
-		if err := saveState(&state); err != nil {
-			return err
-		}
-
-		fmt.Fprintf(os.Stderr, "Switched to '%s'\n", name)
-	} else {
-		state := loadState()
-		state.ActiveProfile = &name
-		if err := saveState(&state); err != nil {
-			return err
+		} else if err := refreshIfExpired(name, profile); err != nil {
+			// A login here would print into whatever captures the token
+			return explainRevoked(name, err)
 		}
-
-		fmt.Fprintln(os.Stderr, "API key profiles can't be written to Claude's config files.")
-		fmt.Fprintln(os.Stderr, "Use one of these instead:")
-		fmt.Fprintln(os.Stderr)
-		fmt.Fprintf(os.Stderr, "  export ANTHROPIC_API_KEY=%s\n", profile.ApiKey)
-		fmt.Fprintf(os.Stderr, "  claude-switch exec %s -- claude\n", name)
+		secret = profile.Credentials.AccessToken
 	}
 
+	fmt.Fprintln(os.Stderr, "Warning: this is a live credential; keep it out of shell history, logs and shared files.")
+	fmt.Println(secret)
 	return nil
 }
 
-// ANSI colour helpers
-const (
-	ansiReset = "\033[0m"
-	ansiBold  = "\033[1m"
-	ansiGreen = "\033[32m"
-	ansiRed   = "\033[31m"
-)
+// redactedSecret replaces every secret in a redacted export.
+const redactedSecret = "<redacted>"
 
-func cmdList() error {
-	names, err := listProfiles()
-	if err != nil {
-		return err
+// redactProfile returns a copy of profile with its tokens and key replaced,
+// keeping everything else so the structure can be shared in a bug report.
+func redactProfile(profile *Profile) *Profile {
+	out := *profile
+	if c := profile.Credentials; c != nil {
+		creds := *c
+		creds.AccessToken = redactedSecret
+		creds.RefreshToken = redactedSecret
+		out.Credentials = &creds
 	}
-	if len(names) == 0 {
-		fmt.Fprintln(os.Stderr, "No profiles. Use 'claude-switch add <name>' or 'claude-switch import <name>' to create one.")
-		return nil
+	if out.ApiKey != "" {
+		out.ApiKey = redactedSecret
 	}
-
-	state := loadState()
-
-	w := tabwriter.NewWriter(os.Stdout, 0, 0, 2, ' ', 0)
-	fmt.Fprintf(w, "%s\t%s\t%s\t%s\t%s\t%s\t%s\n",
-		ansiBold+" "+ansiReset,
-		ansiBold+"NAME"+ansiReset,
-		ansiBold+"TYPE"+ansiReset,
-		ansiBold+"EMAIL"+ansiReset,
-		ansiBold+"ORG"+ansiReset,
-		ansiBold+"PLAN"+ansiReset,
-		ansiBold+"EXPIRES"+ansiReset)
-
-	for _, name := range names {
-		isActive := state.ActiveProfile != nil && *state.ActiveProfile == name
-
-		profile, err := loadProfile(name)
-		if err != nil {
-			active := " "
-			if isActive {
-				active = "*"
-			}
-			fmt.Fprintf(w, "%s\t%s\t%s%s%s\t%s\t%s\t%s\t%s\n",
-				active, name, ansiRed, "error", ansiReset, "-", "-", "-", "-")
-			continue
-		}
-
-		expiry := "-"
-		if ts := profile.ExpiresAt(); ts != nil {
-			t := time.UnixMilli(int64(*ts)).UTC()
-			expiry = t.Format("2006-01-02 15:04 UTC")
-		}
-
-		if isActive {
-			fmt.Fprintf(w, "%s*%s\t%s%s%s\t%s\t%s\t%s\t%s\t%s\n",
-				ansiGreen+ansiBold, ansiReset,
-				ansiGreen+ansiBold, name, ansiReset,
-				profile.DisplayType(),
-				profile.DisplayEmail(),
-				profile.DisplayOrg(),
-				profile.DisplaySub(),
-				expiry)
-		} else {
-			fmt.Fprintf(w, " \t%s\t%s\t%s\t%s\t%s\t%s\n",
-				name,
-				profile.DisplayType(),
-				profile.DisplayEmail(),
-				profile.DisplayOrg(),
-				profile.DisplaySub(),
-				expiry)
-		}
+	if out.PrevRefreshToken != "" {
+		out.PrevRefreshToken = redactedSecret
 	}
-
-	w.Flush()
-	return nil
+	out.Redacted = "secrets removed by claude-switch export --redact; this profile can't be used"
+	return &out
 }
 
-func cmdRemove(name string) error {
-	if err := removeProfile(name); err != nil {
+// cmdExport writes a profile as JSON, in the format of the profile files, to
+// a file or stdout. import --from-file reads it back on another machine.
+func cmdExport(args []string) error {
+	parsed, err := parseArgs(args, "--redact", "--include-secrets")
+	if err != nil {
 		return err
 	}
-	fmt.Fprintf(os.Stderr, "Removed profile '%s'\n", name)
-	return nil
-}
-
-func cmdExec() error {
-	if len(os.Args) < 3 {
-		return fmt.Errorf("exec requires a profile name")
-	}
-	name := os.Args[2]
-
-	// Find the command args (everything after --)
-	cmdArgs := os.Args[3:]
-	// Strip leading "--" if present
-	if len(cmdArgs) > 0 && cmdArgs[0] == "--" {
-		cmdArgs = cmdArgs[1:]
+	if len(parsed.pos) == 0 || len(parsed.pos) > 2 {
+		return fmt.Errorf("export requires a profile name and optionally an output file")
 	}
-	if len(cmdArgs) == 0 {
-		return fmt.Errorf("no command specified")
+	if parsed.has("--redact") && parsed.has("--include-secrets") {
+		return fmt.Errorf("--redact and --include-secrets can't be combined")
 	}
+	name := parsed.pos[0]
 
 	profile, err := loadProfile(name)
 	if err != nil {
 		return err
 	}
-
-	if profile.Type == "oauth" {
-		if isExpired(profile.Credentials) {
-			fmt.Fprintln(os.Stderr, "Token expired, refreshing...")
-			refreshed, rerr := refreshToken(profile.Credentials)
-			if rerr != nil {
-				if re, ok := rerr.(*RefreshError); ok && re.Kind == refreshInvalidGrant {
-					newProfile, err := reauthenticateProfile(name)
-					if err != nil {
-						return err
-					}
-					if newProfile.Type != "oauth" {
-						return fmt.Errorf("re-authentication resulted in non-OAuth profile")
-					}
-					return execWithEnv(cmdArgs, "CLAUDE_CODE_OAUTH_TOKEN", newProfile.Credentials.AccessToken)
-				}
-				return rerr
-			}
-			profile.Credentials = refreshed
-			if err := saveProfile(name, profile); err != nil {
-				return err
-			}
-		}
-		return execWithEnv(cmdArgs, "CLAUDE_CODE_OAUTH_TOKEN", profile.Credentials.AccessToken)
+	switch {
+	case parsed.has("--redact"):
+		profile = redactProfile(profile)
+	case !parsed.has("--include-secrets"):
+		fmt.Fprintln(os.Stderr, "Warning: this contains live credentials; use --redact for a copy that is safe to share, or --include-secrets to skip this warning.")
 	}
 
-	// API key profile
-	return execWithEnv(cmdArgs, "ANTHROPIC_API_KEY", profile.ApiKey)
-}
-
-func execWithEnv(args []string, envKey, envVal string) error {
-	binary, err := exec.LookPath(args[0])
+	out, err := json.MarshalIndent(profile, "", "  ")
 	if err != nil {
-		return fmt.Errorf("exec failed: %w", err)
+		return err
+	}
+	if len(parsed.pos) == 1 {
+		fmt.Println(string(out))
+		return nil
 	}
-	env := append(os.Environ(), envKey+"="+envVal)
-	return syscall.Exec(binary, args, env)
+	path := parsed.pos[1]
+	if err := writeSecure(path, append(out, '\n')); err != nil {
+		return err
+	}
+	logf("Exported '%s' to %s\n", name, path)
+	return nil
 }
 
 // --- Helpers ---
@@ -398,10 +2998,15 @@ func profileExists(name string) bool {
 }
 
 func importCurrentCredentials() (*Profile, error) {
-	claudePath := claudeJSONPath()
+	return importCredentials(readOAuthCredentials(), claudeJSONPath())
+}
 
-	oauthRaw := readOAuthCredentials()
+var errNoCredentials = errors.New("no credentials found")
 
+// importCredentials builds a profile from a raw claudeAiOauth entry (nil if
+// there is none), falling back to the API key in the .claude.json at
+// claudePath.
+func importCredentials(oauthRaw json.RawMessage, claudePath string) (*Profile, error) {
 	// Try API key
 	var apiKey string
 	data, err := os.ReadFile(claudePath)
@@ -422,6 +3027,9 @@ func importCurrentCredentials() (*Profile, error) {
 		if err := json.Unmarshal(oauthRaw, &creds); err != nil {
 			return nil, fmt.Errorf("failed to parse OAuth credentials: %w", err)
 		}
+		if missing := creds.MissingFields(); len(missing) > 0 {
+			return nil, fmt.Errorf("OAuth credentials are missing %s — if a login is still in progress, let it finish and try again", strings.Join(missing, ", "))
+		}
 
 		var account json.RawMessage
 		data, err := os.ReadFile(claudePath)
@@ -446,21 +3054,259 @@ func importCurrentCredentials() (*Profile, error) {
 		}, nil
 	}
 
-	return nil, fmt.Errorf("no credentials found")
+	return nil, errNoCredentials
+}
+
+func importSnippetFile(path string) (*Profile, error) {
+	f, err := os.Open(path)
+	if err != nil {
+		return nil, err
+	}
+	defer f.Close()
+	return importSnippet(io.LimitReader(f, maxProfileDownload), path)
+}
+
+// importSnippet builds a profile from credentials copied off another
+// machine. It accepts a whole .credentials.json, with oauthAccount added
+// alongside claudeAiOauth if wanted, a {"credentials", "account"} pair, the
+// bare claudeAiOauth object, or a profile file as written by export.
+func importSnippet(r io.Reader, source string) (*Profile, error) {
+	data, err := io.ReadAll(r)
+	if err != nil {
+		return nil, fmt.Errorf("reading %s: %w", source, err)
+	}
+	var doc map[string]json.RawMessage
+	if err := json.Unmarshal(data, &doc); err != nil {
+		return nil, fmt.Errorf("%s isn't a JSON object: %w", source, err)
+	}
+
+	if _, ok := doc["type"]; ok {
+		profile, err := decodeProfile(data)
+		if err != nil {
+			return nil, fmt.Errorf("%s isn't a valid profile: %w", source, err)
+		}
+		if err := checkProfile(profile); err != nil {
+			return nil, fmt.Errorf("%s: %w", source, err)
+		}
+		return profile, nil
+	}
+
+	credsRaw, account := doc["claudeAiOauth"], doc["oauthAccount"]
+	if credsRaw == nil {
+		credsRaw = doc["credentials"]
+	}
+	if account == nil {
+		account = doc["account"]
+	}
+	if credsRaw == nil {
+		if _, ok := doc["accessToken"]; !ok {
+			return nil, fmt.Errorf("%s doesn't hold Claude credentials: expected a claudeAiOauth or credentials object, or accessToken, refreshToken and expiresAt", source)
+		}
+		credsRaw = data
+	}
+
+	var creds OAuthCredentials
+	if err := json.Unmarshal(credsRaw, &creds); err != nil {
+		return nil, fmt.Errorf("%s: failed to parse OAuth credentials: %w", source, err)
+	}
+	if missing := creds.MissingFields(); len(missing) > 0 {
+		return nil, fmt.Errorf("%s: OAuth credentials are missing %s", source, strings.Join(missing, ", "))
+	}
+	if string(account) == "null" {
+		account = nil
+	}
+	if account != nil && accountField(account, "accountUuid") == "" && accountField(account, "emailAddress") == "" {
+		return nil, fmt.Errorf("%s: the account has neither accountUuid nor emailAddress", source)
+	}
+	return &Profile{Type: "oauth", Credentials: &creds, Account: account}, nil
+}
+
+// ensureFresh refreshes an expired OAuth profile and saves the new tokens. If
+// the refresh token has been revoked it falls back to re-authenticating, and
+// the returned bool reports that it did.
+func ensureFresh(name string, profile *Profile) (*Profile, bool, error) {
+	if profile.Type != "oauth" {
+		return profile, false, nil
+	}
+	if err := settleUnknownExpiry(name, profile); err != nil {
+		return nil, false, err
+	}
+	if !isExpired(profile.Credentials, expiryBuffer()) {
+		return profile, false, nil
+	}
+	if err := requireWritable("refreshing an expired token"); err != nil {
+		return nil, false, err
+	}
+
+	logf("Token expired, refreshing...\n")
+	if err := refreshProfile(name, profile); err != nil {
+		if re, ok := err.(*RefreshError); ok && re.Kind == refreshInvalidGrant {
+			newProfile, err := reauthenticateProfile(name, profile)
+			if err != nil {
+				return nil, false, &RefreshError{
+					Kind:    refreshInvalidGrant,
+					Message: fmt.Sprintf("refresh token for '%s' was revoked and re-authentication failed: %v", name, err),
+				}
+			}
+			if newProfile.Type != "oauth" {
+				return nil, false, fmt.Errorf("re-authentication resulted in non-OAuth profile")
+			}
+			return newProfile, true, nil
+		}
+		return nil, false, err
+	}
+	return profile, false, nil
+}
+
+// refreshIfExpired refreshes an OAuth profile whose token has expired,
+// without falling back to an interactive login.
+func refreshIfExpired(name string, profile *Profile) error {
+	if profile.Type != "oauth" {
+		return nil
+	}
+	if err := settleUnknownExpiry(name, profile); err != nil {
+		return err
+	}
+	if !isExpired(profile.Credentials, expiryBuffer()) {
+		return nil
+	}
+	if err := requireWritable("refreshing an expired token"); err != nil {
+		return err
+	}
+	return refreshProfile(name, profile)
+}
+
+// settleUnknownExpiry deals with profiles imported with expiresAt 0, which
+// would otherwise count as expired on every use. A JWT access token states
+// its own expiry, which is saved in its place. Otherwise the refresh that
+// follows sets a real expiry, since the server always sends one, so it
+// happens only once. Without a refresh token there is nothing to refresh
+// with, and the profile has to be imported again.
+func settleUnknownExpiry(name string, profile *Profile) error {
+	c := profile.Credentials
+	if c.ExpiresAt != 0 {
+		return nil
+	}
+	if claims, ok := decodeClaims(c.AccessToken); ok && claims.Exp > 0 {
+		c.ExpiresAt = uint64(claims.Exp) * 1000
+		if noStateWrite {
+			return nil
+		}
+		return saveProfile(name, profile)
+	}
+	if c.RefreshToken == "" {
+		return fmt.Errorf("profile '%s' has neither a token expiry nor a refresh token; add or import it again", name)
+	}
+	logf("Profile '%s' has no token expiry; refreshing once to get one\n", name)
+	return nil
+}
+
+// refreshProfile refreshes an OAuth profile's tokens and saves them. When it
+// is the active profile, Claude's live credentials are updated as well, since
+// the server may have invalidated the refresh token they hold. The profile is
+// saved as soon as the refresh succeeds, before any other work, so a batch
+// that fails partway keeps every rotated token it already received.
+//
+// Refresh tokens may be single-use, so the refresh runs under the lock, and
+// if another run refreshed the profile while this one waited for it, the
+// tokens that run saved are taken instead.
+func refreshProfile(name string, profile *Profile) error {
+	return withLock(func() error {
+		stored, err := loadProfile(name)
+		if err == nil && stored.Credentials != nil &&
+			stored.Credentials.RefreshToken != profile.Credentials.RefreshToken &&
+			!isExpired(stored.Credentials, expiryBuffer()) {
+			*profile = *stored
+			return nil
+		}
+		return refreshAndSave(name, profile)
+	})
+}
+
+func refreshAndSave(name string, profile *Profile) error {
+	refreshed, err := refreshToken(profile.Credentials)
+	var re *RefreshError
+	if errors.As(err, &re) && re.Kind == refreshInvalidGrant && profile.PrevRefreshToken != "" &&
+		profile.PrevRefreshToken != profile.Credentials.RefreshToken {
+		// The last rotation may not have taken on the server's side, leaving
+		// the previous token as the live one
+		logf("Refresh token for '%s' was rejected; retrying with the previous one\n", name)
+		prev := *profile.Credentials
+		prev.RefreshToken = profile.PrevRefreshToken
+		refreshed, err = refreshToken(&prev)
+	}
+	if err != nil {
+		return err
+	}
+
+	old := profile.Credentials
+	if refreshed.RefreshToken != old.RefreshToken {
+		profile.PrevRefreshToken = old.RefreshToken
+	}
+	profile.Credentials = refreshed
+	refreshedAt := nowMs()
+	profile.LastRefreshedAt = &refreshedAt
+	if err := saveProfile(name, profile); err != nil {
+		// The old refresh token may already be spent, so say what was lost
+		return fmt.Errorf("refreshed '%s' but couldn't save the new tokens, so it may need to be added again: %w", name, err)
+	}
+	warnIfIdentityChanged(name, profile.Account, old, refreshed)
+
+	state := loadState()
+	if !state.isActive(name) {
+		return nil
+	}
+	if err := writeCredentials(refreshed); err != nil {
+		return err
+	}
+	return writeKeychainCredentials(refreshed)
+}
+
+// warnIfIdentityChanged checks that refreshed tokens still belong to the
+// stored account. A refresh is assumed to preserve identity, but a
+// misconfigured client could break that. When both tokens are JWTs their
+// subjects are compared at no cost. Otherwise it takes an account lookup,
+// an extra request on every refresh, so that is only made when
+// CLAUDE_SWITCH_CHECK_IDENTITY is set, and skipped quietly if it fails.
+func warnIfIdentityChanged(name string, stored json.RawMessage, old, creds *OAuthCredentials) {
+	before, wasJWT := decodeClaims(old.AccessToken)
+	after, isJWT := decodeClaims(creds.AccessToken)
+	if wasJWT && isJWT && before.Sub != "" && after.Sub != "" {
+		if after.Sub != before.Sub {
+			fmt.Fprintf(os.Stderr, "Warning: the refreshed token for '%s' has subject %s, not %s like the old one\n",
+				name, after.Sub, before.Sub)
+		}
+		return
+	}
+	if os.Getenv("CLAUDE_SWITCH_CHECK_IDENTITY") == "" {
+		return
+	}
+
+	want := accountField(stored, "accountUuid")
+	if want == "" {
+		return
+	}
+	fetched, err := fetchAccount(creds)
+	if err != nil {
+		return
+	}
+	if got := accountField(fetched, "accountUuid"); got != "" && got != want {
+		fmt.Fprintf(os.Stderr, "Warning: the refreshed token for '%s' belongs to %s, not the stored account %s\n",
+			name, accountField(fetched, "emailAddress"), accountField(stored, "emailAddress"))
+	}
 }
 
-func reauthenticateProfile(name string) (*Profile, error) {
+// reauthenticateProfile logs in again for a profile whose refresh token was
+// revoked. The new login replaces only the credentials and account; what was
+// set on the old profile itself carries over.
+func reauthenticateProfile(name string, old *Profile) (*Profile, error) {
 	fmt.Fprintf(os.Stderr, "Refresh token expired for profile '%s'. Please re-authenticate...\n", name)
 
 	if err := clearAuth(); err != nil {
 		return nil, err
 	}
 
-	cmd := exec.Command("claude", "/login")
-	cmd.Stdin = os.Stdin
-	cmd.Stdout = os.Stdout
-	cmd.Stderr = os.Stderr
-	if err := cmd.Run(); err != nil {
+	if err := runLogin(); err != nil {
 		return nil, fmt.Errorf("claude exited with error — re-authentication failed: %w", err)
 	}
 
@@ -468,6 +3314,7 @@ func reauthenticateProfile(name string) (*Profile, error) {
 	if err != nil {
 		return nil, fmt.Errorf("no credentials found after login — did auth complete?")
 	}
+	keepProfileSettings(profile, old)
 
 	if err := saveProfile(name, profile); err != nil {
 		return nil, err
@@ -477,12 +3324,47 @@ func reauthenticateProfile(name string) (*Profile, error) {
 	return profile, nil
 }
 
+// keepProfileSettings copies what was set on old, rather than read from a
+// login, onto a freshly logged-in profile.
+func keepProfileSettings(profile, old *Profile) {
+	profile.Label = old.Label
+	profile.DefaultCommand = old.DefaultCommand
+	profile.UIPrefs = old.UIPrefs
+	profile.PrevRefreshToken = old.PrevRefreshToken
+	if profile.ApiKey == "" {
+		profile.ApiKey = old.ApiKey
+		profile.Workspace = old.Workspace
+	}
+}
+
+// claudeBinary returns the Claude CLI to launch. CLAUDE_SWITCH_CLAUDE_BIN
+// overrides it, e.g. to point at a stub that writes canned credentials.
+func claudeBinary() string {
+	if bin := os.Getenv("CLAUDE_SWITCH_CLAUDE_BIN"); bin != "" {
+		return bin
+	}
+	return "claude"
+}
+
+// runLogin launches Claude's interactive login flow.
+func runLogin() error {
+	cmd := exec.Command(claudeBinary(), "/login")
+	cmd.Stdin = os.Stdin
+	cmd.Stdout = os.Stdout
+	cmd.Stderr = os.Stderr
+	if err := cmd.Run(); err != nil {
+		return err
+	}
+	// This login was claude-switch's doing, not drift
+	return recordLiveCredentials()
+}
+
 func printProfileSaved(action, name string, profile *Profile) {
 	label := strings.ToUpper(action[:1]) + action[1:]
 	if profile.Type == "oauth" {
 		email := profile.DisplayEmail()
-		fmt.Fprintf(os.Stderr, "%s profile '%s' (%s)\n", label, name, email)
+		logf("%s profile '%s' (%s)\n", label, name, email)
 	} else {
-		fmt.Fprintf(os.Stderr, "%s profile '%s' (API key)\n", label, name)
+		logf("%s profile '%s' (API key)\n", label, name)
 	}
 }
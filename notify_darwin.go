@@ -0,0 +1,34 @@
+//go:build darwin
+
+package main
+
+import (
+	"os/exec"
+	"strings"
+	"unicode"
+)
+
+func sendNotification(title, body string) {
+	script := "display notification " + appleScriptString(body) + " with title " + appleScriptString(title)
+	exec.Command("osascript", "-e", script).Run()
+}
+
+// appleScriptString quotes s as an AppleScript string literal. AppleScript
+// only knows the \" and \\ escapes, so Go's %q would leave \u escapes and
+// the like in the text; control characters are dropped instead.
+func appleScriptString(s string) string {
+	var b strings.Builder
+	b.WriteByte('"')
+	for _, r := range s {
+		switch {
+		case r == '"' || r == '\\':
+			b.WriteByte('\\')
+			b.WriteRune(r)
+		case unicode.IsControl(r):
+		default:
+			b.WriteRune(r)
+		}
+	}
+	b.WriteByte('"')
+	return b.String()
+}
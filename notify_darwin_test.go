@@ -0,0 +1,19 @@
+//go:build darwin
+
+package main
+
+import "testing"
+
+func TestAppleScriptString(t *testing.T) {
+	for _, tt := range []struct{ in, want string }{
+		{"Switched to work", `"Switched to work"`},
+		{`say "hi"`, `"say \"hi\""`},
+		{`C:\path`, `"C:\\path"`},
+		{"café ☕", `"café ☕"`},
+		{"line\nbreak\x1b[31m", `"linebreak[31m"`},
+	} {
+		if got := appleScriptString(tt.in); got != tt.want {
+			t.Errorf("appleScriptString(%q) = %s, want %s", tt.in, got, tt.want)
+		}
+	}
+}
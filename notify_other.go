@@ -0,0 +1,14 @@
+//go:build !darwin
+
+package main
+
+import "os/exec"
+
+// Elsewhere notify-send (libnotify) is the common denominator; without it
+// there is simply no notification.
+func sendNotification(title, body string) {
+	if _, err := exec.LookPath("notify-send"); err != nil {
+		return
+	}
+	exec.Command("notify-send", "--app-name=claude-switch", title, body).Run()
+}
@@ -0,0 +1,334 @@
+package main
+
+import (
+	"crypto/sha256"
+	"encoding/hex"
+	"encoding/json"
+	"maps"
+	"net/http"
+	"net/http/httptest"
+	"net/url"
+	"strings"
+	"testing"
+	"time"
+)
+
+func TestDecodeClaims(t *testing.T) {
+	jwt := testJWT(map[string]any{"exp": 1700000000, "sub": "user-1", "scope": "user:inference"})
+	claims, ok := decodeClaims(jwt)
+	if !ok {
+		t.Fatalf("decodeClaims didn't decode a JWT")
+	}
+	if claims.Exp != 1700000000 || claims.Sub != "user-1" || claims.Scope != "user:inference" {
+		t.Errorf("decodeClaims = %+v, want exp 1700000000, sub user-1, scope user:inference", claims)
+	}
+
+	for _, token := range []string{
+		"sk-ant-oat01-opaque",
+		"a.b",
+		"header.!!!.sig",
+		"header.bm90IGpzb24.sig", // payload "not json"
+	} {
+		if claims, ok := decodeClaims(token); ok {
+			t.Errorf("decodeClaims(%q) = %+v, want false for a token that isn't a JWT", token, claims)
+		}
+	}
+}
+
+func TestClaimedExpiry(t *testing.T) {
+	exp := time.Now().Add(time.Hour).Unix()
+	jwt := testJWT(map[string]any{"exp": exp})
+
+	creds := &OAuthCredentials{AccessToken: jwt, ExpiresAt: uint64(exp) * 1000}
+	if claimed, differs := claimedExpiry(creds); claimed != uint64(exp)*1000 || differs {
+		t.Errorf("claimedExpiry with a matching expiresAt = %d, %v; want %d, false", claimed, differs, exp*1000)
+	}
+	creds.ExpiresAt += 2 * 60 * 1000
+	if _, differs := claimedExpiry(creds); !differs {
+		t.Error("claimedExpiry didn't flag an expiresAt two minutes off the token's exp")
+	}
+	creds.AccessToken = "sk-ant-oat01-opaque"
+	if claimed, differs := claimedExpiry(creds); claimed != 0 || differs {
+		t.Errorf("claimedExpiry for an opaque token = %d, %v; want 0, false", claimed, differs)
+	}
+}
+
+func TestCheckTokenRedirect(t *testing.T) {
+	post := func(rawURL string) *http.Request {
+		req, err := http.NewRequest(http.MethodPost, rawURL, nil)
+		if err != nil {
+			t.Fatal(err)
+		}
+		return req
+	}
+	via := []*http.Request{post("https://console.anthropic.com/v1/oauth/token")}
+
+	tests := []struct {
+		to     string
+		follow bool
+	}{
+		{"https://console.anthropic.com/v2/oauth/token", true},
+		{"http://console.anthropic.com/v1/oauth/token", false},
+		{"https://evil.example.com/v1/oauth/token", false},
+		{"https://console.anthropic.com:8443/v1/oauth/token", false},
+	}
+	for _, tt := range tests {
+		err := checkTokenRedirect(post(tt.to), via)
+		if got := err == nil; got != tt.follow {
+			t.Errorf("checkTokenRedirect to %s = %v, want follow %v", tt.to, err, tt.follow)
+		}
+	}
+
+	get, _ := http.NewRequest(http.MethodGet, "https://console.anthropic.com/v2/oauth/token", nil)
+	if checkTokenRedirect(get, via) == nil {
+		t.Error("checkTokenRedirect followed a redirect that turns the POST into a GET")
+	}
+
+	local := []*http.Request{post("http://127.0.0.1:8080/token")}
+	if err := checkTokenRedirect(post("http://127.0.0.1:8080/v2/token"), local); err != nil {
+		t.Errorf("checkTokenRedirect refused a plain http redirect on loopback: %v", err)
+	}
+}
+
+func TestEveryClientTimesOut(t *testing.T) {
+	for name, client := range map[string]*http.Client{"apiClient": apiClient, "tokenClient": tokenClient} {
+		if client.Timeout != httpTimeout || client.Timeout <= 0 {
+			t.Errorf("%s.Timeout = %v, want CLAUDE_SWITCH_HTTP_TIMEOUT's %v", name, client.Timeout, httpTimeout)
+		}
+	}
+}
+
+func TestTokenRedirectToAnotherHostIsNotFollowed(t *testing.T) {
+	e := newTestEnv(t)
+	elsewhere := httptest.NewServer(http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		t.Error("the refresh token was sent to the host the token endpoint redirected to")
+	}))
+	defer elsewhere.Close()
+	e.tokenServer(func(w http.ResponseWriter, r *http.Request) {
+		http.Redirect(w, r, elsewhere.URL+"/token", http.StatusTemporaryRedirect)
+	})
+	expired := testCreds("old-access", "old-refresh")
+	expired.ExpiresAt = 1
+	e.writeProfile("work", &Profile{Type: "oauth", Credentials: expired})
+
+	r := e.run("", "refresh", "work")
+	if r.code != exitError || !strings.Contains(r.stderr, "redirected (307)") {
+		t.Errorf("refresh through a cross-host redirect exited with %d, stderr %q; want it reported, not followed", r.code, r.stderr)
+	}
+}
+
+func TestResponseExpiry(t *testing.T) {
+	now := nowMs()
+	tests := []struct {
+		name    string
+		result  map[string]any
+		want    uint64 // 0 means an error is expected
+		fromNow bool
+	}{
+		{"expires_in", map[string]any{"expires_in": 3600.0}, 3600 * 1000, true},
+		{"expires_at in seconds", map[string]any{"expires_at": 1900000000.0}, 1900000000 * 1000, false},
+		{"expires_at in ms", map[string]any{"expires_at": 1900000000000.0}, 1900000000000, false},
+		{"expires_in 0 falls back to expires_at", map[string]any{"expires_in": 0.0, "expires_at": 1900000000.0}, 1900000000 * 1000, false},
+		{"expires_in 0", map[string]any{"expires_in": 0.0}, 0, false},
+		{"negative expires_in", map[string]any{"expires_in": -5.0}, 0, false},
+		{"missing", map[string]any{}, 0, false},
+	}
+	for _, tt := range tests {
+		got, err := responseExpiry(tt.result)
+		switch {
+		case tt.want == 0:
+			if err == nil {
+				t.Errorf("%s: responseExpiry = %d, want an error", tt.name, got)
+			}
+		case err != nil:
+			t.Errorf("%s: responseExpiry: %v", tt.name, err)
+		case tt.fromNow && (got < now+tt.want || got > nowMs()+tt.want):
+			t.Errorf("%s: responseExpiry = %d, want %d from now", tt.name, got, tt.want)
+		case !tt.fromNow && got != tt.want:
+			t.Errorf("%s: responseExpiry = %d, want %d", tt.name, got, tt.want)
+		}
+	}
+}
+
+func TestRefreshWithoutUsableExpiry(t *testing.T) {
+	for _, tt := range []struct {
+		name   string
+		expiry map[string]any
+	}{
+		{"missing expires_in", map[string]any{}},
+		{"expires_in 0", map[string]any{"expires_in": 0}},
+	} {
+		t.Run(tt.name, func(t *testing.T) {
+			e := newTestEnv(t)
+			e.tokenServer(func(w http.ResponseWriter, r *http.Request) {
+				body := map[string]any{"access_token": "new-access", "refresh_token": "new-refresh"}
+				maps.Copy(body, tt.expiry)
+				w.Header().Set("Content-Type", "application/json")
+				w.Write(mustJSON(body))
+			})
+			expired := testCreds("old-access", "old-refresh")
+			expired.ExpiresAt = 1
+			e.writeProfile("work", &Profile{Type: "oauth", Credentials: expired})
+
+			before := nowMs()
+			r := e.mustRun("refresh", "work")
+			if strings.Contains(r.stderr, "assuming the token expires") {
+				t.Errorf("the fallback expiry was announced without --verbose: %s", r.stderr)
+			}
+			got := e.readProfile("work").Credentials.ExpiresAt
+			if got < before+fallbackExpiryMs || got > nowMs()+fallbackExpiryMs {
+				t.Errorf("expiresAt = %d, want the %d minute fallback from now", got, fallbackExpiryMs/60000)
+			}
+
+			e.writeProfile("work", &Profile{Type: "oauth", Credentials: expired})
+			if r := e.mustRun("-v", "refresh", "work"); !strings.Contains(r.stderr, "assuming the token expires") {
+				t.Errorf("--verbose didn't mention the fallback expiry: %s", r.stderr)
+			}
+		})
+	}
+}
+
+func TestRefreshWithExpiresAt(t *testing.T) {
+	e := newTestEnv(t)
+	expiresAt := time.Now().Add(2 * time.Hour).Unix()
+	e.tokenServer(func(w http.ResponseWriter, r *http.Request) {
+		w.Header().Set("Content-Type", "application/json")
+		w.Write(mustJSON(map[string]any{"access_token": "new-access", "refresh_token": "new-refresh", "expires_at": expiresAt}))
+	})
+	expired := testCreds("old-access", "old-refresh")
+	expired.ExpiresAt = 1
+	e.writeProfile("work", &Profile{Type: "oauth", Credentials: expired})
+
+	e.mustRun("refresh", "work")
+	if got, want := e.readProfile("work").Credentials.ExpiresAt, uint64(expiresAt)*1000; got != want {
+		t.Errorf("expiresAt = %d, want %d from the response's expires_at", got, want)
+	}
+}
+
+// rewriteHost sends every request to target, whatever host it was for.
+type rewriteHost struct{ target *url.URL }
+
+func (rt rewriteHost) RoundTrip(req *http.Request) (*http.Response, error) {
+	req = req.Clone(req.Context())
+	req.URL.Scheme, req.URL.Host = rt.target.Scheme, rt.target.Host
+	return http.DefaultTransport.RoundTrip(req)
+}
+
+// apiServer answers apiClient's requests with handler for the rest of the
+// test, as the API's URLs are fixed.
+func apiServer(t *testing.T, handler http.HandlerFunc) {
+	srv := httptest.NewServer(handler)
+	t.Cleanup(srv.Close)
+	target, _ := url.Parse(srv.URL)
+	saved := apiClient.Transport
+	apiClient.Transport = rewriteHost{target}
+	t.Cleanup(func() { apiClient.Transport = saved })
+}
+
+func TestFetchAccount(t *testing.T) {
+	apiServer(t, func(w http.ResponseWriter, r *http.Request) {
+		if r.URL.Path != "/api/oauth/profile" || r.Header.Get("Authorization") != "Bearer work-access" {
+			http.Error(w, "unexpected request", http.StatusBadRequest)
+			return
+		}
+		w.Write([]byte(`{
+			"account": {"uuid": "11111111-1111-1111-1111-111111111111", "email": "me@acme.com", "display_name": "Me"},
+			"organization": {"uuid": "22222222-2222-2222-2222-222222222222", "name": "Acme"}
+		}`))
+	})
+
+	account, err := fetchAccount(testCreds("work-access", "work-refresh"))
+	if err != nil {
+		t.Fatal(err)
+	}
+	var got map[string]string
+	if err := json.Unmarshal(account, &got); err != nil {
+		t.Fatal(err)
+	}
+	want := map[string]string{
+		"accountUuid":      "11111111-1111-1111-1111-111111111111",
+		"emailAddress":     "me@acme.com",
+		"displayName":      "Me",
+		"organizationUuid": "22222222-2222-2222-2222-222222222222",
+		"organizationName": "Acme",
+	}
+	if !maps.Equal(got, want) {
+		t.Errorf("fetchAccount = %v, want %v", got, want)
+	}
+}
+
+func TestFetchAccountFailures(t *testing.T) {
+	for _, body := range []string{`{"account": {"uuid": "x"}}`, `not json`} {
+		apiServer(t, func(w http.ResponseWriter, r *http.Request) {
+			w.Write([]byte(body))
+		})
+		if account, err := fetchAccount(testCreds("work-access", "work-refresh")); err == nil {
+			t.Errorf("fetchAccount with a response of %s = %s, want an error", body, account)
+		}
+	}
+	apiServer(t, func(w http.ResponseWriter, r *http.Request) {
+		http.Error(w, "unauthorized", http.StatusUnauthorized)
+	})
+	if _, err := fetchAccount(testCreds("work-access", "work-refresh")); err == nil || !strings.Contains(err.Error(), "401") {
+		t.Errorf("fetchAccount after a 401 = %v, want an error with the status", err)
+	}
+}
+
+// tlsAPIServer is apiServer over https, for downloads that insist on it.
+func tlsAPIServer(t *testing.T, handler http.HandlerFunc) *httptest.Server {
+	srv := httptest.NewTLSServer(handler)
+	t.Cleanup(srv.Close)
+	saved := apiClient.Transport
+	apiClient.Transport = srv.Client().Transport
+	t.Cleanup(func() { apiClient.Transport = saved })
+	return srv
+}
+
+func TestImportURL(t *testing.T) {
+	e := newTestEnv(t)
+	profile := mustJSON(&Profile{Type: "oauth", Credentials: testCreds("work-access", "work-refresh")})
+	plain := httptest.NewServer(http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		w.Write(profile)
+	}))
+	t.Cleanup(plain.Close)
+	srv := tlsAPIServer(t, func(w http.ResponseWriter, r *http.Request) {
+		switch r.URL.Path {
+		case "/work.json":
+			w.Write(profile)
+		case "/huge.json":
+			w.Write(make([]byte, maxProfileDownload+1))
+		case "/downgrade.json":
+			http.Redirect(w, r, plain.URL+"/work.json", http.StatusFound)
+		default:
+			http.NotFound(w, r)
+		}
+	})
+
+	sum := sha256.Sum256(profile)
+	if err := cmdImportURL([]string{"work", srv.URL + "/work.json", "--sha256", hex.EncodeToString(sum[:])}); err != nil {
+		t.Fatal(err)
+	}
+	if got := e.readProfile("work").Credentials.AccessToken; got != "work-access" {
+		t.Errorf("imported profile's access token = %q, want work-access", got)
+	}
+
+	tests := []struct {
+		args []string
+		want string
+	}{
+		{[]string{"plain", plain.URL + "/work.json"}, "only accepts https://"},
+		{[]string{"missing", srv.URL + "/missing.json"}, "download failed (404)"},
+		{[]string{"huge", srv.URL + "/huge.json"}, "too big to be a profile"},
+		{[]string{"downgrade", srv.URL + "/downgrade.json"}, "redirected to a non-HTTPS URL"},
+		{[]string{"tampered", srv.URL + "/work.json", "--sha256", strings.Repeat("0", 64)}, "checksum mismatch"},
+	}
+	for _, tt := range tests {
+		err := cmdImportURL(tt.args)
+		if err == nil || !strings.Contains(err.Error(), tt.want) {
+			t.Errorf("import-url %v = %v, want an error containing %q", tt.args, err, tt.want)
+		}
+		if e.hasProfile(tt.args[0]) {
+			t.Errorf("import-url %v saved a profile", tt.args)
+		}
+	}
+}
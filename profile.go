@@ -1,12 +1,23 @@
 package main
 
 import (
+	"bytes"
+	"crypto/sha256"
+	"encoding/hex"
 	"encoding/json"
+	"errors"
 	"fmt"
+	"io/fs"
 	"os"
 	"path/filepath"
+	"runtime"
+	"slices"
 	"sort"
 	"strings"
+	"sync"
+	"syscall"
+	"time"
+	"unicode"
 )
 
 // --- Claude Code's own credential/config structures ---
@@ -20,14 +31,59 @@ type OAuthCredentials struct {
 	RateLimitTier    *string  `json:"rateLimitTier,omitempty"`
 }
 
+func (c *OAuthCredentials) HasScope(scope string) bool {
+	for _, s := range c.Scopes {
+		if s == scope {
+			return true
+		}
+	}
+	return false
+}
+
+// LacksInference reports whether the token is known to be missing the
+// user:inference scope. Such a token looks healthy but can't run Claude.
+func (c *OAuthCredentials) LacksInference() bool {
+	return len(c.Scopes) > 0 && !c.HasScope("user:inference")
+}
+
+// MissingFields lists the fields a usable login needs but c lacks. Claude
+// writes its credentials file in stages during /login, so a partial file
+// usually means a login is still in progress. An expiresAt of 0 isn't
+// missing: settleUnknownExpiry finds the real expiry on first use.
+func (c *OAuthCredentials) MissingFields() []string {
+	var missing []string
+	if c.AccessToken == "" {
+		missing = append(missing, "accessToken")
+	}
+	if c.RefreshToken == "" {
+		missing = append(missing, "refreshToken")
+	}
+	return missing
+}
+
 // --- Profile (tagged union via "type" field) ---
 
 type Profile struct {
-	Type        string            `json:"type"`
-	Credentials *OAuthCredentials `json:"credentials,omitempty"`
-	Account     json.RawMessage   `json:"account,omitempty"`
-	ApiKey      string            `json:"api_key,omitempty"`
-	Label       *string           `json:"label,omitempty"`
+	Type            string            `json:"type"`
+	Credentials     *OAuthCredentials `json:"credentials,omitempty"`
+	Account         json.RawMessage   `json:"account,omitempty"`
+	ApiKey          string            `json:"api_key,omitempty"`
+	Workspace       string            `json:"workspace,omitempty"`
+	Label           *string           `json:"label,omitempty"`
+	LastRefreshedAt *uint64           `json:"last_refreshed_at,omitempty"`
+	DefaultCommand  []string          `json:"default_command,omitempty"`
+
+	// PrevRefreshToken is the refresh token replaced by the last rotation,
+	// kept in case the server never actually rotated it
+	PrevRefreshToken string `json:"prev_refresh_token,omitempty"`
+
+	// UIPrefs holds Claude's display settings captured with --with-ui-prefs,
+	// re-applied on switch so each account looks different.
+	UIPrefs map[string]json.RawMessage `json:"ui_prefs,omitempty"`
+
+	// Redacted is set on copies made by export --redact, whose secrets are
+	// placeholders, so they are never mistaken for working profiles
+	Redacted string `json:"redacted,omitempty"`
 }
 
 func accountField(account json.RawMessage, key string) string {
@@ -63,6 +119,16 @@ func (p *Profile) DisplayOrg() string {
 	return "-"
 }
 
+// DisplayOrgRole shows the account's role in its organization, e.g. admin.
+func (p *Profile) DisplayOrgRole() string {
+	if p.Type == "oauth" {
+		if role := accountField(p.Account, "organizationRole"); role != "" {
+			return role
+		}
+	}
+	return "-"
+}
+
 func (p *Profile) DisplaySub() string {
 	if p.Type == "oauth" && p.Credentials != nil && p.Credentials.SubscriptionType != nil {
 		return *p.Credentials.SubscriptionType
@@ -70,6 +136,19 @@ func (p *Profile) DisplaySub() string {
 	return "-"
 }
 
+// DisplayTokenAge shows how long ago claude-switch last refreshed the token,
+// which can be stale even while it is still valid.
+func (p *Profile) DisplayTokenAge() string {
+	if p.Type != "oauth" || p.LastRefreshedAt == nil {
+		return "-"
+	}
+	now := nowMs()
+	if *p.LastRefreshedAt >= now {
+		return formatDuration(0)
+	}
+	return formatDuration(now - *p.LastRefreshedAt)
+}
+
 func (p *Profile) ExpiresAt() *uint64 {
 	if p.Type == "oauth" && p.Credentials != nil {
 		return &p.Credentials.ExpiresAt
@@ -80,12 +159,127 @@ func (p *Profile) ExpiresAt() *uint64 {
 // --- State tracking ---
 
 type State struct {
-	ActiveProfile *string `json:"active_profile,omitempty"`
+	ActiveProfile   *string           `json:"active_profile,omitempty"`
+	PreviousProfile *string           `json:"previous_profile,omitempty"`
+	DefaultProfile  *string           `json:"default_profile,omitempty"`
+	TempSwitch      *TempSwitch       `json:"temp_switch,omitempty"`
+	LastUsed        map[string]uint64 `json:"last_used,omitempty"`
+	Disabled        map[string]bool   `json:"disabled,omitempty"`
+	Nicknames       map[string]string `json:"nicknames,omitempty"`
+	Locked          bool              `json:"locked,omitempty"`
+
+	// CredentialsHash is the SHA-256 of Claude's credentials file as
+	// claude-switch last wrote it.
+	CredentialsHash string `json:"credentials_hash,omitempty"`
+
+	// Storage is "keyring" when new profiles go to the OS keyring, whose
+	// entries KeyringProfiles indexes since keyrings can't be listed.
+	Storage         string   `json:"storage,omitempty"`
+	KeyringProfiles []string `json:"keyring_profiles,omitempty"`
+}
+
+func (s *State) isActive(name string) bool {
+	return s.ActiveProfile != nil && *s.ActiveProfile == name
+}
+
+// displayName is the nickname set with `nick`, falling back to the name.
+func (s *State) displayName(name string) string {
+	if nick := s.Nicknames[name]; nick != "" {
+		return nick
+	}
+	return name
+}
+
+// isDisabled reports whether a profile is hidden from list and skipped by
+// batch commands. It can still be used by name.
+func (s *State) isDisabled(name string) bool {
+	return s.Disabled[name]
+}
+
+// forget drops every reference to a profile, reporting whether there were any.
+func (s *State) forget(name string) bool {
+	changed := false
+	if s.ActiveProfile != nil && *s.ActiveProfile == name {
+		s.ActiveProfile = nil
+		changed = true
+	}
+	if s.PreviousProfile != nil && *s.PreviousProfile == name {
+		s.PreviousProfile = nil
+		changed = true
+	}
+	if s.DefaultProfile != nil && *s.DefaultProfile == name {
+		s.DefaultProfile = nil
+		changed = true
+	}
+	if _, ok := s.LastUsed[name]; ok {
+		delete(s.LastUsed, name)
+		changed = true
+	}
+	if _, ok := s.Disabled[name]; ok {
+		delete(s.Disabled, name)
+		changed = true
+	}
+	if _, ok := s.Nicknames[name]; ok {
+		delete(s.Nicknames, name)
+		changed = true
+	}
+	return changed
+}
+
+// rename moves every reference to a profile over to its new name, reporting
+// whether there were any.
+func (s *State) rename(oldName, newName string) bool {
+	changed := false
+	for _, p := range []*string{s.ActiveProfile, s.PreviousProfile, s.DefaultProfile} {
+		if p != nil && *p == oldName {
+			*p = newName
+			changed = true
+		}
+	}
+	if s.TempSwitch != nil && s.TempSwitch.Previous != nil && *s.TempSwitch.Previous == oldName {
+		*s.TempSwitch.Previous = newName
+		changed = true
+	}
+	if ts, ok := s.LastUsed[oldName]; ok {
+		delete(s.LastUsed, oldName)
+		s.LastUsed[newName] = ts
+		changed = true
+	}
+	if s.Disabled[oldName] {
+		delete(s.Disabled, oldName)
+		s.Disabled[newName] = true
+		changed = true
+	}
+	if nick, ok := s.Nicknames[oldName]; ok {
+		delete(s.Nicknames, oldName)
+		s.Nicknames[newName] = nick
+		changed = true
+	}
+	return changed
+}
+
+// TempSwitch records a `use --for` switch that is undone once RevertAt (Unix
+// ms) has passed.
+type TempSwitch struct {
+	Previous *string `json:"previous,omitempty"`
+	RevertAt uint64  `json:"revert_at"`
 }
 
 // --- Directory/path helpers ---
 
+// configDirFlag is set by the --config-dir global flag.
+var configDirFlag string
+
+// configDir is where claude-switch keeps its data: --config-dir, then
+// CLAUDE_SWITCH_HOME, then the XDG location. The first two allow moving it
+// off a read-only mount.
 func configDir() string {
+	if configDirFlag != "" {
+		return configDirFlag
+	}
+	if home := os.Getenv("CLAUDE_SWITCH_HOME"); home != "" {
+		return home
+	}
 	if xdg := os.Getenv("XDG_CONFIG_HOME"); xdg != "" {
 		return filepath.Join(xdg, "claude-switch")
 	}
@@ -130,41 +324,288 @@ func claudeJSONPath() string {
 // --- Credential reading (flat-file with macOS keychain fallback) ---
 
 func readOAuthCredentials() json.RawMessage {
-	data, err := os.ReadFile(credentialsPath())
+	if raw := readOAuthCredentialsFile(credentialsPath()); raw != nil {
+		return raw
+	}
+
+	// Fallback: macOS keychain
+	return readKeychainCredentials()
+}
+
+func readOAuthCredentialsFile(path string) json.RawMessage {
+	return readJSONKey(path, "claudeAiOauth")
+}
+
+// readJSONKey returns one top-level value of the JSON object at path, or nil.
+func readJSONKey(path, key string) json.RawMessage {
+	data, err := os.ReadFile(path)
 	if err == nil {
 		var doc map[string]json.RawMessage
 		if json.Unmarshal(data, &doc) == nil {
-			if raw, ok := doc["claudeAiOauth"]; ok {
+			if raw, ok := doc[key]; ok {
 				return raw
 			}
 		}
 	}
+	return nil
+}
 
-	// Fallback: macOS keychain
-	return readKeychainCredentials()
+// activeMatchesLive compares an OAuth profile with the credentials Claude is
+// actually using, as they drift when Claude is logged in directly or its
+// files are edited by hand. known is false when there is nothing to compare.
+func activeMatchesLive(profile *Profile) (matches, known bool) {
+	if profile.Type != "oauth" || profile.Credentials == nil {
+		return false, false
+	}
+	raw := readOAuthCredentials()
+	if raw == nil {
+		return false, true
+	}
+	var live OAuthCredentials
+	if json.Unmarshal(raw, &live) != nil {
+		return false, false
+	}
+	// A token refreshed by Claude itself changes the access token but keeps
+	// the refresh token unless it was rotated
+	if live.RefreshToken == profile.Credentials.RefreshToken || live.AccessToken == profile.Credentials.AccessToken {
+		return true, true
+	}
+	// New tokens for the same account, as after logging in to it again
+	want := accountField(profile.Account, "accountUuid")
+	got := accountField(readJSONKey(claudeJSONPath(), "oauthAccount"), "accountUuid")
+	return want != "" && got == want, true
+}
+
+// authSnapshot holds Claude's auth keys as they were, so a login that
+// replaced them can be undone exactly. A nil field was absent.
+type authSnapshot struct {
+	oauth   json.RawMessage
+	account json.RawMessage
+	apiKey  json.RawMessage
+}
+
+func snapshotAuth() authSnapshot {
+	return authSnapshot{
+		oauth:   readOAuthCredentials(),
+		account: readJSONKey(claudeJSONPath(), "oauthAccount"),
+		apiKey:  readJSONKey(claudeJSONPath(), "primaryApiKey"),
+	}
+}
+
+// restore writes the snapshot back, dropping auth keys added since.
+func (a authSnapshot) restore() error {
+	if err := clearAuth(); err != nil {
+		return err
+	}
+	if a.oauth != nil {
+		var creds OAuthCredentials
+		if err := json.Unmarshal(a.oauth, &creds); err != nil {
+			return err
+		}
+		if err := writeCredentials(&creds); err != nil {
+			return err
+		}
+		if err := writeKeychainCredentials(&creds); err != nil {
+			return err
+		}
+	}
+	if a.account == nil && a.apiKey == nil {
+		return nil
+	}
+	return updateJSONFile(claudeJSONPath(), func(doc map[string]json.RawMessage) {
+		if a.account != nil {
+			doc["oauthAccount"] = a.account
+		}
+		if a.apiKey != nil {
+			doc["primaryApiKey"] = a.apiKey
+		}
+	})
 }
 
 // --- File I/O with 0600 permissions ---
 
+// noStateWrite is set by the --no-state-write global flag. It makes every
+// write fail so an audit run is guaranteed to leave the disk untouched.
+var noStateWrite bool
+
+func requireWritable(action string) error {
+	if noStateWrite {
+		return fmt.Errorf("%s would modify files, but --no-state-write is set", action)
+	}
+	return nil
+}
+
+// writeSecure writes data to path with mode 0600. It writes a temp file and
+// renames it over path, so a run killed midway leaves the old file intact
+// rather than a truncated one.
 func writeSecure(path string, data []byte) error {
-	dir := filepath.Dir(path)
-	if err := os.MkdirAll(dir, 0o755); err != nil {
+	if err := requireWritable("writing " + path); err != nil {
+		return err
+	}
+	if err := ensureDir(filepath.Dir(path)); err != nil {
 		return err
 	}
-	return os.WriteFile(path, data, 0o600)
+	// Replace what a symlink points at, not the link itself
+	if target, err := filepath.EvalSymlinks(path); err == nil {
+		path = target
+	}
+
+	tmp, err := writeTemp(path, data)
+	if err != nil {
+		if tmp != "" {
+			os.Remove(tmp)
+		}
+		return explainWriteError(path, err)
+	}
+	if err := os.Rename(tmp, path); err != nil {
+		os.Remove(tmp)
+		return explainWriteError(path, err)
+	}
+	checkSecureMode(path)
+	return nil
+}
+
+// writeTemp writes and syncs a fresh scratch file for an atomic write of
+// path, so the rename that follows never exposes a file whose contents
+// haven't reached the disk. The file sits next to path so the rename stays on
+// one filesystem, is hidden, and gets a name of its own for every write, so
+// neither two runs nor two goroutines writing the same file collide. It
+// returns the file's name once it exists, even on failure.
+func writeTemp(path string, data []byte) (string, error) {
+	f, err := os.CreateTemp(filepath.Dir(path), "."+filepath.Base(path)+".*.tmp")
+	if err != nil {
+		return "", err
+	}
+	if _, err := f.Write(data); err != nil {
+		f.Close()
+		return f.Name(), err
+	}
+	if err := f.Sync(); err != nil {
+		f.Close()
+		return f.Name(), err
+	}
+	return f.Name(), f.Close()
+}
+
+// isTempFile reports whether a file name has the shape writeTemp gives, as
+// in ".work.json.4242.tmp".
+func isTempFile(name string) bool {
+	rest, ok := strings.CutPrefix(name, ".")
+	if !ok {
+		return false
+	}
+	rest, ok = strings.CutSuffix(rest, ".tmp")
+	if !ok {
+		return false
+	}
+	i := strings.LastIndex(rest, ".")
+	if i < 0 {
+		return false
+	}
+	base, pid := rest[:i], rest[i+1:]
+	if pid == "" || strings.Trim(pid, "0123456789") != "" {
+		return false
+	}
+	return strings.HasSuffix(base, ".json")
+}
+
+// staleTempAge is how old a temp file must be before it counts as orphaned,
+// so cleaning up never races another run that is midway through a write.
+const staleTempAge = time.Minute
+
+// orphanedTempFiles finds temp files left behind by runs that died between
+// writing and renaming, in every directory claude-switch writes into.
+func orphanedTempFiles() []string {
+	dirs := []string{configDir(), profilesDir(), trashDir(), filepath.Dir(credentialsPath()), filepath.Dir(claudeJSONPath())}
+	slices.Sort(dirs)
+	dirs = slices.Compact(dirs)
+
+	var orphans []string
+	for _, dir := range dirs {
+		entries, err := os.ReadDir(dir)
+		if err != nil {
+			continue
+		}
+		for _, e := range entries {
+			if e.IsDir() || !isTempFile(e.Name()) {
+				continue
+			}
+			info, err := e.Info()
+			if err != nil || time.Since(info.ModTime()) < staleTempAge {
+				continue
+			}
+			orphans = append(orphans, filepath.Join(dir, e.Name()))
+		}
+	}
+	return orphans
+}
+
+// checkSecureMode makes sure a freshly written file really is 0600. Some
+// filesystems (FAT, certain network mounts) ignore modes entirely, so it
+// tightens the mode and warns if that didn't take.
+func checkSecureMode(path string) {
+	if runtime.GOOS == "windows" {
+		return
+	}
+	os.Chmod(path, 0o600)
+	info, err := os.Stat(path)
+	if err != nil || info.Mode().Perm()&0o077 == 0 {
+		return
+	}
+	fmt.Fprintf(os.Stderr, "Warning: %s has mode %04o because its filesystem doesn't enforce permissions, so other users may be able to read its secrets; consider moving %s to a filesystem that does\n", path, info.Mode().Perm(), filepath.Dir(path))
+}
+
+// ensureDir creates dir if it is missing. Something other than a directory
+// in its place gets a clear error instead of MkdirAll's terse one.
+func ensureDir(dir string) error {
+	if info, err := os.Stat(dir); err == nil && !info.IsDir() {
+		return notDirError(dir)
+	}
+	if err := os.MkdirAll(dir, 0o755); err != nil {
+		return explainWriteError(dir, err)
+	}
+	return nil
+}
+
+func notDirError(path string) error {
+	return fmt.Errorf("%s should be a directory but is a file; move or remove it and try again", path)
+}
+
+// explainWriteError replaces a bare permission error with one naming the file
+// and, where it helps, who owns it.
+func explainWriteError(path string, err error) error {
+	if errors.Is(err, fs.ErrPermission) {
+		return fmt.Errorf("cannot write %s: permission denied%s", path, ownerHint(path))
+	}
+	if errors.Is(err, syscall.EROFS) {
+		dir := configDir()
+		if path == dir || strings.HasPrefix(path, dir+string(filepath.Separator)) {
+			return fmt.Errorf("cannot write %s: read-only filesystem; set CLAUDE_SWITCH_HOME or pass --config-dir to keep claude-switch's data somewhere writable", path)
+		}
+		return fmt.Errorf("cannot write %s: read-only filesystem; Claude's config must be writable to switch profiles, though read-only commands such as list still work", path)
+	}
+	return err
 }
 
 // --- Profile name validation ---
 
+// errInvalidName is wrapped by every invalid profile name error.
+var errInvalidName = errors.New("invalid profile name")
+
 func validateProfileName(name string) error {
 	if name == "" || strings.Contains(name, "/") || strings.Contains(name, "\\") ||
 		name == "." || name == ".." || strings.Contains(name, string(os.PathSeparator)) {
-		return fmt.Errorf("invalid profile name: '%s'", name)
+		return fmt.Errorf("%w: '%s'", errInvalidName, name)
+	}
+	// NUL and other control characters make odd filenames and can carry
+	// terminal escape sequences into list output
+	if strings.ContainsFunc(name, unicode.IsControl) {
+		return fmt.Errorf("%w %q: control characters are not allowed", errInvalidName, name)
 	}
 	// Ensure it maps to exactly one normal path component
 	cleaned := filepath.Clean(name)
 	if cleaned != name || filepath.Base(name) != name {
-		return fmt.Errorf("invalid profile name: '%s'", name)
+		return fmt.Errorf("%w: '%s'", errInvalidName, name)
 	}
 	return nil
 }
@@ -175,6 +616,13 @@ func profilePath(name string) string {
 
 // --- Profile CRUD ---
 
+// errNotFound is wrapped by every "profile not found" error so the CLI can
+// report it with its own exit code.
+var errNotFound = errors.New("not found")
+
+// errExists is wrapped by the errors for a profile name already taken.
+var errExists = errors.New("already exists")
+
 func saveProfile(name string, profile *Profile) error {
 	if err := validateProfileName(name); err != nil {
 		return err
@@ -183,41 +631,130 @@ func saveProfile(name string, profile *Profile) error {
 	if err != nil {
 		return err
 	}
-	return writeSecure(profilePath(name), data)
+	if state := loadState(); state.Locked {
+		if data, err = encryptProfile(data); err != nil {
+			return err
+		}
+	}
+	return withLock(func() error {
+		return writeProfileData(name, data)
+	})
 }
 
 func loadProfile(name string) (*Profile, error) {
 	if err := validateProfileName(name); err != nil {
 		return nil, err
 	}
-	data, err := os.ReadFile(profilePath(name))
+	// A missing profiles directory is just a fresh install with no profiles
+	data, err := readProfileData(name)
+	if errors.Is(err, fs.ErrNotExist) {
+		return nil, fmt.Errorf("profile '%s' %w", name, errNotFound)
+	}
 	if err != nil {
-		return nil, fmt.Errorf("profile '%s' not found", name)
+		return nil, fmt.Errorf("profile '%s': %w", name, err)
 	}
+	if isEncrypted(data) {
+		if data, err = decryptProfile(data); err != nil {
+			return nil, fmt.Errorf("profile '%s': %w", name, err)
+		}
+	}
+	profile, err := decodeProfile(data)
+	if err != nil {
+		return nil, fmt.Errorf("profile '%s': %w", name, err)
+	}
+	return profile, nil
+}
+
+// strictJSON is set by the --strict-json global flag. Profile files with
+// fields claude-switch doesn't recognise are then rejected instead of having
+// those fields silently ignored, which catches typos in hand-edited files.
+var strictJSON bool
+
+func decodeProfile(data []byte) (*Profile, error) {
 	var profile Profile
-	if err := json.Unmarshal(data, &profile); err != nil {
-		return nil, err
+	if !strictJSON {
+		if err := json.Unmarshal(data, &profile); err != nil {
+			return nil, err
+		}
+		return &profile, nil
+	}
+	dec := json.NewDecoder(bytes.NewReader(data))
+	dec.DisallowUnknownFields()
+	if err := dec.Decode(&profile); err != nil {
+		return nil, fmt.Errorf("strict parsing failed: %w", err)
 	}
 	return &profile, nil
 }
 
+// checkProfile reports what makes a decoded profile unusable, for profiles
+// that come from outside claude-switch.
+func checkProfile(p *Profile) error {
+	if p.Redacted != "" {
+		return fmt.Errorf("it is a redacted export, whose secrets were removed")
+	}
+	switch p.Type {
+	case "oauth":
+		if p.Credentials == nil {
+			return fmt.Errorf("OAuth profile has no credentials")
+		}
+		if missing := p.Credentials.MissingFields(); len(missing) > 0 {
+			return fmt.Errorf("OAuth credentials are missing %s", strings.Join(missing, ", "))
+		}
+	case "api_key":
+		if p.ApiKey == "" {
+			return fmt.Errorf("API key profile has no api_key")
+		}
+	default:
+		return fmt.Errorf("unknown profile type '%s'", p.Type)
+	}
+	return nil
+}
+
+// findDuplicate returns the name of a saved profile for the same account as
+// profile: the same account UUID for OAuth, or the same key and workspace
+// for an API key.
+func findDuplicate(profile *Profile) (string, bool) {
+	uuid := accountField(profile.Account, "accountUuid")
+	if profile.Type == "oauth" && uuid == "" {
+		return "", false
+	}
+	names, _ := listProfiles()
+	for _, name := range names {
+		other, err := loadProfile(name)
+		if err != nil || other.Type != profile.Type {
+			continue
+		}
+		if profile.Type == "oauth" && accountField(other.Account, "accountUuid") == uuid {
+			return name, true
+		}
+		if profile.Type != "oauth" && other.ApiKey == profile.ApiKey && other.Workspace == profile.Workspace {
+			return name, true
+		}
+	}
+	return "", false
+}
+
 func listProfiles() ([]string, error) {
+	// Profiles in the keyring are listed alongside those on disk
+	state := loadState()
+	names := slices.Clone(state.KeyringProfiles)
+
 	dir := profilesDir()
 	entries, err := os.ReadDir(dir)
-	if err != nil {
-		if os.IsNotExist(err) {
-			return nil, nil
+	if err != nil && !os.IsNotExist(err) {
+		if info, statErr := os.Stat(dir); statErr == nil && !info.IsDir() {
+			return nil, notDirError(dir)
 		}
 		return nil, err
 	}
-	var names []string
 	for _, e := range entries {
-		if e.IsDir() {
+		// Leftovers from an interrupted write are never profiles
+		if e.IsDir() || isTempFile(e.Name()) {
 			continue
 		}
-		name := e.Name()
-		if filepath.Ext(name) == ".json" {
-			names = append(names, strings.TrimSuffix(name, ".json"))
+		name, ok := strings.CutSuffix(e.Name(), ".json")
+		if ok && !slices.Contains(names, name) {
+			names = append(names, name)
 		}
 	}
 	sort.Strings(names)
@@ -228,21 +765,53 @@ func removeProfile(name string) error {
 	if err := validateProfileName(name); err != nil {
 		return err
 	}
-	path := profilePath(name)
-	if _, err := os.Stat(path); os.IsNotExist(err) {
-		return fmt.Errorf("profile '%s' not found", name)
+	if !profileStored(name) {
+		return fmt.Errorf("profile '%s' %w", name, errNotFound)
+	}
+	if err := requireWritable("remove"); err != nil {
+		return err
 	}
-	if err := os.Remove(path); err != nil {
+	if err := moveToTrash(name); err != nil {
 		return err
 	}
 
-	// Clear active state if this was the active profile
-	state := loadState()
-	if state.ActiveProfile != nil && *state.ActiveProfile == name {
-		state.ActiveProfile = nil
-		return saveState(&state)
+	// Clear any state that refers to the removed profile
+	return updateState(func(state *State) bool {
+		return state.forget(name)
+	})
+}
+
+// renameProfile moves a profile to a new name, carrying its state along.
+func renameProfile(oldName, newName string) error {
+	if err := validateProfileName(oldName); err != nil {
+		return err
 	}
-	return nil
+	if err := validateProfileName(newName); err != nil {
+		return err
+	}
+	if _, err := loadProfile(oldName); err != nil {
+		return err
+	}
+	if profileStored(newName) {
+		return fmt.Errorf("profile '%s' %w", newName, errExists)
+	}
+
+	// Copy the stored bytes as is, in the same backend, and before removing
+	// the old copy so a failure midway never loses the profile
+	data, err := readProfileData(oldName)
+	if err != nil {
+		return err
+	}
+	if err := storeProfileData(newName, data, inKeyring(oldName)); err != nil {
+		return err
+	}
+	if err := deleteProfileData(oldName); err != nil {
+		return err
+	}
+
+	return updateState(func(state *State) bool {
+		return state.rename(oldName, newName)
+	})
 }
 
 // --- State CRUD ---
@@ -259,105 +828,219 @@ func loadState() State {
 	return state
 }
 
+// stateMu keeps this run's state writes apart, as batch commands save from
+// several goroutines at once; the lock file only keeps separate runs apart.
+var stateMu sync.Mutex
+
 func saveState(state *State) error {
+	stateMu.Lock()
+	defer stateMu.Unlock()
+	return writeState(state)
+}
+
+// updateState loads the state, lets fn change it and saves it if fn reports
+// a change, all under both locks, so concurrent updates can't overwrite each
+// other's changes.
+func updateState(fn func(state *State) bool) error {
+	stateMu.Lock()
+	defer stateMu.Unlock()
+	return withLock(func() error {
+		state := loadState()
+		if !fn(&state) {
+			return nil
+		}
+		return writeState(&state)
+	})
+}
+
+func writeState(state *State) error {
 	data, err := json.MarshalIndent(state, "", "  ")
 	if err != nil {
 		return err
 	}
-	return writeSecure(statePath(), data)
+	return withLock(func() error {
+		return writeSecure(statePath(), data)
+	})
 }
 
 // --- Surgical config editing ---
 
-func writeCredentials(creds *OAuthCredentials) error {
-	path := credentialsPath()
+// updateJSONFile rewrites the JSON object at path through fn, keeping every
+// key fn doesn't touch. A missing file starts out empty; one that doesn't look
+// like Claude's is left alone unless --force is set.
+func updateJSONFile(path string, fn func(doc map[string]json.RawMessage)) error {
 	var doc map[string]json.RawMessage
 
 	data, err := os.ReadFile(path)
-	if err == nil {
-		if json.Unmarshal(data, &doc) != nil {
-			doc = make(map[string]json.RawMessage)
+	if err == nil && len(bytes.TrimSpace(data)) > 0 {
+		if json.Unmarshal(data, &doc) != nil || !looksLikeClaudeConfig(doc) {
+			if !forceWrite {
+				return fmt.Errorf("%s doesn't look like a Claude Code config file, so it was left alone (check CLAUDE_CONFIG_DIR, or pass --force to write it anyway)", path)
+			}
 		}
-	} else {
+	}
+	if doc == nil {
 		doc = make(map[string]json.RawMessage)
 	}
 
-	credsJSON, err := json.Marshal(creds)
+	fn(doc)
+
+	out, err := json.MarshalIndent(doc, "", "  ")
 	if err != nil {
 		return err
 	}
-	doc["claudeAiOauth"] = credsJSON
+	return writeSecure(path, out)
+}
 
-	out, err := json.MarshalIndent(doc, "", "  ")
+// forceWrite is set by the --force global flag. It lets claude-switch edit
+// config files that don't look like Claude Code's, and save an account that
+// is already saved under another name.
+var forceWrite bool
+
+// claudeConfigKeys are top-level keys Claude Code writes to its config files.
+// A non-empty file with none of them probably belongs to something else.
+var claudeConfigKeys = []string{
+	"claudeAiOauth", "mcpOAuth", "oauthAccount", "primaryApiKey", "userID",
+	"numStartups", "installMethod", "firstStartTime", "projects",
+	"hasCompletedOnboarding", "autoUpdates", "theme",
+}
+
+func looksLikeClaudeConfig(doc map[string]json.RawMessage) bool {
+	if len(doc) == 0 {
+		return true
+	}
+	for _, key := range claudeConfigKeys {
+		if _, ok := doc[key]; ok {
+			return true
+		}
+	}
+	return false
+}
+
+func writeCredentials(creds *OAuthCredentials) error {
+	credsJSON, err := json.Marshal(creds)
 	if err != nil {
 		return err
 	}
-	return writeSecure(path, out)
+	if err := updateJSONFile(credentialsPath(), func(doc map[string]json.RawMessage) {
+		doc["claudeAiOauth"] = credsJSON
+	}); err != nil {
+		return err
+	}
+	return recordLiveCredentials()
+}
+
+// liveCredentialsHash fingerprints Claude's credentials file, or returns ""
+// when there is none.
+func liveCredentialsHash() string {
+	data, err := os.ReadFile(credentialsPath())
+	if err != nil {
+		return ""
+	}
+	sum := sha256.Sum256(data)
+	return hex.EncodeToString(sum[:])
+}
+
+// recordLiveCredentials remembers Claude's credentials file as claude-switch
+// left it, so changes made elsewhere can be spotted later.
+func recordLiveCredentials() error {
+	hash := liveCredentialsHash()
+	return updateState(func(state *State) bool {
+		state.CredentialsHash = hash
+		return true
+	})
 }
 
 func writeOAuthAccount(account json.RawMessage) error {
 	if account == nil {
 		return nil
 	}
-	path := claudeJSONPath()
-	var doc map[string]json.RawMessage
+	return updateJSONFile(claudeJSONPath(), func(doc map[string]json.RawMessage) {
+		doc["oauthAccount"] = account
+	})
+}
 
-	data, err := os.ReadFile(path)
-	if err == nil {
-		if json.Unmarshal(data, &doc) != nil {
-			doc = make(map[string]json.RawMessage)
-		}
-	} else {
-		doc = make(map[string]json.RawMessage)
+// writeAPIKey stores an API key as Claude's primaryApiKey and removes the
+// OAuth login, so Claude isn't left choosing between the two.
+func writeAPIKey(key string) error {
+	keyJSON, err := json.Marshal(key)
+	if err != nil {
+		return err
+	}
+	if err := updateJSONFile(claudeJSONPath(), func(doc map[string]json.RawMessage) {
+		doc["primaryApiKey"] = keyJSON
+		delete(doc, "oauthAccount")
+	}); err != nil {
+		return err
+	}
+	if _, err := os.Stat(credentialsPath()); err != nil {
+		return nil
 	}
+	return updateJSONFile(credentialsPath(), func(doc map[string]json.RawMessage) {
+		delete(doc, "claudeAiOauth")
+	})
+}
+
+func clearAPIKey() error {
+	if _, err := os.Stat(claudeJSONPath()); err != nil {
+		return nil
+	}
+	return updateJSONFile(claudeJSONPath(), func(doc map[string]json.RawMessage) {
+		delete(doc, "primaryApiKey")
+	})
+}
 
-	doc["oauthAccount"] = account
+// uiPrefKeys are the ~/.claude.json settings safe to carry between accounts:
+// purely cosmetic, with nothing tied to the account itself.
+var uiPrefKeys = []string{"theme", "editorMode", "verbose"}
 
-	out, err := json.MarshalIndent(doc, "", "  ")
+// readUIPrefs captures the uiPrefKeys present in Claude's config at path.
+func readUIPrefs(path string) map[string]json.RawMessage {
+	data, err := os.ReadFile(path)
 	if err != nil {
-		return err
+		return nil
 	}
-	return writeSecure(path, out)
+	var doc map[string]json.RawMessage
+	if json.Unmarshal(data, &doc) != nil {
+		return nil
+	}
+	prefs := make(map[string]json.RawMessage)
+	for _, key := range uiPrefKeys {
+		if v, ok := doc[key]; ok {
+			prefs[key] = v
+		}
+	}
+	if len(prefs) == 0 {
+		return nil
+	}
+	return prefs
 }
 
-func clearAuth() error {
-	credsPath := credentialsPath()
-	if _, err := os.Stat(credsPath); err == nil {
-		data, err := os.ReadFile(credsPath)
-		if err != nil {
-			return err
-		}
-		var doc map[string]json.RawMessage
-		if json.Unmarshal(data, &doc) != nil {
-			doc = make(map[string]json.RawMessage)
-		}
-		delete(doc, "claudeAiOauth")
-		out, err := json.MarshalIndent(doc, "", "  ")
-		if err != nil {
-			return err
+func writeUIPrefs(prefs map[string]json.RawMessage) error {
+	if len(prefs) == 0 {
+		return nil
+	}
+	return updateJSONFile(claudeJSONPath(), func(doc map[string]json.RawMessage) {
+		for key, v := range prefs {
+			doc[key] = v
 		}
-		if err := writeSecure(credsPath, out); err != nil {
+	})
+}
+
+func clearAuth() error {
+	if _, err := os.Stat(credentialsPath()); err == nil {
+		if err := updateJSONFile(credentialsPath(), func(doc map[string]json.RawMessage) {
+			delete(doc, "claudeAiOauth")
+		}); err != nil {
 			return err
 		}
 	}
 
-	claudePath := claudeJSONPath()
-	if _, err := os.Stat(claudePath); err == nil {
-		data, err := os.ReadFile(claudePath)
-		if err != nil {
-			return err
-		}
-		var doc map[string]json.RawMessage
-		if json.Unmarshal(data, &doc) != nil {
-			doc = make(map[string]json.RawMessage)
-		}
-		delete(doc, "oauthAccount")
-		delete(doc, "primaryApiKey")
-		out, err := json.MarshalIndent(doc, "", "  ")
-		if err != nil {
-			return err
-		}
-		if err := writeSecure(claudePath, out); err != nil {
+	if _, err := os.Stat(claudeJSONPath()); err == nil {
+		if err := updateJSONFile(claudeJSONPath(), func(doc map[string]json.RawMessage) {
+			delete(doc, "oauthAccount")
+			delete(doc, "primaryApiKey")
+		}); err != nil {
 			return err
 		}
 	}
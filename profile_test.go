@@ -0,0 +1,193 @@
+package main
+
+import (
+	"errors"
+	"fmt"
+	"os"
+	"path/filepath"
+	"slices"
+	"sync"
+	"testing"
+	"time"
+)
+
+// tempFilesIn lists the temp files writeSecure may have left in dir.
+func tempFilesIn(t *testing.T, dir string) []string {
+	t.Helper()
+	entries, err := os.ReadDir(dir)
+	if err != nil {
+		t.Fatal(err)
+	}
+	var temps []string
+	for _, e := range entries {
+		if isTempFile(e.Name()) {
+			temps = append(temps, e.Name())
+		}
+	}
+	return temps
+}
+
+func TestWriteSecureReplacesAtomically(t *testing.T) {
+	dir := t.TempDir()
+	path := filepath.Join(dir, "work.json")
+	if err := os.WriteFile(path, []byte("old"), 0o644); err != nil {
+		t.Fatal(err)
+	}
+
+	if err := writeSecure(path, []byte("new")); err != nil {
+		t.Fatal(err)
+	}
+	if data, _ := os.ReadFile(path); string(data) != "new" {
+		t.Errorf("file holds %q after writeSecure, want new", data)
+	}
+	if info, err := os.Stat(path); err != nil {
+		t.Error(err)
+	} else if info.Mode().Perm() != 0o600 {
+		t.Errorf("file mode after writeSecure = %v, want 0600", info.Mode().Perm())
+	}
+	if temps := tempFilesIn(t, dir); len(temps) != 0 {
+		t.Errorf("writeSecure left temp files behind: %v", temps)
+	}
+}
+
+func TestWriteSecureKeepsSymlink(t *testing.T) {
+	dir := t.TempDir()
+	target := filepath.Join(dir, "real.json")
+	link := filepath.Join(dir, "link.json")
+	if err := os.WriteFile(target, []byte("old"), 0o600); err != nil {
+		t.Fatal(err)
+	}
+	if err := os.Symlink(target, link); err != nil {
+		t.Fatal(err)
+	}
+
+	if err := writeSecure(link, []byte("new")); err != nil {
+		t.Fatal(err)
+	}
+	if info, err := os.Lstat(link); err != nil || info.Mode()&os.ModeSymlink == 0 {
+		t.Error("writeSecure replaced the symlink instead of the file it points at")
+	}
+	if data, _ := os.ReadFile(target); string(data) != "new" {
+		t.Errorf("symlink target holds %q after writeSecure, want new", data)
+	}
+}
+
+func TestWriteSecureRemovesTempFileOnFailure(t *testing.T) {
+	dir := t.TempDir()
+	// A non-empty directory in the way makes the rename fail after the temp
+	// file has been written
+	path := filepath.Join(dir, "work.json")
+	if err := os.MkdirAll(filepath.Join(path, "inside"), 0o700); err != nil {
+		t.Fatal(err)
+	}
+
+	if err := writeSecure(path, []byte("new")); err == nil {
+		t.Fatal("writeSecure over a directory succeeded")
+	}
+	if temps := tempFilesIn(t, dir); len(temps) != 0 {
+		t.Errorf("a failed writeSecure left temp files behind: %v", temps)
+	}
+}
+
+func TestIsTempFile(t *testing.T) {
+	for name, want := range map[string]bool{
+		".work.json.4242.tmp":  true,
+		".state.json.1.tmp":    true,
+		"work.json":            false,
+		".work.json.tmp":       false,
+		".work.json.42a.tmp":   false,
+		".notes.txt.4242.tmp":  false,
+		"work.json.4242.tmp":   false,
+		".work.json.4242.tmp~": false,
+	} {
+		if got := isTempFile(name); got != want {
+			t.Errorf("isTempFile(%q) = %v, want %v", name, got, want)
+		}
+	}
+}
+
+func TestOrphanedTempFilesSkipsFreshOnes(t *testing.T) {
+	e := newTestEnv(t)
+	stale := filepath.Join(e.dataDir, "profiles", ".work.json.4242.tmp")
+	fresh := filepath.Join(e.dataDir, "profiles", ".personal.json.4243.tmp")
+	e.writeFile(stale, []byte("{}"))
+	e.writeFile(fresh, []byte("{}"))
+	old := time.Now().Add(-2 * staleTempAge)
+	if err := os.Chtimes(stale, old, old); err != nil {
+		t.Fatal(err)
+	}
+
+	if got := orphanedTempFiles(); !slices.Equal(got, []string{stale}) {
+		t.Errorf("orphanedTempFiles() = %v, want only the stale %s", got, stale)
+	}
+
+	// Other checks may find nothing to work with here, so only the files count
+	e.run("", "doctor", "--fix")
+	if _, err := os.Stat(stale); !os.IsNotExist(err) {
+		t.Errorf("doctor --fix left the stale temp file (stat: %v)", err)
+	}
+	if _, err := os.Stat(fresh); err != nil {
+		t.Errorf("doctor --fix removed a temp file another run may be writing: %v", err)
+	}
+}
+
+func TestConcurrentWritesOfOneFile(t *testing.T) {
+	dir := t.TempDir()
+	path := filepath.Join(dir, "state.json")
+	var wg sync.WaitGroup
+	errs := make(chan error, 20)
+	for i := range 20 {
+		wg.Add(1)
+		go func() {
+			defer wg.Done()
+			errs <- writeSecure(path, []byte(fmt.Sprintf(`{"writer":%d}`, i)))
+		}()
+	}
+	wg.Wait()
+	close(errs)
+	for err := range errs {
+		if err != nil {
+			t.Errorf("concurrent writeSecure: %v", err)
+		}
+	}
+	if temps := tempFilesIn(t, dir); len(temps) != 0 {
+		t.Errorf("concurrent writes left temp files behind: %v", temps)
+	}
+}
+
+func TestConcurrentStateUpdatesAllLand(t *testing.T) {
+	newTestEnv(t)
+	var wg sync.WaitGroup
+	for i := range 20 {
+		wg.Add(1)
+		go func() {
+			defer wg.Done()
+			name := fmt.Sprintf("profile-%d", i)
+			err := updateState(func(state *State) bool {
+				state.KeyringProfiles = append(state.KeyringProfiles, name)
+				return true
+			})
+			if err != nil {
+				t.Error(err)
+			}
+		}()
+	}
+	wg.Wait()
+
+	if got := loadState().KeyringProfiles; len(got) != 20 {
+		t.Errorf("state lists %d of the 20 profiles added concurrently: %v", len(got), got)
+	}
+}
+
+func TestValidateProfileName(t *testing.T) {
+	for _, name := range []string{"work", "work-2", "Work Max", "ünïcödé"} {
+		if err := validateProfileName(name); err != nil {
+			t.Errorf("validateProfileName(%q) = %v, want it accepted", name, err)
+		}
+	}
+	for _, name := range []string{"", ".", "..", "../etc", "a/b", `a\b`, "evil\x1b[2J", "nul\x00", "tab\tname", "line\n"} {
+		if err := validateProfileName(name); !errors.Is(err, errInvalidName) {
+			t.Errorf("validateProfileName(%q) = %v, want errInvalidName", name, err)
+		}
+	}
+}
@@ -0,0 +1,155 @@
+package main
+
+import (
+	"fmt"
+	"os"
+	"path/filepath"
+	"sort"
+	"strconv"
+	"strings"
+)
+
+// Removed profiles are moved into a trash directory rather than deleted,
+// since a profile may hold the only copy of a single-use refresh token.
+const (
+	trashMaxEntries = 50
+	trashMaxAgeMs   = 30 * 24 * 60 * 60 * 1000
+)
+
+type trashEntry struct {
+	Name      string
+	RemovedAt uint64
+	File      string
+}
+
+func trashDir() string {
+	return filepath.Join(configDir(), "trash")
+}
+
+// parseTrashFile splits a "<name>-<unix ms>.json" trash filename.
+func parseTrashFile(file string) (trashEntry, bool) {
+	base, ok := strings.CutSuffix(file, ".json")
+	if !ok {
+		return trashEntry{}, false
+	}
+	i := strings.LastIndex(base, "-")
+	if i <= 0 {
+		return trashEntry{}, false
+	}
+	ts, err := strconv.ParseUint(base[i+1:], 10, 64)
+	if err != nil {
+		return trashEntry{}, false
+	}
+	return trashEntry{Name: base[:i], RemovedAt: ts, File: file}, true
+}
+
+// listTrash returns the trashed profiles, newest first.
+func listTrash() ([]trashEntry, error) {
+	entries, err := os.ReadDir(trashDir())
+	if err != nil {
+		if os.IsNotExist(err) {
+			return nil, nil
+		}
+		return nil, err
+	}
+	var trashed []trashEntry
+	for _, e := range entries {
+		if e.IsDir() {
+			continue
+		}
+		if t, ok := parseTrashFile(e.Name()); ok {
+			trashed = append(trashed, t)
+		}
+	}
+	sort.Slice(trashed, func(i, j int) bool {
+		return trashed[i].RemovedAt > trashed[j].RemovedAt
+	})
+	return trashed, nil
+}
+
+func moveToTrash(name string) error {
+	dir := trashDir()
+	if err := ensureDir(dir); err != nil {
+		return err
+	}
+	dest := filepath.Join(dir, fmt.Sprintf("%s-%d.json", name, nowMs()))
+	if inKeyring(name) {
+		// Keyring profiles are trashed as files, so restore brings them back
+		// on disk until they are next saved
+		data, err := readProfileData(name)
+		if err != nil {
+			return err
+		}
+		if err := writeSecure(dest, data); err != nil {
+			return err
+		}
+		if err := deleteProfileData(name); err != nil {
+			return err
+		}
+	} else if err := os.Rename(profilePath(name), dest); err != nil {
+		return explainWriteError(profilesDir(), err)
+	}
+	pruneTrash()
+	return nil
+}
+
+// pruneTrash drops the oldest entries beyond the size and age caps.
+func pruneTrash() {
+	trashed, err := listTrash()
+	if err != nil {
+		return
+	}
+	now := nowMs()
+	for i, t := range trashed {
+		if i >= trashMaxEntries || t.RemovedAt+trashMaxAgeMs < now {
+			os.Remove(filepath.Join(trashDir(), t.File))
+		}
+	}
+}
+
+// restoreFromTrash moves the most recently trashed copy of a profile back.
+func restoreFromTrash(name string) error {
+	if err := validateProfileName(name); err != nil {
+		return err
+	}
+	if err := requireWritable("trash restore"); err != nil {
+		return err
+	}
+	if profileStored(name) {
+		return fmt.Errorf("profile '%s' %w (use 'remove' first)", name, errExists)
+	}
+
+	trashed, err := listTrash()
+	if err != nil {
+		return err
+	}
+	for _, t := range trashed {
+		if t.Name != name {
+			continue
+		}
+		if err := ensureDir(profilesDir()); err != nil {
+			return err
+		}
+		if err := os.Rename(filepath.Join(trashDir(), t.File), profilePath(name)); err != nil {
+			return explainWriteError(profilesDir(), err)
+		}
+		return nil
+	}
+	return fmt.Errorf("no trashed profile named '%s'", name)
+}
+
+func emptyTrash() (int, error) {
+	if err := requireWritable("trash empty"); err != nil {
+		return 0, err
+	}
+	trashed, err := listTrash()
+	if err != nil {
+		return 0, err
+	}
+	for i, t := range trashed {
+		if err := os.Remove(filepath.Join(trashDir(), t.File)); err != nil {
+			return i, err
+		}
+	}
+	return len(trashed), nil
+}
@@ -0,0 +1,63 @@
+package main
+
+import (
+	"os"
+	"path/filepath"
+	"strings"
+	"testing"
+)
+
+func TestRemoveThenRestore(t *testing.T) {
+	e := newTestEnv(t)
+	e.login(testCreds("work-access", "work-refresh"), testAccount("me@acme.com", "66666666-6666-6666-6666-666666666666"))
+	e.mustRun("import", "work")
+
+	e.mustRun("remove", "work")
+	if e.hasProfile("work") {
+		t.Fatal("work is still stored after remove")
+	}
+	if out := e.mustRun("trash", "list").stdout; !strings.Contains(out, "work") {
+		t.Errorf("trash list doesn't show the removed profile:\n%s", out)
+	}
+
+	e.mustRun("trash", "restore", "work")
+	if got := e.readProfile("work").Credentials.RefreshToken; got != "work-refresh" {
+		t.Errorf("restored profile's refresh token = %q, want work-refresh", got)
+	}
+	if r := e.run("", "trash", "restore", "work"); r.code == 0 {
+		t.Error("restoring over a stored profile succeeded")
+	}
+}
+
+func TestTrashEmpty(t *testing.T) {
+	e := newTestEnv(t)
+	for _, name := range []string{"work", "personal"} {
+		e.writeProfile(name, &Profile{Type: "oauth", Credentials: testCreds(name+"-access", name+"-refresh")})
+		e.mustRun("remove", name)
+	}
+
+	e.mustRun("trash", "empty")
+	entries, err := os.ReadDir(filepath.Join(e.dataDir, "trash"))
+	if err != nil {
+		t.Fatal(err)
+	}
+	if len(entries) != 0 {
+		t.Errorf("trash empty left %d files behind", len(entries))
+	}
+	if r := e.run("", "trash", "restore", "work"); r.code == 0 {
+		t.Error("restored a profile from an emptied trash")
+	}
+}
+
+func TestTrashListEscapesNames(t *testing.T) {
+	e := newTestEnv(t)
+	e.writeFile(filepath.Join(e.dataDir, "trash", "evil\x1b[2J-1700000000000.json"), []byte("{}"))
+
+	out := e.mustRun("trash", "list").stdout
+	if strings.Contains(out, "\x1b") {
+		t.Errorf("trash list printed a raw escape sequence:\n%q", out)
+	}
+	if !strings.Contains(out, `evil\x1b[2J`) {
+		t.Errorf("trash list doesn't show the escaped name:\n%q", out)
+	}
+}
@@ -0,0 +1,271 @@
+package main
+
+import (
+	"fmt"
+	"io"
+	"math"
+	"os"
+	"regexp"
+	"strconv"
+	"strings"
+	"sync"
+	"time"
+	"unicode"
+)
+
+// quiet and verbose are set by the -q/--quiet and -v/--verbose global flags.
+var (
+	quiet   bool
+	verbose bool
+)
+
+// logf prints a status message on stderr unless --quiet was given. Warnings,
+// errors and prompts are printed directly, so quiet never hides them.
+func logf(format string, a ...any) {
+	if !quiet {
+		fmt.Fprintf(os.Stderr, format, a...)
+	}
+}
+
+// debugf prints detail that only --verbose asks for.
+func debugf(format string, a ...any) {
+	if verbose {
+		fmt.Fprintf(os.Stderr, format, a...)
+	}
+}
+
+// isUUID reports whether s has the 8-4-4-4-12 hex digit form of a UUID.
+func isUUID(s string) bool {
+	if len(s) != 36 {
+		return false
+	}
+	for i := 0; i < len(s); i++ {
+		switch i {
+		case 8, 13, 18, 23:
+			if s[i] != '-' {
+				return false
+			}
+		default:
+			if !strings.ContainsRune("0123456789abcdefABCDEF", rune(s[i])) {
+				return false
+			}
+		}
+	}
+	return true
+}
+
+// durationUnits maps the suffixes accepted by parseDuration to milliseconds.
+var durationUnits = map[byte]uint64{
+	's': 1000,
+	'm': 60 * 1000,
+	'h': 60 * 60 * 1000,
+	'd': 24 * 60 * 60 * 1000,
+	'w': 7 * 24 * 60 * 60 * 1000,
+}
+
+// maxDurationMs is the longest duration, about 292 years, that still fits in
+// a time.Duration once converted, so callers can multiply by
+// time.Millisecond without overflowing into a negative or tiny timeout.
+const maxDurationMs = uint64(math.MaxInt64 / int64(time.Millisecond))
+
+// parseDuration parses a human duration such as "30s", "15m", "2h", "7d",
+// "1w" or a combination like "1h30m" into milliseconds. Every flag that takes
+// a duration goes through it so they all accept the same syntax. Durations
+// longer than maxDurationMs are rejected.
+func parseDuration(s string) (uint64, error) {
+	if s == "" {
+		return 0, fmt.Errorf("invalid duration: empty")
+	}
+	var total uint64
+	rest := s
+	for rest != "" {
+		i := 0
+		for i < len(rest) && rest[i] >= '0' && rest[i] <= '9' {
+			i++
+		}
+		if i == 0 {
+			return 0, fmt.Errorf("invalid duration '%s': expected a number at '%s'", s, rest)
+		}
+		if i == len(rest) {
+			return 0, fmt.Errorf("invalid duration '%s': missing unit after %s (use s, m, h, d or w)", s, rest)
+		}
+		unit, ok := durationUnits[rest[i]]
+		if !ok {
+			return 0, fmt.Errorf("invalid duration '%s': unknown unit at '%s' (use s, m, h, d or w)", s, rest[i:])
+		}
+		n, err := strconv.ParseUint(rest[:i], 10, 64)
+		if err != nil || n > maxDurationMs/unit || total+n*unit > maxDurationMs {
+			return 0, fmt.Errorf("invalid duration '%s': too large", s)
+		}
+		total += n * unit
+		rest = rest[i+1:]
+	}
+	return total, nil
+}
+
+// formatDuration renders a span of milliseconds in its largest whole unit,
+// e.g. "45s", "12m", "3h" or "2d".
+func formatDuration(ms uint64) string {
+	d := time.Duration(ms) * time.Millisecond
+	switch {
+	case d < time.Minute:
+		return fmt.Sprintf("%ds", int(d.Seconds()))
+	case d < time.Hour:
+		return fmt.Sprintf("%dm", int(d.Minutes()))
+	case d < 48*time.Hour:
+		return fmt.Sprintf("%dh", int(d.Hours()))
+	default:
+		return fmt.Sprintf("%dd", int(d.Hours()/24))
+	}
+}
+
+// envOr returns the environment variable name, or def when it is unset or
+// empty.
+func envOr(name, def string) string {
+	if v := os.Getenv(name); v != "" {
+		return v
+	}
+	return def
+}
+
+// envDurationMs reads a duration in milliseconds from an environment
+// variable given in seconds or as a duration such as "2m". It returns def
+// when the variable is unset, and reports and ignores an invalid value.
+func envDurationMs(name string, def uint64) uint64 {
+	v := os.Getenv(name)
+	if v == "" {
+		return def
+	}
+	if secs, err := strconv.ParseUint(v, 10, 64); err == nil {
+		if secs > maxDurationMs/1000 {
+			fmt.Fprintf(os.Stderr, "Warning: ignoring %s: %s seconds is too large\n", name, v)
+			return def
+		}
+		return secs * 1000
+	}
+	ms, err := parseDuration(v)
+	if err != nil {
+		fmt.Fprintf(os.Stderr, "Warning: ignoring %s: %v\n", name, err)
+		return def
+	}
+	return ms
+}
+
+// formatTimestamp renders a Unix millisecond timestamp in UTC alongside how
+// far it is from now, e.g. "2025-06-01 12:00 UTC (in 3h)".
+func formatTimestamp(ms uint64) string {
+	abs := time.UnixMilli(int64(ms)).UTC().Format("2006-01-02 15:04 UTC")
+	now := nowMs()
+	if ms >= now {
+		return fmt.Sprintf("%s (in %s)", abs, formatDuration(ms-now))
+	}
+	return fmt.Sprintf("%s (%s ago)", abs, formatDuration(now-ms))
+}
+
+// isTerminal reports whether f is an interactive terminal rather than a
+// pipe or file.
+func isTerminal(f *os.File) bool {
+	info, err := f.Stat()
+	return err == nil && info.Mode()&os.ModeCharDevice != 0
+}
+
+// ansiEscape matches the colour and style codes claude-switch prints.
+var ansiEscape = regexp.MustCompile("\033\\[[0-9;]*m")
+
+// stripANSI removes colour and style codes from s.
+func stripANSI(s string) string {
+	return ansiEscape.ReplaceAllString(s, "")
+}
+
+// stdoutColour reports whether stdout may be coloured: only a terminal, and
+// only while NO_COLOR is unset.
+func stdoutColour() bool {
+	return os.Getenv("NO_COLOR") == "" && isTerminal(os.Stdout)
+}
+
+// colourWriter returns w unchanged when colour is on, and otherwise a writer
+// that strips colour codes before passing text on to w. Put it in front of a
+// tabwriter, so the columns are measured without the codes.
+func colourWriter(w io.Writer, colour bool) io.Writer {
+	if colour {
+		return w
+	}
+	return ansiStripper{w}
+}
+
+type ansiStripper struct {
+	w io.Writer
+}
+
+func (s ansiStripper) Write(p []byte) (int, error) {
+	if _, err := io.WriteString(s.w, stripANSI(string(p))); err != nil {
+		return 0, err
+	}
+	return len(p), nil
+}
+
+// maskSecret shows just enough of a token or key to recognise it.
+func maskSecret(secret string) string {
+	if len(secret) <= 8 {
+		return "…"
+	}
+	return secret[:8] + "…"
+}
+
+// defaultJobs is how many token requests batch commands make at once unless
+// told otherwise with -j.
+const defaultJobs = 4
+
+// runParallel calls fn for each index in [0, n), running at most jobs calls
+// at a time. With jobs == 1 the calls run in order.
+func runParallel(n, jobs int, fn func(i int)) {
+	if jobs <= 1 {
+		for i := 0; i < n; i++ {
+			fn(i)
+		}
+		return
+	}
+	var wg sync.WaitGroup
+	sem := make(chan struct{}, jobs)
+	for i := 0; i < n; i++ {
+		wg.Add(1)
+		sem <- struct{}{}
+		go func() {
+			defer wg.Done()
+			defer func() { <-sem }()
+			fn(i)
+		}()
+	}
+	wg.Wait()
+}
+
+// truncate shortens s to at most width characters, marking the cut with an
+// ellipsis. A width of 0 means no limit.
+func truncate(s string, width int) string {
+	runes := []rune(s)
+	if width <= 0 || len(runes) <= width {
+		return s
+	}
+	if width == 1 {
+		return "…"
+	}
+	return string(runes[:width-1]) + "…"
+}
+
+// escapeControl replaces control characters in s with their Go escapes, so
+// text from a crafted filename can't emit terminal escape sequences.
+func escapeControl(s string) string {
+	if !strings.ContainsFunc(s, unicode.IsControl) {
+		return s
+	}
+	var b strings.Builder
+	for _, r := range s {
+		if unicode.IsControl(r) {
+			q := strconv.QuoteRune(r)
+			b.WriteString(q[1 : len(q)-1])
+			continue
+		}
+		b.WriteRune(r)
+	}
+	return b.String()
+}
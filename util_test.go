@@ -0,0 +1,62 @@
+package main
+
+import "testing"
+
+func TestParseDuration(t *testing.T) {
+	tests := []struct {
+		in   string
+		want uint64
+	}{
+		{"30s", 30 * 1000},
+		{"15m", 15 * 60 * 1000},
+		{"1h30m", 90 * 60 * 1000},
+		{"2w", 14 * 24 * 60 * 60 * 1000},
+		{"0s", 0},
+		{"9223372036s", 9223372036 * 1000}, // the longest whole number of seconds allowed
+	}
+	for _, tt := range tests {
+		got, err := parseDuration(tt.in)
+		if err != nil || got != tt.want {
+			t.Errorf("parseDuration(%q) = %d, %v; want %d", tt.in, got, err, tt.want)
+		}
+	}
+}
+
+func TestParseDurationRejects(t *testing.T) {
+	for _, in := range []string{
+		"",                        // empty
+		"30",                      // missing unit
+		"1h30",                    // missing unit after a valid part
+		"h",                       // missing number
+		"5y",                      // unknown unit
+		"-5m",                     // sign
+		"99999999999999999999s",   // doesn't fit in uint64
+		"30000000000000000w",      // overflows once converted to ms
+		"18446744073709551s1000s", // overflows when parts are added
+		"9223372037s",             // fits in uint64 ms but not in a time.Duration
+		"106751d1d",               // the same, once the parts are added
+	} {
+		if got, err := parseDuration(in); err == nil {
+			t.Errorf("parseDuration(%q) = %d, want an error", in, got)
+		}
+	}
+}
+
+func TestTruncate(t *testing.T) {
+	tests := []struct {
+		in    string
+		width int
+		want  string
+	}{
+		{"me@example.com", 0, "me@example.com"},
+		{"me@example.com", 14, "me@example.com"},
+		{"me@example.com", 6, "me@ex…"},
+		{"me@example.com", 1, "…"},
+		{"ünïcödé", 4, "ünï…"},
+	}
+	for _, tt := range tests {
+		if got := truncate(tt.in, tt.width); got != tt.want {
+			t.Errorf("truncate(%q, %d) = %q, want %q", tt.in, tt.width, got, tt.want)
+		}
+	}
+}
@@ -0,0 +1,80 @@
+package main
+
+import (
+	"fmt"
+	"os/exec"
+	"regexp"
+	"strconv"
+	"strings"
+)
+
+// The Claude Code versions whose config layout claude-switch is known to
+// work with: at least minClaudeVersion and below maxClaudeVersion.
+const (
+	minClaudeVersion = "1.0.0"
+	maxClaudeVersion = "3.0.0"
+)
+
+var versionPattern = regexp.MustCompile(`\d+\.\d+\.\d+`)
+
+var (
+	cachedClaudeVersion string
+	cachedVersionErr    error
+	versionChecked      bool
+)
+
+// claudeVersion runs `claude --version` once per invocation and returns the
+// version number it reports.
+func claudeVersion() (string, error) {
+	if versionChecked {
+		return cachedClaudeVersion, cachedVersionErr
+	}
+	versionChecked = true
+
+	out, err := exec.Command(claudeBinary(), "--version").Output()
+	if err != nil {
+		cachedVersionErr = fmt.Errorf("running %s --version: %w", claudeBinary(), err)
+		return "", cachedVersionErr
+	}
+	cachedClaudeVersion = versionPattern.FindString(string(out))
+	if cachedClaudeVersion == "" {
+		cachedVersionErr = fmt.Errorf("couldn't find a version number in %q", strings.TrimSpace(string(out)))
+	}
+	return cachedClaudeVersion, cachedVersionErr
+}
+
+// claudeVersionWarning describes why the installed Claude Code may not be
+// compatible, or returns "" when it is in the known-good range or can't be
+// detected.
+func claudeVersionWarning() string {
+	v, err := claudeVersion()
+	if err != nil {
+		return ""
+	}
+	if compareVersions(v, minClaudeVersion) < 0 || compareVersions(v, maxClaudeVersion) >= 0 {
+		return fmt.Sprintf("Claude Code %s is outside the versions claude-switch is known to work with (%s up to %s); its config layout may differ", v, minClaudeVersion, maxClaudeVersion)
+	}
+	return ""
+}
+
+// compareVersions compares dotted version numbers numerically, returning
+// -1, 0 or 1.
+func compareVersions(a, b string) int {
+	as, bs := strings.Split(a, "."), strings.Split(b, ".")
+	for i := 0; i < len(as) || i < len(bs); i++ {
+		var x, y int
+		if i < len(as) {
+			x, _ = strconv.Atoi(as[i])
+		}
+		if i < len(bs) {
+			y, _ = strconv.Atoi(bs[i])
+		}
+		if x != y {
+			if x < y {
+				return -1
+			}
+			return 1
+		}
+	}
+	return 0
+}
@@ -0,0 +1,46 @@
+package main
+
+import (
+	"os"
+	"path/filepath"
+	"strings"
+	"testing"
+)
+
+func TestCompareVersions(t *testing.T) {
+	tests := []struct {
+		a, b string
+		want int
+	}{
+		{"2.1.0", "2.1.0", 0},
+		{"2.1", "2.1.0", 0},
+		{"2.1.0", "3.0.0", -1},
+		{"2.10.0", "2.9.0", 1},
+		{"0.9.9", "1.0.0", -1},
+	}
+	for _, tt := range tests {
+		if got := compareVersions(tt.a, tt.b); got != tt.want {
+			t.Errorf("compareVersions(%q, %q) = %d, want %d", tt.a, tt.b, got, tt.want)
+		}
+	}
+}
+
+func TestClaudeVersionOutsideKnownRange(t *testing.T) {
+	e := newTestEnv(t)
+	e.stubLogin(testCreds("work-access", "work-refresh"), testAccount("me@acme.com", "11111111-1111-1111-1111-111111111111"))
+	const warning = "Claude Code 3.1.0 is outside the versions claude-switch is known to work with"
+
+	if r := e.mustRun("doctor"); strings.Contains(r.stdout, "outside the versions") {
+		t.Errorf("doctor warns about a known-good version:\n%s", r.stdout)
+	}
+	stub := strings.Replace(stubClaude, "2.1.0", "3.1.0", 1)
+	if err := os.WriteFile(filepath.Join(e.root, "claude"), []byte(stub), 0o755); err != nil {
+		t.Fatal(err)
+	}
+	if r := e.mustRun("doctor"); !strings.Contains(r.stdout, "WARN  "+warning) {
+		t.Errorf("doctor doesn't warn about Claude Code 3.1.0:\n%s", r.stdout)
+	}
+	if r := e.mustRun("add", "work"); !strings.Contains(r.stderr, "Warning: "+warning) {
+		t.Errorf("add doesn't warn about Claude Code 3.1.0:\n%s", r.stderr)
+	}
+}